@@ -72,6 +72,10 @@ pub mod asset {
             format!("charpack/{file}")
         }
 
+        pub fn item(icon_id: &str) -> String {
+            format!("item/{icon_id}")
+        }
+
         pub fn gamedata(file_path: &str) -> String {
             format!("gamedata/{file_path}")
         }
@@ -47,10 +47,25 @@ bitflags! {
 }
 
 impl LogLevel {
+    const ATTRIBUTES: [char; 8] = ['D', 'H', 'C', 'S', 'W', 'E', 'N', 'F'];
+
+    /// [`Self::attributes_tag`] 的逆操作，忽略大小写及无法识别的字符（如 `-`）
+    pub fn from_attributes_tag(tag: &str) -> Self {
+        let mut bits = 0;
+        for c in tag.chars() {
+            if let Some(i) = Self::ATTRIBUTES
+                .iter()
+                .position(|x| x.eq_ignore_ascii_case(&c))
+            {
+                bits |= 1 << i;
+            }
+        }
+        Self(bits)
+    }
+
     pub fn attributes_tag(&self) -> String {
-        const ATTRIBUTES: [char; 8] = ['D', 'H', 'C', 'S', 'W', 'E', 'N', 'F'];
         let mut result = String::with_capacity(8);
-        for (i, tag) in ATTRIBUTES.iter().enumerate() {
+        for (i, tag) in Self::ATTRIBUTES.iter().enumerate() {
             result.push(if self.0 & (1 << i) as u32 != 0 {
                 *tag
             } else {
@@ -1,6 +1,67 @@
-use std::io;
+use std::io::{self, Read, Write};
 
 use qbsdiff::{Bsdiff, Bspatch};
+use serde::{Deserialize, Serialize};
+
+///
+/// 增量更新格式，记录在 `index.json` 中，并体现在 patch 文件的扩展名上
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchFormat {
+    Bsdiff,
+    /// zstd 的 patch-from 模式，以旧版本作为 ref prefix 压缩新版本
+    Zstd,
+    /// 类似 rsync 的分块差分，再经 zstd 压缩
+    Chunked,
+}
+
+impl PatchFormat {
+    /// 当前客户端支持的格式，按优先级排序
+    pub const SUPPORTED: [PatchFormat; 3] =
+        [PatchFormat::Zstd, PatchFormat::Chunked, PatchFormat::Bsdiff];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            PatchFormat::Bsdiff => "bspatch",
+            PatchFormat::Zstd => "zstpatch",
+            PatchFormat::Chunked => "chkpatch",
+        }
+    }
+
+    pub fn from_filename(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+        Self::SUPPORTED
+            .into_iter()
+            .find(|x| x.extension() == extension)
+    }
+
+    pub fn diff(self, source: &[u8], target: &[u8]) -> io::Result<bytes::Bytes> {
+        match self {
+            PatchFormat::Bsdiff => bsdiff(source, target),
+            PatchFormat::Zstd => zstd_diff(source, target),
+            PatchFormat::Chunked => chunked::diff(source, target),
+        }
+    }
+
+    pub fn patch(self, source: &[u8], patch: &[u8]) -> io::Result<bytes::Bytes> {
+        match self {
+            PatchFormat::Bsdiff => bspatch(source, patch),
+            PatchFormat::Zstd => zstd_patch(source, patch),
+            PatchFormat::Chunked => chunked::patch(source, patch),
+        }
+    }
+
+    pub fn patch_check_integrity(
+        self,
+        source: &[u8],
+        patch: &[u8],
+        hash: &[u8],
+        hasher: impl digest::Digest,
+    ) -> io::Result<bytes::Bytes> {
+        check_integrity(self.patch(source, patch)?, hash, hasher)
+    }
+}
 
 pub fn bsdiff(source: &[u8], target: &[u8]) -> io::Result<bytes::Bytes> {
     let mut patch = Vec::new();
@@ -19,9 +80,40 @@ pub fn bspatch_check_integrity(
     source: &[u8],
     patch: &[u8],
     hash: &[u8],
+    hasher: impl digest::Digest,
+) -> io::Result<bytes::Bytes> {
+    PatchFormat::Bsdiff.patch_check_integrity(source, patch, hash, hasher)
+}
+
+pub fn zstd_diff(source: &[u8], target: &[u8]) -> io::Result<bytes::Bytes> {
+    let mut encoder =
+        zstd::stream::write::Encoder::with_ref_prefix(Vec::new(), consts::ZSTD_LEVEL, source)?;
+    // 窗口需覆盖整个旧版本，才能引用其中的内容
+    encoder.window_log(zstd_window_log(source.len() + target.len()))?;
+    encoder.long_distance_matching(true)?;
+    encoder.set_pledged_src_size(Some(target.len() as u64))?;
+    encoder.write_all(target)?;
+    Ok(encoder.finish()?.into())
+}
+
+pub fn zstd_patch(source: &[u8], patch: &[u8]) -> io::Result<bytes::Bytes> {
+    let mut decoder =
+        zstd::stream::read::Decoder::with_ref_prefix(io::BufReader::new(patch), source)?;
+    decoder.window_log_max(consts::ZSTD_WINDOW_LOG_MAX)?;
+    let mut target = Vec::new();
+    decoder.read_to_end(&mut target)?;
+    Ok(target.into())
+}
+
+fn zstd_window_log(len: usize) -> u32 {
+    (usize::BITS - len.max(1).leading_zeros()).clamp(10, consts::ZSTD_WINDOW_LOG_MAX)
+}
+
+fn check_integrity(
+    target: bytes::Bytes,
+    hash: &[u8],
     mut hasher: impl digest::Digest,
 ) -> io::Result<bytes::Bytes> {
-    let target = bspatch(source, patch)?;
     hasher.update(&target);
     let actual_hash = hasher.finalize();
     if actual_hash.as_slice() == hash {
@@ -39,13 +131,182 @@ pub fn bspatch_check_integrity(
 }
 
 pub fn bspatch_filename(source_hash: &[u8], target_hash: &[u8]) -> String {
+    patch_filename(source_hash, target_hash, PatchFormat::Bsdiff)
+}
+
+pub fn patch_filename(source_hash: &[u8], target_hash: &[u8], format: PatchFormat) -> String {
     format!(
-        "{}-{}.bspatch",
+        "{}-{}.{}",
         hex::encode(&source_hash[0..source_hash.len().min(consts::PATCH_FILENAME_HASH_BYTES)]),
-        hex::encode(&target_hash[0..target_hash.len().min(consts::PATCH_FILENAME_HASH_BYTES)])
+        hex::encode(&target_hash[0..target_hash.len().min(consts::PATCH_FILENAME_HASH_BYTES)]),
+        format.extension()
     )
 }
 
+///
+/// 分块差分：以固定大小的块索引旧版本，在新版本上滚动查找相同的块，
+/// 生成 `Copy`（引用旧版本）与 `Insert`（新内容）指令序列
+///
+mod chunked {
+    use std::{collections::HashMap, io};
+
+    use super::consts::{CHUNKED_BLOCK_SIZE as BLOCK_SIZE, CHUNKED_MAGIC, ZSTD_LEVEL};
+
+    const OP_COPY: u8 = 0;
+    const OP_INSERT: u8 = 1;
+
+    /// rsync 的弱校验和
+    struct RollingHash {
+        a: u32,
+        b: u32,
+    }
+
+    impl RollingHash {
+        fn new(block: &[u8]) -> Self {
+            let mut a = 0u32;
+            let mut b = 0u32;
+            for (i, x) in block.iter().enumerate() {
+                a = a.wrapping_add(*x as u32);
+                b = b.wrapping_add(((block.len() - i) as u32).wrapping_mul(*x as u32));
+            }
+            Self { a, b }
+        }
+
+        fn roll(&mut self, out: u8, into: u8) {
+            self.a = self.a.wrapping_sub(out as u32).wrapping_add(into as u32);
+            self.b = self
+                .b
+                .wrapping_sub((BLOCK_SIZE as u32).wrapping_mul(out as u32))
+                .wrapping_add(self.a);
+        }
+
+        fn digest(&self) -> u32 {
+            (self.a & 0xffff) | (self.b << 16)
+        }
+    }
+
+    pub fn diff(source: &[u8], target: &[u8]) -> io::Result<bytes::Bytes> {
+        let mut index = HashMap::<u32, Vec<usize>>::new();
+        for offset in (0..source.len().saturating_sub(BLOCK_SIZE - 1)).step_by(BLOCK_SIZE) {
+            index
+                .entry(RollingHash::new(&source[offset..offset + BLOCK_SIZE]).digest())
+                .or_default()
+                .push(offset);
+        }
+
+        let mut ops = Vec::from(CHUNKED_MAGIC);
+        ops.extend_from_slice(&(target.len() as u64).to_le_bytes());
+        let mut literal_start = 0;
+        let mut i = 0;
+        let mut rolling =
+            (target.len() >= BLOCK_SIZE).then(|| RollingHash::new(&target[..BLOCK_SIZE]));
+        while let Some(hash) = rolling.as_mut() {
+            let block = &target[i..i + BLOCK_SIZE];
+            let matched = index.get(&hash.digest()).and_then(|offsets| {
+                offsets
+                    .iter()
+                    .find(|&&offset| &source[offset..offset + BLOCK_SIZE] == block)
+            });
+            if let Some(&offset) = matched {
+                let mut len = BLOCK_SIZE;
+                while i + len < target.len()
+                    && offset + len < source.len()
+                    && target[i + len] == source[offset + len]
+                {
+                    len += 1;
+                }
+                push_insert(&mut ops, &target[literal_start..i]);
+                push_copy(&mut ops, offset, len);
+                i += len;
+                literal_start = i;
+                rolling = (i + BLOCK_SIZE <= target.len())
+                    .then(|| RollingHash::new(&target[i..i + BLOCK_SIZE]));
+            } else if i + BLOCK_SIZE < target.len() {
+                hash.roll(target[i], target[i + BLOCK_SIZE]);
+                i += 1;
+            } else {
+                rolling = None;
+            }
+        }
+        push_insert(&mut ops, &target[literal_start..]);
+
+        Ok(zstd::bulk::compress(&ops, ZSTD_LEVEL)?.into())
+    }
+
+    pub fn patch(source: &[u8], patch: &[u8]) -> io::Result<bytes::Bytes> {
+        let ops = zstd::stream::decode_all(patch)?;
+        let mut reader = Reader(&ops);
+        if reader.take(CHUNKED_MAGIC.len())? != CHUNKED_MAGIC {
+            return Err(invalid_data("invalid chunked patch header"));
+        }
+        let target_len = reader.u64()? as usize;
+        let mut target = Vec::with_capacity(target_len);
+        while !reader.0.is_empty() {
+            match reader.take(1)?[0] {
+                OP_COPY => {
+                    let offset = reader.u64()? as usize;
+                    let len = reader.u64()? as usize;
+                    let range = offset
+                        .checked_add(len)
+                        .filter(|end| *end <= source.len())
+                        .map(|end| offset..end)
+                        .ok_or(invalid_data("copy out of source range"))?;
+                    target.extend_from_slice(&source[range]);
+                }
+                OP_INSERT => {
+                    let len = reader.u64()? as usize;
+                    target.extend_from_slice(reader.take(len)?);
+                }
+                op => return Err(invalid_data(&format!("unknown chunked patch op {op}"))),
+            }
+        }
+        if target.len() != target_len {
+            return Err(invalid_data("chunked patch target length mismatch"));
+        }
+        Ok(target.into())
+    }
+
+    fn push_copy(ops: &mut Vec<u8>, offset: usize, len: usize) {
+        ops.push(OP_COPY);
+        ops.extend_from_slice(&(offset as u64).to_le_bytes());
+        ops.extend_from_slice(&(len as u64).to_le_bytes());
+    }
+
+    fn push_insert(ops: &mut Vec<u8>, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        ops.push(OP_INSERT);
+        ops.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        ops.extend_from_slice(bytes);
+    }
+
+    fn invalid_data(msg: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    struct Reader<'a>(&'a [u8]);
+
+    impl<'a> Reader<'a> {
+        fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+            if self.0.len() < len {
+                return Err(invalid_data("unexpected end of chunked patch"));
+            }
+            let (head, tail) = self.0.split_at(len);
+            self.0 = tail;
+            Ok(head)
+        }
+
+        fn u64(&mut self) -> io::Result<u64> {
+            Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+        }
+    }
+}
+
 pub mod consts {
     pub const PATCH_FILENAME_HASH_BYTES: usize = 16;
+    pub const ZSTD_LEVEL: i32 = 19;
+    pub const ZSTD_WINDOW_LOG_MAX: u32 = 30;
+    pub const CHUNKED_BLOCK_SIZE: usize = 2048;
+    pub const CHUNKED_MAGIC: &[u8] = b"ARKCHNK1";
 }
@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, bail};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+
+///
+/// 公钥轮换声明：由一个已信任的公钥（`signer`）签名，添加新公钥 `key`，并可选地吊销 `revokes`。
+///
+/// 公钥均为 hex 编码的 ed25519 公钥（32 字节）。
+///
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct KeyRotation {
+    pub key: String,
+    #[serde(default)]
+    pub revokes: Option<String>,
+    pub signer: String,
+    /// hex 编码的签名，签名内容见 [`KeyRotation::message`]
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct KeyRotationListV1 {
+    pub rotations: Vec<KeyRotation>,
+}
+
+impl KeyRotation {
+    pub fn sign(
+        signing_key: &SigningKey,
+        key: &VerifyingKey,
+        revokes: Option<&VerifyingKey>,
+    ) -> Self {
+        let key = hex::encode(key.as_bytes());
+        let revokes = revokes.map(|x| hex::encode(x.as_bytes()));
+        let signature = signing_key.sign(&Self::message_of(&key, revokes.as_deref()));
+        Self {
+            key,
+            revokes,
+            signer: hex::encode(signing_key.verifying_key().as_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        }
+    }
+
+    /// 被签名的内容
+    pub fn message(&self) -> Vec<u8> {
+        Self::message_of(&self.key, self.revokes.as_deref())
+    }
+
+    fn message_of(key: &str, revokes: Option<&str>) -> Vec<u8> {
+        format!(
+            "{}\n{}\n{}",
+            consts::KEY_ROTATION_MESSAGE_PREFIX,
+            key,
+            revokes.unwrap_or_default()
+        )
+        .into_bytes()
+    }
+}
+
+///
+/// 可信公钥集合，以编译时内置的公钥为根，通过 [`KeyRotation`] 扩展或吊销。
+///
+#[derive(Debug, Clone)]
+pub struct TrustedKeys {
+    keys: Vec<VerifyingKey>,
+    /// 根公钥只能由自身吊销，防止泄露的轮换公钥吊销根公钥
+    roots: Vec<VerifyingKey>,
+    revoked: HashSet<[u8; 32]>,
+}
+
+impl TrustedKeys {
+    pub fn new(keys: impl IntoIterator<Item = VerifyingKey>) -> Self {
+        let keys: Vec<_> = keys.into_iter().collect();
+        Self {
+            roots: keys.clone(),
+            keys,
+            revoked: HashSet::new(),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        Self::new([*crate::release_public_key()])
+    }
+
+    pub fn keys(&self) -> &[VerifyingKey] {
+        &self.keys
+    }
+
+    pub fn is_trusted(&self, key: &VerifyingKey) -> bool {
+        self.keys.contains(key)
+    }
+
+    ///
+    /// 依次应用轮换声明，直到没有可以应用的声明为止（声明之间可以互相依赖，顺序不限）。
+    ///
+    /// 吊销同样与顺序无关：先收集所有可应用声明中的吊销，再重新应用声明，
+    /// 此时由已吊销公钥签名的声明将被拒绝（吊销签名者自身的声明除外）。
+    /// 由其他公钥吊销根公钥的声明一律拒绝。
+    ///
+    /// 返回成功应用的声明；签名者不可信或签名无效的声明将被忽略。
+    ///
+    pub fn apply_rotations<'a>(&mut self, rotations: &'a [KeyRotation]) -> Vec<&'a KeyRotation> {
+        let mut probe = self.clone();
+        probe.apply_rotations_in_order(rotations);
+        self.revoked.extend(probe.revoked);
+
+        let applied = self.apply_rotations_in_order(rotations);
+        let revoked = &self.revoked;
+        self.keys.retain(|x| !revoked.contains(x.as_bytes()));
+        applied
+    }
+
+    fn apply_rotations_in_order<'a>(
+        &mut self,
+        rotations: &'a [KeyRotation],
+    ) -> Vec<&'a KeyRotation> {
+        let mut applied = vec![];
+        let mut pending: Vec<&KeyRotation> = rotations.iter().collect();
+        loop {
+            let mut progressed = false;
+            pending.retain(|rotation| match self.try_apply_rotation(rotation) {
+                Ok(true) => {
+                    applied.push(*rotation);
+                    progressed = true;
+                    false
+                }
+                Ok(false) => true,
+                Err(_) => false,
+            });
+            if !progressed {
+                break;
+            }
+        }
+
+        applied
+    }
+
+    /// 使用任意可信公钥校验签名
+    pub fn verify_strict(&self, message: &[u8], signature: &Signature) -> anyhow::Result<()> {
+        if self
+            .keys
+            .iter()
+            .any(|key| key.verify_strict(message, signature).is_ok())
+        {
+            Ok(())
+        } else {
+            bail!("signature is not signed by any trusted key")
+        }
+    }
+
+    /// `Ok(false)` 表示签名者尚不可信，之后可能可以应用
+    fn try_apply_rotation(&mut self, rotation: &KeyRotation) -> anyhow::Result<bool> {
+        let signer = parse_key(&rotation.signer)?;
+        let revokes = rotation.revokes.as_deref().map(parse_key).transpose()?;
+        if matches!(revokes, Some(revokes) if revokes != signer && self.roots.contains(&revokes)) {
+            bail!("root key can only be revoked by itself");
+        }
+        if self.revoked.contains(signer.as_bytes()) && revokes != Some(signer) {
+            bail!("signer has been revoked");
+        }
+        if !self.is_trusted(&signer) {
+            return Ok(false);
+        }
+
+        let signature = crate::try_parse_signature(&hex::decode(&rotation.signature)?)?;
+        signer.verify_strict(&rotation.message(), &signature)?;
+
+        let key = parse_key(&rotation.key)?;
+        if let Some(revokes) = revokes {
+            self.keys.retain(|x| *x != revokes);
+            self.revoked.insert(revokes.to_bytes());
+        }
+        if !self.revoked.contains(key.as_bytes()) && !self.is_trusted(&key) {
+            self.keys.push(key);
+        }
+        Ok(true)
+    }
+}
+
+pub fn parse_key(hex_key: &str) -> anyhow::Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key)?
+        .try_into()
+        .map_err(|_| anyhow!("invalid public key length"))?;
+    Ok(VerifyingKey::from_bytes(&bytes)?)
+}
+
+pub mod consts {
+    pub const KEY_ROTATION_MESSAGE_PREFIX: &str = "arkhost-ota:key-rotation:v1";
+}
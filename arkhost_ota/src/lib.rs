@@ -1,10 +1,13 @@
 pub mod bin_diff;
+pub mod keys;
 
 use std::{collections::HashMap, sync::OnceLock};
 
+use anyhow::bail;
 use ed25519_dalek::{pkcs8::DecodePublicKey, Signature, VerifyingKey};
 use semver::Version;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Digest;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Resource {
@@ -15,12 +18,203 @@ pub struct Resource {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReleaseIndexV1 {
     pub branches: HashMap<String, Release>,
+    /// 单调递增的序号，客户端拒绝低于已见过序号的索引，防止旧索引被重放
+    #[serde(default)]
+    pub serial: u64,
+    /// 过期时间（UNIX 时间戳，秒）
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+impl ReleaseIndexV1 {
+    ///
+    /// 检查索引是否过期，以及序号是否低于 `last_seen_serial`。
+    ///
+    /// 序号相同视为同一索引，允许通过。
+    ///
+    pub fn verify_freshness(
+        &self,
+        last_seen_serial: Option<u64>,
+        now_unix: u64,
+    ) -> anyhow::Result<()> {
+        if let Some(last_seen_serial) = last_seen_serial {
+            if self.serial < last_seen_serial {
+                bail!(
+                    "index serial {} is older than last seen serial {}",
+                    self.serial,
+                    last_seen_serial
+                );
+            }
+        }
+        if let Some(expires_at) = self.expires_at {
+            if now_unix >= expires_at {
+                bail!("index expired at {}", expires_at);
+            }
+        }
+        Ok(())
+    }
+
+    ///
+    /// 选择 `branch` 分支应使用的版本。
+    ///
+    /// 索引中没有该分支（如未发布 Beta 版本）时使用默认分支；
+    /// 非默认分支落后于默认分支时，同样使用默认分支的版本。
+    ///
+    pub fn release_for_branch(&self, branch: &str) -> Option<&Release> {
+        let default_release = self.branches.get(consts::DEFAULT_BRANCH);
+        match (self.branches.get(branch), default_release) {
+            (Some(release), Some(default_release)) if default_release.version > release.version => {
+                Some(default_release)
+            }
+            (Some(release), _) => Some(release),
+            (None, default_release) => default_release,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Release {
     pub version: Version,
     pub file: Resource,
+    /// 该版本提供的增量更新格式，旧索引中没有该字段时视为仅提供 bsdiff
+    #[serde(default = "default_patch_formats")]
+    pub patch_formats: Vec<bin_diff::PatchFormat>,
+    /// 灰度发布比例（0-100），不指定时视为全量发布
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollout_percentage: Option<u8>,
+}
+
+impl Release {
+    /// 根据安装 ID 所在的分桶判断是否在灰度范围内
+    pub fn rollout_eligible(&self, install_id: &[u8]) -> bool {
+        match self.rollout_percentage {
+            Some(percentage) => rollout_bucket(install_id, &self.version) < percentage,
+            None => true,
+        }
+    }
+
+    ///
+    /// 从哈希为 `source_hash` 的版本增量更新时使用的 patch。
+    ///
+    /// 按 [`bin_diff::PatchFormat::SUPPORTED`] 的优先级，选择该版本提供且清单中存在的格式。
+    ///
+    pub fn select_patch(
+        &self,
+        manifest: &ReleaseManifestV1,
+        source_hash: &str,
+    ) -> Option<(bin_diff::PatchFormat, String)> {
+        bin_diff::PatchFormat::SUPPORTED
+            .into_iter()
+            .filter(|format| self.patch_formats.contains(format))
+            .map(|format| (format, file_patch_path(&self.file, source_hash, format)))
+            .find(|(_, path)| manifest.artifacts.contains_key(path))
+    }
+}
+
+///
+/// 由安装 ID 与版本号得到 0-99 的分桶。
+///
+/// 同一安装在同一版本的灰度过程中分桶不变，不同版本之间分桶重新打散。
+///
+pub fn rollout_bucket(install_id: &[u8], version: &Version) -> u8 {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(install_id);
+    hasher.update(version.to_string().as_bytes());
+    let hash = hasher.finalize();
+    (u64::from_le_bytes(hash[0..8].try_into().unwrap()) % 100) as u8
+}
+
+fn default_patch_formats() -> Vec<bin_diff::PatchFormat> {
+    vec![bin_diff::PatchFormat::Bsdiff]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Artifact {
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Artifact {
+    /// 检查服务端文件大小（如 HEAD 响应的 Content-Length）是否与清单一致
+    pub fn verify_size(&self, path: &str, size: u64) -> anyhow::Result<()> {
+        if size != self.size {
+            bail!(
+                "size of '{}' does not match manifest: expected: {}; got: {}",
+                path,
+                self.size,
+                size
+            );
+        }
+        Ok(())
+    }
+
+    /// 检查下载内容的大小及哈希值是否与清单一致
+    pub fn verify_content(&self, path: &str, bytes: &[u8]) -> anyhow::Result<()> {
+        self.verify_size(path, bytes.len() as u64)?;
+        let hash = hex::encode(sha2::Sha256::digest(bytes));
+        if !hash.eq_ignore_ascii_case(&self.sha256) {
+            bail!(
+                "hash of '{}' does not match manifest: expected: {}; got: {}",
+                path,
+                self.sha256,
+                hash
+            );
+        }
+        Ok(())
+    }
+}
+
+///
+/// 某个版本的所有可下载文件（完整程序及各 `.bspatch`），与 `index.json` 同样需要签名。
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReleaseManifestV1 {
+    pub version: Version,
+    /// 相对于 [`consts::url::asset::ui_ota_v1::FILES`] 的路径 -> 文件信息
+    pub artifacts: HashMap<String, Artifact>,
+}
+
+impl ReleaseManifestV1 {
+    /// 不在清单中的文件一律拒绝
+    pub fn artifact(&self, path: &str) -> anyhow::Result<&Artifact> {
+        match self.artifacts.get(path) {
+            Some(artifact) => Ok(artifact),
+            None => bail!("artifact '{}' is not listed in manifest", path),
+        }
+    }
+
+    /// 检查清单是否与 `index.json` 中的版本一致
+    pub fn verify_release(&self, release: &Release) -> anyhow::Result<()> {
+        if self.version != release.version {
+            bail!(
+                "manifest version {} does not match release version {}",
+                self.version,
+                release.version
+            );
+        }
+        let artifact = self.artifact(&file_path(&release.file))?;
+        if !artifact.sha256.eq_ignore_ascii_case(&release.file.hash) {
+            bail!(
+                "manifest hash {} does not match release hash {}",
+                artifact.sha256,
+                release.file.hash
+            );
+        }
+        Ok(())
+    }
+}
+
+///
+/// 例如 file = `Resource { path: "foo/bar.txt", hash: "deadbeef" }`;
+///
+/// 得到 `"foo/bar.txt/deadbeef.manifest.json"`
+///
+pub fn manifest_path(file: &Resource) -> String {
+    format!("{}.manifest.json", file_path(file))
+}
+
+pub fn manifest_sig_path(file: &Resource) -> String {
+    format!("{}.sig", manifest_path(file))
 }
 
 ///
@@ -43,12 +237,24 @@ pub fn file_path(file: &Resource) -> String {
 /// 得到 `"foo/bar.txt/c0ffee-deadbeef.bspatch"` （hash会被裁剪到32个字符长度）
 ///
 pub fn file_bspatch_path(file: &Resource, source_hash: &str) -> String {
+    file_patch_path(file, source_hash, bin_diff::PatchFormat::Bsdiff)
+}
+
+///
+/// 同 [`file_bspatch_path`]，扩展名由 `format` 决定
+///
+pub fn file_patch_path(
+    file: &Resource,
+    source_hash: &str,
+    format: bin_diff::PatchFormat,
+) -> String {
     let source_hash = &source_hash[0..32.min(source_hash.len())];
     let hash = &file.hash[0..32.min(file.hash.len())];
+    let extension = format.extension();
     if file.path.ends_with('/') {
-        format!("{}{}-{}.bspatch", &file.path, source_hash, hash)
+        format!("{}{}-{}.{}", &file.path, source_hash, hash, extension)
     } else {
-        format!("{}/{}-{}.bspatch", &file.path, source_hash, hash)
+        format!("{}/{}-{}.{}", &file.path, source_hash, hash, extension)
     }
 }
 
@@ -64,9 +270,24 @@ pub fn try_parse_signature(bytes: &[u8]) -> signature::Result<Signature> {
     Signature::from_slice(bytes)
 }
 
+///
+/// 使用可信公钥校验签名后解析 JSON（如 `index.json` 与各版本的清单）
+///
+pub fn parse_signed_json<T: DeserializeOwned>(
+    trusted_keys: &keys::TrustedKeys,
+    bytes: &[u8],
+    sig_bytes: &[u8],
+) -> anyhow::Result<T> {
+    let sig = try_parse_signature(sig_bytes)?;
+    trusted_keys.verify_strict(bytes, &sig)?;
+    Ok(serde_json::de::from_slice(bytes)?)
+}
+
 pub mod consts {
     pub const DEFAULT_BRANCH: &str = "main";
+    pub const BETA_BRANCH: &str = "beta";
     pub const TMP_PATCH_EXECUTABLE_NAME: &str = "closure-studio.__exe_patch__.tmp";
+    pub const BACKUP_EXECUTABLE_NAME: &str = "closure-studio.__exe_backup__.tmp";
     pub const RELEASE_PUB_KEY_PKCS8: &str = include_str!("../resource/release.pub");
 
     pub mod url {
@@ -74,6 +295,7 @@ pub mod consts {
             pub mod ui_ota_v1 {
                 pub const INDEX: &str = "ui/ota/v1/index.json";
                 pub const INDEX_SIG: &str = "ui/ota/v1/index.json.sig";
+                pub const KEYS: &str = "ui/ota/v1/keys.json";
                 pub const FILES: &str = "ui/ota/v1/";
             }
         }
@@ -40,6 +40,48 @@ mod tests {
         assert_eq!(actual_target, target);
     }
 
+    #[test]
+    fn test_patch_formats() {
+        // 伪随机内容，避免新版本只通过自身的重复内容就能还原
+        let mut seed = 0x2545f491u32;
+        let source: Vec<u8> = (0..200_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (seed >> 16) as u8
+            })
+            .collect();
+        let mut target = source.clone();
+        target[1000..1100].fill(0xaa);
+        target.splice(50_000..50_000, b"inserted".repeat(100));
+        target.truncate(180_000);
+        target.extend_from_slice(&source[..10_000]);
+        let (_, target_hash) = testing_hashes(&source, &target);
+
+        for format in PatchFormat::SUPPORTED {
+            let patch = format.diff(&source, &target).unwrap();
+            assert!(
+                patch.len() < target.len() / 10,
+                "{format:?}: {}",
+                patch.len()
+            );
+            let actual_target = format
+                .patch_check_integrity(&source, &patch, &target_hash, Sha256::new())
+                .unwrap();
+            assert_eq!(actual_target, target);
+            let mut corrupted_source = source.clone();
+            corrupted_source[5000] ^= 0xff;
+            assert!(format
+                .patch_check_integrity(&corrupted_source, &patch, &target_hash, Sha256::new())
+                .is_err());
+        }
+
+        let (source, target) = testing_binaries();
+        for format in PatchFormat::SUPPORTED {
+            let patch = format.diff(&source, &target).unwrap();
+            assert_eq!(format.patch(&source, &patch).unwrap(), target);
+        }
+    }
+
     #[test]
     fn test_patch_filename() {
         let (source, target) = testing_binaries();
@@ -49,5 +91,15 @@ mod tests {
             bspatch_filename(&source_hash, &target_hash),
             "456bbefe515d6e82eda993e5e4b9cfe8-69a2ed695177ed187db406e44ffafd6d.bspatch"
         );
+        let filename = patch_filename(&source_hash, &target_hash, PatchFormat::Chunked);
+        assert_eq!(
+            filename,
+            "456bbefe515d6e82eda993e5e4b9cfe8-69a2ed695177ed187db406e44ffafd6d.chkpatch"
+        );
+        assert_eq!(
+            PatchFormat::from_filename(&filename),
+            Some(PatchFormat::Chunked)
+        );
+        assert_eq!(PatchFormat::from_filename("foo.bar"), None);
     }
 }
@@ -1,7 +1,9 @@
 #[cfg(test)]
 pub mod tests {
+    use arkhost_ota::keys::{KeyRotation, TrustedKeys};
     use arkhost_ota::*;
-    use ed25519_dalek::{pkcs8::DecodePublicKey, Signature, VerifyingKey};
+    use ed25519_dalek::{pkcs8::DecodePublicKey, Signature, Signer, SigningKey, VerifyingKey};
+    use std::collections::HashMap;
 
     #[test]
     pub fn test_file_url() {
@@ -32,6 +34,145 @@ pub mod tests {
             file_bspatch_path(&file, source_hash),
             "foo/bar.txt/4a25c063ed412cf03617cc19df33e246-2ac32e4e6b64d0c53a4dd9bbca50565e.bspatch"
         );
+        assert_eq!(
+            file_patch_path(&file, source_hash, bin_diff::PatchFormat::Zstd),
+            "foo/bar.txt/4a25c063ed412cf03617cc19df33e246-2ac32e4e6b64d0c53a4dd9bbca50565e.zstpatch"
+        );
+    }
+
+    #[test]
+    pub fn test_manifest() {
+        let release = Release {
+            version: semver::Version::new(1, 2, 3),
+            file: Resource {
+                path: "foo/bar.txt".into(),
+                hash: "2ac32e4e6b64d0c53a4dd9bbca50565e59d89d8f63e9192528a9a996e149e095".into(),
+            },
+            patch_formats: vec![bin_diff::PatchFormat::Bsdiff],
+            rollout_percentage: None,
+        };
+        assert_eq!(
+            manifest_path(&release.file),
+            "foo/bar.txt/2ac32e4e6b64d0c53a4dd9bbca50565e.manifest.json"
+        );
+        assert_eq!(
+            manifest_sig_path(&release.file),
+            "foo/bar.txt/2ac32e4e6b64d0c53a4dd9bbca50565e.manifest.json.sig"
+        );
+
+        let patch_path = file_bspatch_path(&release.file, "c0ffee");
+        let mut manifest = ReleaseManifestV1 {
+            version: release.version.clone(),
+            artifacts: HashMap::from([
+                (
+                    file_path(&release.file),
+                    Artifact {
+                        size: 1024,
+                        sha256: release.file.hash.clone(),
+                    },
+                ),
+                (
+                    patch_path.clone(),
+                    Artifact {
+                        size: 64,
+                        sha256: "deadbeef".into(),
+                    },
+                ),
+            ]),
+        };
+        manifest.verify_release(&release).unwrap();
+        assert_eq!(manifest.artifact(&patch_path).unwrap().size, 64);
+        assert!(manifest
+            .artifact(&file_bspatch_path(&release.file, "beef"))
+            .is_err());
+
+        // 仅选择该版本提供且清单中存在的格式
+        assert_eq!(
+            release.select_patch(&manifest, "c0ffee"),
+            Some((bin_diff::PatchFormat::Bsdiff, patch_path.clone()))
+        );
+        assert_eq!(release.select_patch(&manifest, "beef"), None);
+        let zstd_path = file_patch_path(&release.file, "c0ffee", bin_diff::PatchFormat::Zstd);
+        manifest.artifacts.insert(
+            zstd_path.clone(),
+            Artifact {
+                size: 32,
+                sha256: "deadbeef".into(),
+            },
+        );
+        assert_eq!(
+            release.select_patch(&manifest, "c0ffee"),
+            Some((bin_diff::PatchFormat::Bsdiff, patch_path.clone()))
+        );
+        let mut zstd_release = release.clone();
+        zstd_release.patch_formats =
+            vec![bin_diff::PatchFormat::Bsdiff, bin_diff::PatchFormat::Zstd];
+        assert_eq!(
+            zstd_release.select_patch(&manifest, "c0ffee"),
+            Some((bin_diff::PatchFormat::Zstd, zstd_path))
+        );
+
+        let artifact = Artifact {
+            size: 5,
+            sha256: "2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824".into(),
+        };
+        artifact.verify_size("hello", 5).unwrap();
+        assert!(artifact.verify_size("hello", 6).is_err());
+        artifact.verify_content("hello", b"hello").unwrap();
+        assert!(artifact.verify_content("hello", b"hellO").is_err());
+        assert!(artifact.verify_content("hello", b"hello!").is_err());
+
+        manifest.version = semver::Version::new(1, 2, 2);
+        assert!(manifest.verify_release(&release).is_err());
+    }
+
+    #[test]
+    pub fn test_rollout() {
+        let mut release = Release {
+            version: semver::Version::new(1, 2, 3),
+            file: Resource {
+                path: "foo".into(),
+                hash: "deadbeef".into(),
+            },
+            patch_formats: vec![],
+            rollout_percentage: None,
+        };
+        let install_ids: Vec<[u8; 16]> = (0..1000u32)
+            .map(|x| {
+                let mut id = [0u8; 16];
+                id[0..4].copy_from_slice(&x.to_le_bytes());
+                id
+            })
+            .collect();
+        let eligible = |release: &Release| {
+            install_ids
+                .iter()
+                .filter(|id| release.rollout_eligible(&id[..]))
+                .count()
+        };
+
+        assert_eq!(eligible(&release), 1000);
+        release.rollout_percentage = Some(0);
+        assert_eq!(eligible(&release), 0);
+        release.rollout_percentage = Some(100);
+        assert_eq!(eligible(&release), 1000);
+        release.rollout_percentage = Some(20);
+        let partial = eligible(&release);
+        assert!((150..250).contains(&partial), "{partial}");
+
+        // 扩大灰度比例时，已在范围内的安装保持在范围内
+        let eligible_ids: Vec<_> = install_ids
+            .iter()
+            .filter(|id| release.rollout_eligible(&id[..]))
+            .collect();
+        release.rollout_percentage = Some(50);
+        assert!(eligible_ids
+            .iter()
+            .all(|id| release.rollout_eligible(&id[..])));
+        assert_eq!(
+            rollout_bucket(&install_ids[0], &release.version),
+            rollout_bucket(&install_ids[0], &release.version)
+        );
     }
 
     #[test]
@@ -51,6 +192,169 @@ pub mod tests {
         assert!(key.verify_strict(text_bytes, &other_sig).is_err());
     }
 
+    #[test]
+    pub fn test_key_rotation() {
+        let root = SigningKey::from_bytes(&[1; 32]);
+        let second = SigningKey::from_bytes(&[2; 32]);
+        let third = SigningKey::from_bytes(&[3; 32]);
+        let rogue = SigningKey::from_bytes(&[4; 32]);
+        let text_bytes = include_bytes!("./input/test.txt");
+
+        // 顺序无关：third 由 second 引入，second 由 root 引入并吊销 root
+        let rotations = vec![
+            KeyRotation::sign(&second, &third.verifying_key(), None),
+            KeyRotation::sign(&root, &second.verifying_key(), Some(&root.verifying_key())),
+            KeyRotation::sign(&rogue, &rogue.verifying_key(), None),
+        ];
+        let mut trusted = TrustedKeys::new([root.verifying_key()]);
+        assert_eq!(trusted.apply_rotations(&rotations).len(), 2);
+        assert!(!trusted.is_trusted(&root.verifying_key()));
+        assert!(trusted.is_trusted(&second.verifying_key()));
+        assert!(trusted.is_trusted(&third.verifying_key()));
+        assert!(!trusted.is_trusted(&rogue.verifying_key()));
+
+        trusted
+            .verify_strict(text_bytes, &third.sign(text_bytes))
+            .unwrap();
+        assert!(trusted
+            .verify_strict(text_bytes, &root.sign(text_bytes))
+            .is_err());
+
+        // 被吊销的公钥不能被重新引入
+        let readd = [KeyRotation::sign(&second, &root.verifying_key(), None)];
+        trusted.apply_rotations(&readd);
+        assert!(!trusted.is_trusted(&root.verifying_key()));
+
+        // 篡改的声明被忽略
+        let mut forged = KeyRotation::sign(&second, &rogue.verifying_key(), None);
+        forged.revokes = Some(hex::encode(third.verifying_key().as_bytes()));
+        assert!(trusted.apply_rotations(&[forged]).is_empty());
+        assert!(trusted.is_trusted(&third.verifying_key()));
+    }
+
+    #[test]
+    pub fn test_key_revocation_order() {
+        let root = SigningKey::from_bytes(&[1; 32]);
+        let second = SigningKey::from_bytes(&[2; 32]);
+        let third = SigningKey::from_bytes(&[3; 32]);
+        let rogue = SigningKey::from_bytes(&[4; 32]);
+
+        // 吊销列于由被吊销公钥签名的声明之后，该声明仍被拒绝
+        let rotations = vec![
+            KeyRotation::sign(&root, &second.verifying_key(), None),
+            KeyRotation::sign(&second, &rogue.verifying_key(), None),
+            KeyRotation::sign(&root, &third.verifying_key(), Some(&second.verifying_key())),
+        ];
+        let mut trusted = TrustedKeys::new([root.verifying_key()]);
+        assert_eq!(trusted.apply_rotations(&rotations).len(), 2);
+        assert!(trusted.is_trusted(&root.verifying_key()));
+        assert!(!trusted.is_trusted(&second.verifying_key()));
+        assert!(trusted.is_trusted(&third.verifying_key()));
+        assert!(!trusted.is_trusted(&rogue.verifying_key()));
+
+        // 吊销签名者自身的声明之前，由该签名者签名的其他声明同样被拒绝
+        let rotations = vec![
+            KeyRotation::sign(&root, &rogue.verifying_key(), None),
+            KeyRotation::sign(&root, &second.verifying_key(), Some(&root.verifying_key())),
+        ];
+        let mut trusted = TrustedKeys::new([root.verifying_key()]);
+        assert_eq!(trusted.apply_rotations(&rotations).len(), 1);
+        assert!(!trusted.is_trusted(&root.verifying_key()));
+        assert!(trusted.is_trusted(&second.verifying_key()));
+        assert!(!trusted.is_trusted(&rogue.verifying_key()));
+
+        // 根公钥与轮换公钥互相吊销时，仅根公钥的吊销生效
+        let rotations = vec![
+            KeyRotation::sign(&root, &second.verifying_key(), None),
+            KeyRotation::sign(&second, &rogue.verifying_key(), Some(&root.verifying_key())),
+            KeyRotation::sign(&root, &third.verifying_key(), Some(&second.verifying_key())),
+        ];
+        let mut trusted = TrustedKeys::new([root.verifying_key()]);
+        assert_eq!(trusted.apply_rotations(&rotations).len(), 2);
+        assert!(trusted.is_trusted(&root.verifying_key()));
+        assert!(!trusted.is_trusted(&second.verifying_key()));
+        assert!(trusted.is_trusted(&third.verifying_key()));
+        assert!(!trusted.is_trusted(&rogue.verifying_key()));
+        // 根公钥的轮换声明不受影响
+        let rotations = [KeyRotation::sign(&root, &rogue.verifying_key(), None)];
+        assert_eq!(trusted.apply_rotations(&rotations).len(), 1);
+        assert!(trusted.is_trusted(&rogue.verifying_key()));
+    }
+
+    #[test]
+    pub fn test_release_for_branch() {
+        let release = |minor| Release {
+            version: semver::Version::new(1, minor, 0),
+            file: Resource {
+                path: "foo".into(),
+                hash: format!("{minor}"),
+            },
+            patch_formats: vec![],
+            rollout_percentage: None,
+        };
+        let mut index = ReleaseIndexV1 {
+            branches: HashMap::from([(consts::DEFAULT_BRANCH.to_owned(), release(2))]),
+            serial: 0,
+            expires_at: None,
+        };
+
+        // 没有 Beta 分支时使用默认分支
+        assert_eq!(
+            index.release_for_branch(consts::BETA_BRANCH),
+            Some(&release(2))
+        );
+
+        index
+            .branches
+            .insert(consts::BETA_BRANCH.to_owned(), release(3));
+        assert_eq!(
+            index.release_for_branch(consts::BETA_BRANCH),
+            Some(&release(3))
+        );
+        assert_eq!(
+            index.release_for_branch(consts::DEFAULT_BRANCH),
+            Some(&release(2))
+        );
+
+        // Beta 分支落后于默认分支时使用默认分支
+        index
+            .branches
+            .insert(consts::BETA_BRANCH.to_owned(), release(1));
+        assert_eq!(
+            index.release_for_branch(consts::BETA_BRANCH),
+            Some(&release(2))
+        );
+
+        index.branches.remove(consts::DEFAULT_BRANCH);
+        assert_eq!(index.release_for_branch(consts::DEFAULT_BRANCH), None);
+    }
+
+    #[test]
+    pub fn test_index_freshness() {
+        let index = ReleaseIndexV1 {
+            branches: HashMap::new(),
+            serial: 10,
+            expires_at: Some(1000),
+        };
+
+        index.verify_freshness(None, 0).unwrap();
+        index.verify_freshness(Some(10), 999).unwrap();
+        index.verify_freshness(Some(9), 999).unwrap();
+        assert!(index.verify_freshness(Some(11), 999).is_err());
+        assert!(index.verify_freshness(Some(10), 1000).is_err());
+
+        let legacy: ReleaseIndexV1 = serde_json::from_str(
+            r#"{"branches":{"main":{"version":"1.0.0","file":{"path":"a","hash":"b"}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.serial, 0);
+        assert_eq!(legacy.expires_at, None);
+        assert_eq!(
+            legacy.branches["main"].patch_formats,
+            vec![bin_diff::PatchFormat::Bsdiff]
+        );
+    }
+
     fn read_sig(bytes: &[u8]) -> Signature {
         Signature::from_slice(bytes).unwrap()
     }
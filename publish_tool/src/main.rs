@@ -1,174 +1,137 @@
-use std::{
-    collections::HashMap,
-    env,
-    path::{Path, PathBuf},
-};
+mod publish;
+mod sign;
+mod verify;
+
+use std::{env, path::PathBuf};
 
 use argh::FromArgs;
-use arkhost_ota::{Release, ReleaseIndexV1, Resource};
-use cargo::{
-    self,
-    core::{compiler::CompileMode, resolver::CliFeatures, Shell, Workspace},
-    ops::{CompileFilter, CompileOptions, FilterRule, LibRule},
-    Config,
-};
-use sha2::Digest;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use arkhost_ota::bin_diff::PatchFormat;
 
 #[derive(Debug, Clone, FromArgs)]
-/// CLI发布工具
+/// CLI发布工具，不指定子命令时同 publish
 struct ProgramOptions {
     #[argh(option)]
-    /// 除基本 Feature FLag 外（如 desktop-app）外指定的 Feature Flag
+    /// 不指定子命令时传递给 publish 的 Feature Flag，兼容引入子命令前的调用方式
     pub features: Option<String>,
+    #[argh(subcommand)]
+    pub command: Option<Command>,
 }
 
-#[tokio::main]
-async fn main() {
-    let po: ProgramOptions = argh::from_env();
-
-    let cwd = env::current_dir().expect("invalid env::current_dir()");
-    let pervious_versions_dir = cwd.join("pervious_versions/");
-    tokio::fs::create_dir_all(&pervious_versions_dir)
-        .await
-        .unwrap();
-    let dst_dir = cwd.join("dst/ui/ota/v1/");
-    let dst_index_path = dst_dir.join("index.json");
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Publish(PublishOptions),
+    Keygen(KeygenOptions),
+    Verify(VerifyOptions),
+}
 
-    println!("Invoking cargo build");
-    let cargo_home = PathBuf::from(env::var("CARGO_HOME").unwrap());
-    let cfg = &Config::new(Shell::new(), cwd.clone(), cargo_home);
-    let ws = Workspace::new(&cwd.join("Cargo.toml"), cfg).unwrap();
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand, name = "publish")]
+/// 编译客户端，生成增量更新并写入 dst/ 下的索引
+pub struct PublishOptions {
+    #[argh(option)]
+    /// 除基本 Feature FLag 外（如 desktop-app）外指定的 Feature Flag
+    pub features: Option<String>,
+    #[argh(option)]
+    /// 索引的有效天数，不指定则不过期
+    pub expire_days: Option<u64>,
+    #[argh(option, default = "arkhost_ota::consts::DEFAULT_BRANCH.to_owned()")]
+    /// 发布到的分支，将合并到已有的 index.json 中，默认为 main
+    pub branch: String,
+    #[argh(option)]
+    /// PKCS#8 PEM 格式的签名私钥，用于生成 index.json.sig 等签名文件
+    pub signing_key: Option<PathBuf>,
+    #[argh(option)]
+    /// 并行生成增量更新的最大数量，默认为 CPU 核心数
+    pub jobs: Option<usize>,
+    #[argh(option, from_str_fn(parse_rollout))]
+    /// 灰度发布比例（0-100），不指定则全量发布
+    pub rollout: Option<u8>,
+    #[argh(option, from_str_fn(parse_patch_formats))]
+    /// 生成的增量更新格式（逗号分隔，可选 bsdiff、zstd、chunked），默认生成全部格式
+    pub patch_formats: Option<Vec<PatchFormat>>,
+}
 
-    let mut compile_opts = CompileOptions::new(cfg, CompileMode::Build).unwrap();
-    compile_opts.build_config.requested_profile = "release".into();
-    compile_opts.filter = CompileFilter::Only {
-        all_targets: false,
-        lib: LibRule::False,
-        bins: FilterRule::new(vec![consts::BINARY_TARGET.into()], false),
-        examples: FilterRule::none(),
-        tests: FilterRule::none(),
-        benches: FilterRule::none(),
-    };
-    let mut cli_features: Vec<String> = vec!["desktop-app".into()];
-    if let Some(features) = po.features {
-        cli_features.push(features);
+fn parse_rollout(value: &str) -> Result<u8, String> {
+    match value.parse::<u8>() {
+        Ok(percentage) if percentage <= 100 => Ok(percentage),
+        _ => Err(format!(
+            "invalid rollout percentage '{value}', expected 0-100"
+        )),
     }
-    println!("Requested features: {cli_features:#?}");
-
-    compile_opts.cli_features =
-        CliFeatures::from_command_line(&cli_features, false, false).unwrap();
+}
 
-    let compilation = cargo::ops::compile(&ws, &compile_opts).unwrap();
-    let release_path = compilation
-        .binaries
-        .iter()
-        .find_map(|x| {
-            if x.unit.target.name() == consts::BINARY_TARGET {
-                Some(x.path.clone())
-            } else {
-                None
-            }
+fn parse_patch_formats(value: &str) -> Result<Vec<PatchFormat>, String> {
+    value
+        .split(',')
+        .map(|x| {
+            serde_json::from_value(serde_json::Value::String(x.trim().to_owned()))
+                .map_err(|_| format!("unknown patch format '{x}'"))
         })
-        .unwrap();
-    let release_file_name = release_path
-        .file_name()
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_owned();
-    let (release_bytes, release_hash) = read_file_with_hash(&release_path).await.unwrap();
-    println!("Release SHA256: {}", hex::encode(release_hash));
-
-    println!("Cleaning dst directory");
-    _ = tokio::fs::remove_dir_all(&dst_dir).await;
-    tokio::fs::create_dir_all(&dst_dir).await.unwrap();
-
-    println!("Deploying release binary to hash versioning directory");
-    let dst_hash_versions_dir = dst_dir.join(PathBuf::from(&format!("{release_file_name}/")));
-    tokio::fs::create_dir_all(&dst_hash_versions_dir)
-        .await
-        .unwrap();
-    let dst_executable_path = dst_hash_versions_dir.join(hex::encode(&release_hash[0..16]));
-    tokio::fs::copy(&release_path, &dst_executable_path)
-        .await
-        .unwrap();
-
-    println!("Creating patches for pervious versions");
-    let mut read_dir = tokio::fs::read_dir(&pervious_versions_dir).await.unwrap();
-    // TODO: 并行化生成patch
-    while let Some(entry) = read_dir.next_entry().await.unwrap() {
-        if matches!(entry.file_type().await, Ok(file_type) if file_type.is_file()) {
-            let (perv_version_bytes, perv_version_hash) =
-                read_file_with_hash(&entry.path()).await.unwrap();
-
-            println!(
-                "Generating patch for pervious version:\n\tSHA256: {}",
-                hex::encode(perv_version_hash)
-            );
-            if perv_version_hash == release_hash {
-                println!("\tFile hash matches release hash, skipping");
-                continue;
-            }
-
-            let patch_path = dst_hash_versions_dir.join(&arkhost_ota::bin_diff::bspatch_filename(
-                &perv_version_hash,
-                &release_hash,
-            ));
-            tokio::fs::write(
-                &patch_path,
-                &arkhost_ota::bin_diff::bsdiff(&perv_version_bytes, &release_bytes).unwrap(),
-            )
-            .await
-            .unwrap();
-        }
-    }
-
-    println!("Creating index");
-    let mut branches = HashMap::<String, Release>::new();
-    branches.insert(
-        arkhost_ota::consts::DEFAULT_BRANCH.to_owned(),
-        Release {
-            version: ws.current().unwrap().version().clone(),
-            file: Resource {
-                path: release_file_name,
-                hash: hex::encode(release_hash),
-            },
-        },
-    );
+        .collect()
+}
 
-    tokio::fs::write(
-        &dst_index_path,
-        serde_json::ser::to_vec(&ReleaseIndexV1 { branches }).unwrap(),
-    )
-    .await
-    .unwrap();
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand, name = "keygen")]
+/// 生成 ed25519 签名密钥对（<out>.pem 与 <out>.pub）
+pub struct KeygenOptions {
+    #[argh(option, default = "\"release\".to_owned()")]
+    /// 输出文件名前缀，默认为 release
+    pub out: String,
+    #[argh(switch)]
+    /// 覆盖已存在的密钥文件
+    pub force: bool,
+    #[argh(option)]
+    /// 使用该私钥签发公钥轮换声明，写入 dst/ 下的 keys.json
+    pub signed_by: Option<PathBuf>,
+    #[argh(switch)]
+    /// 在轮换声明中吊销 --signed-by 的公钥
+    pub revoke_signer: bool,
+}
 
-    println!("Done");
+#[derive(Debug, Clone, FromArgs)]
+#[argh(subcommand, name = "verify")]
+/// 在本地 HTTP 服务器上模拟客户端更新，检查 dst/ 下发布的文件
+pub struct VerifyOptions {
+    #[argh(option, default = "arkhost_ota::consts::DEFAULT_BRANCH.to_owned()")]
+    /// 检查的分支，默认为 main
+    pub branch: String,
+    #[argh(option)]
+    /// PEM 格式的公钥，不指定则使用客户端内置公钥及 keys.json
+    pub public_key: Option<PathBuf>,
 }
 
-async fn read_file_with_hash(
-    path: &Path,
-) -> anyhow::Result<(Vec<u8>, digest::Output<sha2::Sha256>)> {
-    let mut hasher = sha2::Sha256::new();
-    let file = tokio::fs::File::open(path).await?;
-    let mut bytes = Vec::new();
-    let mut reader = BufReader::new(file);
+#[tokio::main]
+async fn main() {
+    let po: ProgramOptions = argh::from_env();
 
-    let mut buf;
-    while {
-        buf = reader.fill_buf().await?;
-        !buf.is_empty()
-    } {
-        hasher.update(buf);
-        bytes.extend_from_slice(buf);
-        let len = buf.len();
-        reader.consume(len);
+    let cwd = env::current_dir().expect("invalid env::current_dir()");
+    let dst_dir = cwd.join(consts::DST_DIR);
+    let command = match (po.command, po.features) {
+        (Some(command), None) => command,
+        (None, features) => Command::Publish(PublishOptions {
+            features,
+            ..PublishOptions::from_args(&["publish"], &[]).expect("invalid default options")
+        }),
+        (Some(_), Some(_)) => {
+            eprintln!("Error: --features before a subcommand is only supported without one, use `publish --features` instead");
+            std::process::exit(1);
+        }
+    };
+    let result = match command {
+        Command::Publish(opts) => publish::publish(opts, &cwd, &dst_dir).await,
+        Command::Keygen(opts) => sign::keygen(opts, &dst_dir).await,
+        Command::Verify(opts) => verify::verify(opts, &cwd, &dst_dir).await,
+    };
+    if let Err(e) = result {
+        eprintln!("Error: {e:?}");
+        std::process::exit(1);
     }
-
-    Ok((bytes, hasher.finalize()))
 }
 
 pub(crate) mod consts {
     pub const BINARY_TARGET: &str = "closure-studio";
+    pub const DST_ROOT_DIR: &str = "dst/";
+    pub const DST_DIR: &str = "dst/ui/ota/v1/";
+    pub const PERVIOUS_VERSIONS_DIR: &str = "pervious_versions/";
 }
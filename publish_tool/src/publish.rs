@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use arkhost_ota::{
+    bin_diff::PatchFormat, Artifact, Release, ReleaseIndexV1, ReleaseManifestV1, Resource,
+};
+use cargo::{
+    self,
+    core::{compiler::CompileMode, resolver::CliFeatures, Shell, Workspace},
+    ops::{CompileFilter, CompileOptions, FilterRule, LibRule},
+    Config,
+};
+use sha2::Digest;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    sync::Semaphore,
+    task::JoinSet,
+};
+
+use crate::{consts, sign, PublishOptions};
+
+pub async fn publish(po: PublishOptions, cwd: &Path, dst_dir: &Path) -> anyhow::Result<()> {
+    let signing_key = match &po.signing_key {
+        Some(path) => Some(sign::read_signing_key(path).await?),
+        None => {
+            println!("No signing key specified, signature files will NOT be generated");
+            None
+        }
+    };
+
+    let pervious_versions_dir = cwd.join(consts::PERVIOUS_VERSIONS_DIR);
+    tokio::fs::create_dir_all(&pervious_versions_dir).await?;
+    let dst_index_path = dst_dir.join("index.json");
+
+    println!("Invoking cargo build");
+    let cargo_home = PathBuf::from(std::env::var("CARGO_HOME")?);
+    let cfg = &Config::new(Shell::new(), cwd.to_owned(), cargo_home);
+    let ws = Workspace::new(&cwd.join("Cargo.toml"), cfg)?;
+
+    let mut compile_opts = CompileOptions::new(cfg, CompileMode::Build)?;
+    compile_opts.build_config.requested_profile = "release".into();
+    compile_opts.filter = CompileFilter::Only {
+        all_targets: false,
+        lib: LibRule::False,
+        bins: FilterRule::new(vec![consts::BINARY_TARGET.into()], false),
+        examples: FilterRule::none(),
+        tests: FilterRule::none(),
+        benches: FilterRule::none(),
+    };
+    let mut cli_features: Vec<String> = vec!["desktop-app".into()];
+    if let Some(features) = po.features {
+        cli_features.push(features);
+    }
+    println!("Requested features: {cli_features:#?}");
+
+    compile_opts.cli_features = CliFeatures::from_command_line(&cli_features, false, false)?;
+
+    let compilation = cargo::ops::compile(&ws, &compile_opts)?;
+    let release_path = compilation
+        .binaries
+        .iter()
+        .find_map(|x| {
+            if x.unit.target.name() == consts::BINARY_TARGET {
+                Some(x.path.clone())
+            } else {
+                None
+            }
+        })
+        .ok_or(anyhow::anyhow!("release binary not found"))?;
+    let release_file_name = release_path
+        .file_name()
+        .and_then(|x| x.to_str())
+        .ok_or(anyhow::anyhow!("invalid release binary file name"))?
+        .to_owned();
+    let (release_bytes, release_hash) = read_file_with_hash(&release_path).await?;
+    println!("Release SHA256: {}", hex::encode(release_hash));
+
+    // 不清空 dst 目录，以保留其他分支的文件
+    println!("Deploying release binary to hash versioning directory");
+    let dst_hash_versions_dir = dst_dir.join(PathBuf::from(&format!("{release_file_name}/")));
+    tokio::fs::create_dir_all(&dst_hash_versions_dir).await?;
+    let dst_executable_path = dst_hash_versions_dir.join(hex::encode(&release_hash[0..16]));
+    tokio::fs::copy(&release_path, &dst_executable_path).await?;
+    let release_resource = Resource {
+        path: release_file_name,
+        hash: hex::encode(release_hash),
+    };
+    let mut artifacts = HashMap::<String, Artifact>::new();
+    artifacts.insert(
+        arkhost_ota::file_path(&release_resource),
+        Artifact {
+            size: release_bytes.len() as u64,
+            sha256: hex::encode(release_hash),
+        },
+    );
+
+    println!("Creating patches for pervious versions");
+    let mut patch_formats = Vec::new();
+    for format in po.patch_formats.unwrap_or(PatchFormat::SUPPORTED.to_vec()) {
+        if !patch_formats.contains(&format) {
+            patch_formats.push(format);
+        }
+    }
+    println!("Patch formats: {patch_formats:?}");
+    let patches = generate_patches(
+        &pervious_versions_dir,
+        &dst_hash_versions_dir,
+        release_bytes,
+        release_hash,
+        &patch_formats,
+        po.jobs,
+    )
+    .await?;
+    let mut used_patch_formats = Vec::new();
+    for (perv_version_hash, format, artifact) in patches {
+        artifacts.insert(
+            arkhost_ota::file_patch_path(
+                &release_resource,
+                &hex::encode(perv_version_hash),
+                format,
+            ),
+            artifact,
+        );
+        if !used_patch_formats.contains(&format) {
+            used_patch_formats.push(format);
+        }
+    }
+
+    let version = ws.current()?.version().clone();
+    println!("Creating manifest");
+    let manifest_path = dst_dir.join(arkhost_ota::manifest_path(&release_resource));
+    tokio::fs::write(
+        &manifest_path,
+        serde_json::ser::to_vec(&ReleaseManifestV1 {
+            version: version.clone(),
+            artifacts,
+        })?,
+    )
+    .await?;
+
+    println!("Merging release into index, branch: '{}'", &po.branch);
+    let mut index = match tokio::fs::read(&dst_index_path).await {
+        Ok(bytes) => serde_json::de::from_slice::<ReleaseIndexV1>(&bytes)?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => ReleaseIndexV1 {
+            branches: HashMap::new(),
+            serial: 0,
+            expires_at: None,
+        },
+        Err(e) => return Err(e.into()),
+    };
+    index.branches.insert(
+        po.branch,
+        Release {
+            version,
+            file: release_resource,
+            patch_formats: used_patch_formats,
+            rollout_percentage: po.rollout,
+        },
+    );
+    // 以发布时间作为序号，保证单调递增
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    index.serial = now.max(index.serial + 1);
+    index.expires_at = po.expire_days.map(|days| now + days * 24 * 60 * 60);
+    tokio::fs::write(&dst_index_path, serde_json::ser::to_vec(&index)?).await?;
+
+    if let Some(signing_key) = &signing_key {
+        println!("Signing manifest and index");
+        for path in [&manifest_path, &dst_index_path] {
+            let sig_path = sign::sign_file(signing_key, path).await?;
+            println!("\t{}", sig_path.display());
+        }
+    }
+
+    println!("Done");
+    Ok(())
+}
+
+///
+/// 并行地为 `pervious_versions_dir` 中的每个旧版本生成到当前版本的增量更新，
+/// 每个旧版本生成 `patch_formats` 中的所有格式，由客户端按自身支持的格式选择
+///
+/// 返回旧版本哈希值、格式及对应的 patch 文件信息
+///
+async fn generate_patches(
+    pervious_versions_dir: &Path,
+    dst_hash_versions_dir: &Path,
+    release_bytes: Vec<u8>,
+    release_hash: digest::Output<sha2::Sha256>,
+    patch_formats: &[PatchFormat],
+    jobs: Option<usize>,
+) -> anyhow::Result<Vec<(digest::Output<sha2::Sha256>, PatchFormat, Artifact)>> {
+    let mut perv_version_paths = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(pervious_versions_dir).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if matches!(entry.file_type().await, Ok(file_type) if file_type.is_file()) {
+            perv_version_paths.push(entry.path());
+        }
+    }
+
+    let jobs = jobs
+        .or(std::thread::available_parallelism().ok().map(|x| x.get()))
+        .unwrap_or(1)
+        .max(1);
+    let semaphore = Arc::new(Semaphore::new(jobs));
+    let release_bytes = Arc::new(release_bytes);
+    let mut tasks = JoinSet::new();
+    for path in perv_version_paths.iter().cloned() {
+        let semaphore = semaphore.clone();
+        let release_bytes = release_bytes.clone();
+        let dst_hash_versions_dir = dst_hash_versions_dir.to_owned();
+        let patch_formats = patch_formats.to_vec();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await?;
+            let (perv_version_bytes, perv_version_hash) = read_file_with_hash(&path).await?;
+            if perv_version_hash == release_hash {
+                return anyhow::Ok((path, perv_version_hash, Vec::new()));
+            }
+
+            let patches = tokio::task::spawn_blocking(move || {
+                patch_formats
+                    .into_iter()
+                    .map(|format| Ok((format, format.diff(&perv_version_bytes, &release_bytes)?)))
+                    .collect::<anyhow::Result<Vec<_>>>()
+            })
+            .await??;
+            let mut artifacts = Vec::new();
+            for (format, patch_bytes) in patches {
+                let patch_path = dst_hash_versions_dir.join(arkhost_ota::bin_diff::patch_filename(
+                    &perv_version_hash,
+                    &release_hash,
+                    format,
+                ));
+                tokio::fs::write(&patch_path, &patch_bytes).await?;
+                artifacts.push((
+                    format,
+                    Artifact {
+                        size: patch_bytes.len() as u64,
+                        sha256: hex::encode(sha2::Sha256::digest(&patch_bytes)),
+                    },
+                ));
+            }
+            Ok((path, perv_version_hash, artifacts))
+        });
+    }
+
+    let total = perv_version_paths.len();
+    let mut finished = 0;
+    let mut patches = Vec::new();
+    println!("Generating {total} patch(es) with {jobs} job(s)");
+    while let Some(result) = tasks.join_next().await {
+        let (path, perv_version_hash, artifacts) = result??;
+        finished += 1;
+        if artifacts.is_empty() {
+            println!(
+                "[{finished}/{total}] {}: file hash matches release hash, skipping",
+                path.display()
+            );
+            continue;
+        }
+        println!(
+            "[{finished}/{total}] Patch generated for {}\n\tSHA256: {}",
+            path.display(),
+            hex::encode(perv_version_hash)
+        );
+        for (format, artifact) in artifacts {
+            println!("\tPatch size: {} bytes ({format:?})", artifact.size);
+            patches.push((perv_version_hash, format, artifact));
+        }
+    }
+
+    Ok(patches)
+}
+
+async fn read_file_with_hash(
+    path: &Path,
+) -> anyhow::Result<(Vec<u8>, digest::Output<sha2::Sha256>)> {
+    let mut hasher = sha2::Sha256::new();
+    let file = tokio::fs::File::open(path).await?;
+    let mut bytes = Vec::new();
+    let mut reader = BufReader::new(file);
+
+    let mut buf;
+    while {
+        buf = reader.fill_buf().await?;
+        !buf.is_empty()
+    } {
+        hasher.update(buf);
+        bytes.extend_from_slice(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    Ok((bytes, hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_generate_patches() {
+        let dir = std::env::temp_dir().join(format!("publish_test_{}", std::process::id()));
+        let (pervious_versions_dir, dst_dir) = (dir.join("pervious"), dir.join("dst"));
+        tokio::fs::create_dir_all(&pervious_versions_dir)
+            .await
+            .unwrap();
+        tokio::fs::create_dir_all(&dst_dir).await.unwrap();
+        let release_bytes = b"closure studio 1.1.0 ".repeat(64);
+        let release_hash = sha2::Sha256::digest(&release_bytes);
+        let source_bytes = b"closure studio 1.0.0 ".repeat(64);
+        tokio::fs::write(pervious_versions_dir.join("old"), &source_bytes)
+            .await
+            .unwrap();
+        tokio::fs::write(pervious_versions_dir.join("same"), &release_bytes)
+            .await
+            .unwrap();
+
+        // 每个旧版本生成所有指定的格式，与当前版本相同的旧版本跳过
+        let patch_formats = [PatchFormat::Bsdiff, PatchFormat::Zstd];
+        let patches = generate_patches(
+            &pervious_versions_dir,
+            &dst_dir,
+            release_bytes.clone(),
+            release_hash,
+            &patch_formats,
+            Some(1),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            patches.iter().map(|(_, x, _)| *x).collect::<Vec<_>>(),
+            patch_formats
+        );
+        for (source_hash, format, artifact) in patches {
+            assert_eq!(source_hash, sha2::Sha256::digest(&source_bytes));
+            let patch_bytes = tokio::fs::read(dst_dir.join(arkhost_ota::bin_diff::patch_filename(
+                &source_hash,
+                &release_hash,
+                format,
+            )))
+            .await
+            .unwrap();
+            artifact.verify_content("patch", &patch_bytes).unwrap();
+            format
+                .patch_check_integrity(
+                    &source_bytes,
+                    &patch_bytes,
+                    &release_hash,
+                    sha2::Sha256::new(),
+                )
+                .unwrap();
+        }
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+}
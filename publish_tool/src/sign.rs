@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use arkhost_ota::keys::{KeyRotation, KeyRotationListV1};
+use ed25519_dalek::{
+    pkcs8::{spki::der::pem::LineEnding, DecodePrivateKey, EncodePrivateKey, EncodePublicKey},
+    Signer, SigningKey,
+};
+
+use crate::KeygenOptions;
+
+pub async fn read_signing_key(path: &Path) -> anyhow::Result<SigningKey> {
+    let pem = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| format!("unable to read signing key {}", path.display()))?;
+    SigningKey::from_pkcs8_pem(&pem)
+        .with_context(|| format!("invalid PKCS#8 signing key {}", path.display()))
+}
+
+///
+/// 对文件签名，签名写入 `<path>.sig`
+///
+pub async fn sign_file(signing_key: &SigningKey, path: &Path) -> anyhow::Result<PathBuf> {
+    let bytes = tokio::fs::read(path).await?;
+    let signature = signing_key.sign(&bytes);
+    let mut sig_path = path.as_os_str().to_owned();
+    sig_path.push(".sig");
+    let sig_path = PathBuf::from(sig_path);
+    tokio::fs::write(&sig_path, signature.to_bytes()).await?;
+    Ok(sig_path)
+}
+
+pub async fn keygen(opts: KeygenOptions, dst_dir: &Path) -> anyhow::Result<()> {
+    let signing_key = SigningKey::generate(&mut rand_core::OsRng);
+    let verifying_key = signing_key.verifying_key();
+
+    let private_key_path = PathBuf::from(format!("{}.pem", opts.out));
+    let public_key_path = PathBuf::from(format!("{}.pub", opts.out));
+    if !opts.force
+        && (tokio::fs::try_exists(&private_key_path).await?
+            || tokio::fs::try_exists(&public_key_path).await?)
+    {
+        anyhow::bail!(
+            "{} or {} already exists, use --force to overwrite",
+            private_key_path.display(),
+            public_key_path.display()
+        );
+    }
+    tokio::fs::write(
+        &private_key_path,
+        signing_key.to_pkcs8_pem(LineEnding::LF)?.as_bytes(),
+    )
+    .await?;
+    tokio::fs::write(
+        &public_key_path,
+        verifying_key.to_public_key_pem(LineEnding::LF)?,
+    )
+    .await?;
+    println!("Private key: {}", private_key_path.display());
+    println!("Public key: {}", public_key_path.display());
+    println!(
+        "Public key (hex): {}",
+        hex::encode(verifying_key.as_bytes())
+    );
+
+    // 由已信任的公钥签发轮换声明，写入 keys.json
+    if let Some(signed_by) = opts.signed_by {
+        let signer = read_signing_key(&signed_by).await?;
+        let revokes = signer.verifying_key();
+        let rotation = KeyRotation::sign(
+            &signer,
+            &verifying_key,
+            opts.revoke_signer.then_some(&revokes),
+        );
+
+        let keys_path = dst_dir.join(consts::KEYS_FILE_NAME);
+        let mut list = match tokio::fs::read(&keys_path).await {
+            Ok(bytes) => serde_json::de::from_slice::<KeyRotationListV1>(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => KeyRotationListV1::default(),
+            Err(e) => return Err(e.into()),
+        };
+        list.rotations.push(rotation);
+        tokio::fs::create_dir_all(dst_dir).await?;
+        tokio::fs::write(&keys_path, serde_json::ser::to_vec_pretty(&list)?).await?;
+        println!("Key rotation appended to {}", keys_path.display());
+    }
+
+    Ok(())
+}
+
+pub mod consts {
+    pub const KEYS_FILE_NAME: &str = "keys.json";
+}
@@ -0,0 +1,302 @@
+use std::{
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, bail};
+use arkhost_ota::{
+    bin_diff::PatchFormat,
+    keys::{KeyRotationListV1, TrustedKeys},
+    Artifact, ReleaseIndexV1, ReleaseManifestV1,
+};
+use ed25519_dalek::{pkcs8::DecodePublicKey, VerifyingKey};
+use sha2::Digest;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{consts, VerifyOptions};
+
+enum DeltaResult {
+    UpToDate,
+    Delta(PatchFormat, u64),
+    NotListed,
+    Failed(anyhow::Error),
+}
+
+///
+/// 在本地 HTTP 服务器上发布 `dst/`，按客户端的更新流程检查：
+/// 索引与清单签名、完整程序下载，以及 `pervious_versions/` 中每个旧版本的增量更新
+///
+pub async fn verify(opts: VerifyOptions, cwd: &Path, dst_dir: &Path) -> anyhow::Result<()> {
+    let trusted_keys = trusted_keys(&opts, dst_dir).await?;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(serve(listener, cwd.join(consts::DST_ROOT_DIR)));
+    println!("Serving {} on http://{addr}/", consts::DST_ROOT_DIR);
+    let verifier = Verifier {
+        client: reqwest::Client::new(),
+        addr,
+    };
+
+    println!("Verifying index");
+    let index: ReleaseIndexV1 = verifier
+        .get_signed_json(
+            &trusted_keys,
+            arkhost_ota::consts::url::asset::ui_ota_v1::INDEX,
+            arkhost_ota::consts::url::asset::ui_ota_v1::INDEX_SIG,
+        )
+        .await?;
+    index.verify_freshness(
+        None,
+        SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+    )?;
+    let release = index.release_for_branch(&opts.branch).ok_or(anyhow!(
+        "unable to find release of branch '{}'",
+        &opts.branch
+    ))?;
+    println!(
+        "\tbranch: '{}', version: {}, serial: {}",
+        &opts.branch, release.version, index.serial
+    );
+
+    println!("Verifying manifest");
+    let manifest: ReleaseManifestV1 = verifier
+        .get_signed_json(
+            &trusted_keys,
+            &verifier.file_path(&arkhost_ota::manifest_path(&release.file)),
+            &verifier.file_path(&arkhost_ota::manifest_sig_path(&release.file)),
+        )
+        .await?;
+    manifest.verify_release(release)?;
+
+    println!("Verifying full download");
+    let release_path = arkhost_ota::file_path(&release.file);
+    let release_bytes = verifier
+        .download(&release_path, manifest.artifact(&release_path)?)
+        .await?;
+    let release_hash = hex::decode(&release.file.hash)?;
+
+    println!("Verifying delta updates for pervious versions");
+    let mut results = Vec::new();
+    let mut read_dir = tokio::fs::read_dir(cwd.join(consts::PERVIOUS_VERSIONS_DIR)).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if !matches!(entry.file_type().await, Ok(file_type) if file_type.is_file()) {
+            continue;
+        }
+        let source_bytes = tokio::fs::read(entry.path()).await?;
+        let source_hash = sha2::Sha256::digest(&source_bytes);
+        let result = if source_hash[..] == release_hash[..] {
+            DeltaResult::UpToDate
+        } else {
+            let source_hash = hex::encode(source_hash);
+            let patch = release
+                .select_patch(&manifest, &source_hash)
+                .map(|(format, path)| (format, &manifest.artifacts[&path], path));
+            match patch {
+                Some((format, artifact, patch_path)) => match verifier
+                    .verify_delta(format, &patch_path, artifact, &source_bytes, &release_hash)
+                    .await
+                {
+                    Ok(_) => DeltaResult::Delta(format, artifact.size),
+                    Err(e) => DeltaResult::Failed(e),
+                },
+                None => DeltaResult::NotListed,
+            }
+        };
+        results.push((entry.path(), hex::encode(source_hash), result));
+    }
+
+    println!("\nResults:");
+    println!("\tfull download: OK ({} bytes)", release_bytes.len());
+    let mut failed = 0;
+    for (path, hash, result) in &results {
+        let status = match result {
+            DeltaResult::UpToDate => "up to date".to_owned(),
+            DeltaResult::Delta(format, size) => format!("delta OK ({size} bytes, {format:?})"),
+            DeltaResult::NotListed => "no patch in manifest, full download only".to_owned(),
+            DeltaResult::Failed(e) => {
+                failed += 1;
+                format!("FAILED: {e:#}")
+            }
+        };
+        println!("\t{} ({}): {status}", path.display(), &hash[..16]);
+    }
+
+    if failed > 0 {
+        bail!("{failed} of {} delta update(s) failed", results.len());
+    }
+    println!("Done");
+    Ok(())
+}
+
+/// 指定 `--public-key` 时仅信任该公钥，否则使用客户端内置公钥及 keys.json 中的轮换声明
+async fn trusted_keys(opts: &VerifyOptions, dst_dir: &Path) -> anyhow::Result<TrustedKeys> {
+    if let Some(path) = &opts.public_key {
+        let pem = tokio::fs::read_to_string(path).await?;
+        return Ok(TrustedKeys::new([VerifyingKey::from_public_key_pem(&pem)?]));
+    }
+
+    let mut trusted_keys = TrustedKeys::builtin();
+    match tokio::fs::read(dst_dir.join(crate::sign::consts::KEYS_FILE_NAME)).await {
+        Ok(bytes) => {
+            let list: KeyRotationListV1 = serde_json::de::from_slice(&bytes)?;
+            let applied = trusted_keys.apply_rotations(&list.rotations).len();
+            println!(
+                "Applied {applied} of {} key rotation(s)",
+                list.rotations.len()
+            );
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    Ok(trusted_keys)
+}
+
+struct Verifier {
+    client: reqwest::Client,
+    addr: SocketAddr,
+}
+
+impl Verifier {
+    fn file_path(&self, path: &str) -> String {
+        format!(
+            "{}{}",
+            arkhost_ota::consts::url::asset::ui_ota_v1::FILES,
+            path
+        )
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("http://{}/{}", self.addr, path)
+    }
+
+    async fn get(&self, path: &str) -> anyhow::Result<bytes::Bytes> {
+        Ok(self
+            .client
+            .get(self.url(path))
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?)
+    }
+
+    async fn get_signed_json<T: serde::de::DeserializeOwned>(
+        &self,
+        trusted_keys: &TrustedKeys,
+        path: &str,
+        sig_path: &str,
+    ) -> anyhow::Result<T> {
+        let bytes = self.get(path).await?;
+        let sig_bytes = self.get(sig_path).await?;
+        arkhost_ota::parse_signed_json(trusted_keys, &bytes, &sig_bytes)
+    }
+
+    /// 与客户端相同：下载前检查大小，下载后检查大小及哈希值
+    async fn download(&self, path: &str, artifact: &Artifact) -> anyhow::Result<bytes::Bytes> {
+        let path = self.file_path(path);
+        let resp = self
+            .client
+            .head(self.url(&path))
+            .send()
+            .await?
+            .error_for_status()?;
+        // HEAD 响应没有 body，需直接读取 Content-Length
+        let content_length = resp
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<u64>().ok());
+        artifact.verify_size(
+            &path,
+            content_length.ok_or(anyhow!("missing Content-Length of '{path}'"))?,
+        )?;
+
+        let bytes = self.get(&path).await?;
+        artifact.verify_content(&path, &bytes)?;
+        Ok(bytes)
+    }
+
+    async fn verify_delta(
+        &self,
+        format: PatchFormat,
+        patch_path: &str,
+        artifact: &Artifact,
+        source_bytes: &[u8],
+        release_hash: &[u8],
+    ) -> anyhow::Result<()> {
+        let patch_bytes = self.download(patch_path, artifact).await?;
+        format.patch_check_integrity(
+            source_bytes,
+            &patch_bytes,
+            release_hash,
+            sha2::Sha256::new(),
+        )?;
+        Ok(())
+    }
+}
+
+/// 仅支持 GET 与 HEAD 的静态文件服务器
+async fn serve(listener: TcpListener, root: PathBuf) {
+    loop {
+        let Ok((stream, _)) = listener.accept().await else {
+            continue;
+        };
+        let root = root.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &root).await {
+                eprintln!("serve: error handling request: {e}");
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, root: &Path) -> anyhow::Result<()> {
+    let mut buf = Vec::new();
+    while !buf.ends_with(b"\r\n\r\n") {
+        let mut chunk = [0u8; 1024];
+        let len = stream.read(&mut chunk).await?;
+        if len == 0 {
+            bail!("connection closed before request header ends");
+        }
+        buf.extend_from_slice(&chunk[..len]);
+    }
+    let header = String::from_utf8_lossy(&buf);
+    let mut request_line = header.lines().next().unwrap_or_default().split(' ');
+    let (method, path) = (
+        request_line.next().unwrap_or_default(),
+        request_line.next().unwrap_or_default(),
+    );
+
+    let rel_path = Path::new(path.trim_start_matches('/'));
+    let body = if rel_path
+        .components()
+        .all(|x| matches!(x, Component::Normal(_)))
+    {
+        tokio::fs::read(root.join(rel_path)).await.ok()
+    } else {
+        None
+    };
+    let (status, body) = match body {
+        Some(body) => ("200 OK", body),
+        None => ("404 Not Found", vec![]),
+    };
+    stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .as_bytes(),
+        )
+        .await?;
+    if method != "HEAD" {
+        stream.write_all(&body).await?;
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+pub mod tests {
+    use arkhost_ota::{bin_diff::PatchFormat, *};
+    use ed25519_dalek::{
+        pkcs8::{spki::der::pem::LineEnding, EncodePublicKey},
+        Signer, SigningKey,
+    };
+    use sha2::Digest;
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        process::Command,
+    };
+
+    #[test]
+    pub fn test_verify() {
+        let dir = test_dir("verify");
+        let source = b"closure studio 1.0.0 ".repeat(64);
+        let target = b"closure studio 1.1.0 ".repeat(64);
+        let patch_path = publish(&dir, &source, &target);
+
+        assert!(run_verify(&dir));
+
+        // patch 与清单不一致时检查失败
+        let ota_dir = dir.join("dst/ui/ota/v1");
+        let mut patch = std::fs::read(ota_dir.join(&patch_path)).unwrap();
+        *patch.last_mut().unwrap() ^= 0xff;
+        std::fs::write(ota_dir.join(&patch_path), patch).unwrap();
+        assert!(!run_verify(&dir));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_verify_untrusted_key() {
+        let dir = test_dir("untrusted_key");
+        publish(&dir, b"1.0.0", b"1.1.0");
+        let other_key = SigningKey::from_bytes(&[2; 32]);
+        std::fs::write(
+            dir.join("release.pub"),
+            other_key
+                .verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert!(!run_verify(&dir));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("publish_tool_test_{}_{name}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("dst/ui/ota/v1")).unwrap();
+        std::fs::create_dir_all(dir.join("pervious_versions")).unwrap();
+        dir
+    }
+
+    ///
+    /// 按 publish 的目录结构写入 `target` 版本及从 `source` 的 bsdiff 增量更新，返回 patch 路径
+    ///
+    fn publish(dir: &Path, source: &[u8], target: &[u8]) -> String {
+        let signing_key = SigningKey::from_bytes(&[1; 32]);
+        std::fs::write(
+            dir.join("release.pub"),
+            signing_key
+                .verifying_key()
+                .to_public_key_pem(LineEnding::LF)
+                .unwrap(),
+        )
+        .unwrap();
+        std::fs::write(dir.join("pervious_versions/old"), source).unwrap();
+
+        let ota_dir = dir.join("dst/ui/ota/v1");
+        let write = |path: &str, bytes: &[u8]| {
+            let path = ota_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, bytes).unwrap();
+        };
+        let write_signed = |path: &str, bytes: &[u8]| {
+            write(path, bytes);
+            write(&format!("{path}.sig"), &signing_key.sign(bytes).to_bytes());
+        };
+        let artifact = |bytes: &[u8]| Artifact {
+            size: bytes.len() as u64,
+            sha256: hex::encode(sha2::Sha256::digest(bytes)),
+        };
+
+        let release = Release {
+            version: "1.1.0".parse().unwrap(),
+            file: Resource {
+                path: "closure-studio".into(),
+                hash: hex::encode(sha2::Sha256::digest(target)),
+            },
+            patch_formats: vec![PatchFormat::Bsdiff],
+            rollout_percentage: None,
+        };
+        let patch = PatchFormat::Bsdiff.diff(source, target).unwrap();
+        let patch_path = file_patch_path(
+            &release.file,
+            &hex::encode(sha2::Sha256::digest(source)),
+            PatchFormat::Bsdiff,
+        );
+        write(&file_path(&release.file), target);
+        write(&patch_path, &patch);
+
+        let manifest = ReleaseManifestV1 {
+            version: release.version.clone(),
+            artifacts: HashMap::from([
+                (file_path(&release.file), artifact(target)),
+                (patch_path.clone(), artifact(&patch)),
+            ]),
+        };
+        write_signed(
+            &manifest_path(&release.file),
+            &serde_json::to_vec(&manifest).unwrap(),
+        );
+        let index = ReleaseIndexV1 {
+            branches: HashMap::from([(consts::DEFAULT_BRANCH.to_owned(), release)]),
+            serial: 1,
+            expires_at: None,
+        };
+        write_signed("index.json", &serde_json::to_vec(&index).unwrap());
+        patch_path
+    }
+
+    fn run_verify(dir: &Path) -> bool {
+        Command::new(env!("CARGO_BIN_EXE_publish-tool"))
+            .args(["verify", "--public-key", "release.pub"])
+            .current_dir(dir)
+            .status()
+            .unwrap()
+            .success()
+    }
+}
@@ -0,0 +1,503 @@
+use crate::app::{ap_planner::ApStatus, api_user_model::StatusTransition};
+use arkhost_api::models::api_arkhost::{GameStatus, StatusDetails};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// 提醒事件类型，可分别开关
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+    ApFull,
+    RecruitLicenseLow,
+    GachaTicketReached,
+    DiamondChanged,
+    StatusChanged,
+}
+
+/// 桌面提醒设置，默认关闭
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSettings {
+    pub enabled: bool,
+    /// 理智回满
+    pub ap_full: bool,
+    /// 公招券低于阈值
+    pub recruit_license: bool,
+    pub recruit_license_threshold: u32,
+    /// 寻访凭证达到阈值
+    pub gacha_ticket: bool,
+    pub gacha_ticket_threshold: u32,
+    /// 源石数量变化
+    pub diamond_changed: bool,
+    /// 托管状态变为异常及恢复运行
+    pub status_changed: bool,
+    /// 不提醒的账号
+    pub disabled_accounts: HashSet<String>,
+    /// 免打扰时段（本地时间，整点），`start` 不小于 `end` 时跨越午夜
+    pub quiet_hours_enabled: bool,
+    pub quiet_hours_start: u32,
+    pub quiet_hours_end: u32,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ap_full: true,
+            recruit_license: true,
+            recruit_license_threshold: consts::DEFAULT_RECRUIT_LICENSE_THRESHOLD,
+            gacha_ticket: true,
+            gacha_ticket_threshold: consts::DEFAULT_GACHA_TICKET_THRESHOLD,
+            diamond_changed: true,
+            status_changed: true,
+            disabled_accounts: HashSet::new(),
+            quiet_hours_enabled: false,
+            quiet_hours_start: consts::DEFAULT_QUIET_HOURS_START,
+            quiet_hours_end: consts::DEFAULT_QUIET_HOURS_END,
+        }
+    }
+}
+
+impl AlertSettings {
+    pub fn is_kind_enabled(&self, kind: AlertKind) -> bool {
+        match kind {
+            AlertKind::ApFull => self.ap_full,
+            AlertKind::RecruitLicenseLow => self.recruit_license,
+            AlertKind::GachaTicketReached => self.gacha_ticket,
+            AlertKind::DiamondChanged => self.diamond_changed,
+            AlertKind::StatusChanged => self.status_changed,
+        }
+    }
+
+    pub fn is_account_enabled(&self, account: &str) -> bool {
+        !self.disabled_accounts.contains(account)
+    }
+
+    /// `hour` 为本地时间的小时数
+    pub fn in_quiet_hours(&self, hour: u32) -> bool {
+        let (start, end) = (self.quiet_hours_start, self.quiet_hours_end);
+        if !self.quiet_hours_enabled || start == end {
+            false
+        } else if start < end {
+            (start..end).contains(&hour)
+        } else {
+            hour >= start || hour < end
+        }
+    }
+
+    pub fn should_notify(&self, account: &str, kind: AlertKind, hour: u32) -> bool {
+        self.enabled
+            && self.is_kind_enabled(kind)
+            && self.is_account_enabled(account)
+            && !self.in_quiet_hours(hour)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlertEvent {
+    ApFull {
+        max_ap: u32,
+    },
+    RecruitLicenseLow {
+        count: u32,
+    },
+    GachaTicketReached {
+        count: u32,
+    },
+    DiamondChanged {
+        from: u32,
+        to: u32,
+    },
+    StatusChanged {
+        from: GameStatus,
+        to: GameStatus,
+    },
+    /// 由异常状态恢复运行
+    Recovered {
+        from: GameStatus,
+    },
+}
+
+impl AlertEvent {
+    pub fn kind(&self) -> AlertKind {
+        match self {
+            Self::ApFull { .. } => AlertKind::ApFull,
+            Self::RecruitLicenseLow { .. } => AlertKind::RecruitLicenseLow,
+            Self::GachaTicketReached { .. } => AlertKind::GachaTicketReached,
+            Self::DiamondChanged { .. } => AlertKind::DiamondChanged,
+            Self::StatusChanged { .. } | Self::Recovered { .. } => AlertKind::StatusChanged,
+        }
+    }
+
+    pub fn summary(&self) -> &'static str {
+        match self {
+            Self::ApFull { .. } => "理智已回满",
+            Self::RecruitLicenseLow { .. } => "公招券不足",
+            Self::GachaTicketReached { .. } => "寻访凭证已达到设定数量",
+            Self::DiamondChanged { .. } => "源石数量变化",
+            Self::StatusChanged { .. } => "托管状态异常",
+            Self::Recovered { .. } => "托管已恢复运行",
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            Self::ApFull { max_ap } => format!("理智已达上限 {max_ap}"),
+            Self::RecruitLicenseLow { count } => format!("剩余公招券 {count}"),
+            Self::GachaTicketReached { count } => format!("当前寻访凭证 {count}"),
+            Self::DiamondChanged { from, to } => format!("源石 {from} → {to}"),
+            Self::StatusChanged { to, .. } => format!("当前状态：{}", status_str(*to)),
+            Self::Recovered { from } => format!("此前状态：{}", status_str(*from)),
+        }
+    }
+}
+
+pub fn status_str(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::LoginFailed => "登录失败",
+        GameStatus::Pending => "未启动",
+        GameStatus::Logging => "登录中",
+        GameStatus::Running => "运行中",
+        GameStatus::Error => "出现错误",
+        GameStatus::ErrorLoggedOut => "已被登出",
+        GameStatus::ErrorBattleFailed => "作战失败",
+        GameStatus::ErrorCaptchaTimedOut => "验证超时",
+        GameStatus::Captcha => "需要验证",
+    }
+}
+
+/// 需要提醒的异常状态，`Error` 通常随即重试，不提醒
+fn is_faulted(status: GameStatus) -> bool {
+    matches!(
+        status,
+        GameStatus::LoginFailed
+            | GameStatus::ErrorLoggedOut
+            | GameStatus::ErrorBattleFailed
+            | GameStatus::ErrorCaptchaTimedOut
+            | GameStatus::Captcha
+    )
+}
+
+/// 托管状态提醒的冷却键，异常与恢复分别按对应的异常状态计算
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StatusAlertKey {
+    Faulted(i32),
+    Recovered(i32),
+}
+
+/// 账号上次检查时的状态，资源字段在获取到详情前为 `None`
+#[derive(Debug, Clone)]
+struct Snapshot {
+    /// 已处理的 [`StatusTransition::seq`]
+    status_seq: u64,
+    /// 尚未恢复运行的异常状态
+    faulted: Option<GameStatus>,
+    /// 各托管状态提醒最近一次的时间，用于抑制反复变化的状态
+    status_alerted_at: HashMap<StatusAlertKey, DateTime<Utc>>,
+    ap_full: Option<bool>,
+    recruit_license: Option<u32>,
+    gacha_ticket: Option<u32>,
+    diamond: Option<u32>,
+}
+
+///
+/// 记录各账号上次检查时的状态，比较后产生提醒事件
+///
+/// 仅在状态跨越阈值时产生事件，因此同一状态不会重复提醒；首次检查的账号及字段仅记录状态。
+/// 托管状态的变化由 `ApiUser` 记录，每次变化至多提醒一次，
+/// 且同一异常状态（及由其恢复）在 [`consts::status_alert_cooldown`] 内不重复提醒
+///
+#[derive(Debug, Default)]
+pub struct AlertTracker {
+    snapshots: HashMap<String, Snapshot>,
+}
+
+impl AlertTracker {
+    pub fn update(
+        &mut self,
+        account: &str,
+        status: GameStatus,
+        transitions: impl IntoIterator<Item = StatusTransition>,
+        details: Option<&StatusDetails>,
+        settings: &AlertSettings,
+        now: DateTime<Utc>,
+    ) -> Vec<AlertEvent> {
+        let mut events = vec![];
+        let transitions: Vec<_> = transitions.into_iter().collect();
+        let current = Snapshot {
+            status_seq: transitions.last().map_or(0, |x| x.seq),
+            faulted: is_faulted(status).then_some(status),
+            status_alerted_at: HashMap::new(),
+            ap_full: details.map(|x| {
+                let ap_status = ApStatus::from(x);
+                ap_status.current_ap(now) >= ap_status.max_ap
+            }),
+            recruit_license: details.map(|x| x.recruit_license),
+            gacha_ticket: details.map(|x| x.gacha_ticket),
+            diamond: details.map(|x| x.android_diamond),
+        };
+        let Some(previous) = self.snapshots.get_mut(account) else {
+            self.snapshots.insert(account.to_owned(), current);
+            return events;
+        };
+
+        // 两次检查之间可能发生多次变化，逐一处理以免遗漏中间的异常状态
+        for transition in transitions.iter().filter(|x| x.seq > previous.status_seq) {
+            previous.status_seq = transition.seq;
+            let event = match transition.to {
+                to if is_faulted(to) => {
+                    previous.faulted = Some(to);
+                    Some((
+                        AlertEvent::StatusChanged {
+                            from: transition.from,
+                            to,
+                        },
+                        StatusAlertKey::Faulted(to as i32),
+                    ))
+                }
+                GameStatus::Running => previous.faulted.map(|from| {
+                    (
+                        AlertEvent::Recovered { from },
+                        StatusAlertKey::Recovered(from as i32),
+                    )
+                }),
+                // 手动停止后不再提醒恢复
+                GameStatus::Pending => {
+                    previous.faulted = None;
+                    None
+                }
+                _ => None,
+            };
+            let Some((event, key)) = event else {
+                continue;
+            };
+            let recently_alerted = previous
+                .status_alerted_at
+                .get(&key)
+                .is_some_and(|x| now - *x < consts::status_alert_cooldown());
+            if recently_alerted {
+                continue;
+            }
+            // 被抑制的恢复提醒保留异常状态，之后恢复时仍可提醒
+            if let AlertEvent::Recovered { .. } = event {
+                previous.faulted = None;
+            }
+            previous.status_alerted_at.insert(key, now);
+            events.push(event);
+        }
+        if let (Some(false), Some(true), Some(details)) =
+            (previous.ap_full, current.ap_full, details)
+        {
+            events.push(AlertEvent::ApFull {
+                max_ap: details.max_ap,
+            });
+        }
+        if let (Some(from), Some(to)) = (previous.recruit_license, current.recruit_license) {
+            let threshold = settings.recruit_license_threshold;
+            if from >= threshold && to < threshold {
+                events.push(AlertEvent::RecruitLicenseLow { count: to });
+            }
+        }
+        if let (Some(from), Some(to)) = (previous.gacha_ticket, current.gacha_ticket) {
+            let threshold = settings.gacha_ticket_threshold;
+            if from < threshold && to >= threshold {
+                events.push(AlertEvent::GachaTicketReached { count: to });
+            }
+        }
+        if let (Some(from), Some(to)) = (previous.diamond, current.diamond) {
+            if from != to {
+                events.push(AlertEvent::DiamondChanged { from, to });
+            }
+        }
+
+        // 未获取到详情时保留上次的资源状态
+        if details.is_some() {
+            previous.ap_full = current.ap_full;
+            previous.recruit_license = current.recruit_license;
+            previous.gacha_ticket = current.gacha_ticket;
+            previous.diamond = current.diamond;
+        }
+        events
+    }
+
+    /// 移除已删除的账号
+    pub fn retain(&mut self, accounts: &HashSet<String>) {
+        self.snapshots.retain(|k, _| accounts.contains(k));
+    }
+}
+
+pub mod consts {
+    pub const DEFAULT_RECRUIT_LICENSE_THRESHOLD: u32 = 10;
+    pub const DEFAULT_GACHA_TICKET_THRESHOLD: u32 = 10;
+    pub const DEFAULT_QUIET_HOURS_START: u32 = 23;
+    pub const DEFAULT_QUIET_HOURS_END: u32 = 7;
+
+    /// 同一托管状态提醒的最短间隔
+    pub fn status_alert_cooldown() -> chrono::Duration {
+        chrono::Duration::minutes(10)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details(ap: u32, recruit_license: u32, gacha_ticket: u32, diamond: u32) -> StatusDetails {
+        StatusDetails {
+            ap,
+            max_ap: 135,
+            last_ap_add_time: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+            recruit_license,
+            gacha_ticket,
+            android_diamond: diamond,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_alert_tracker() {
+        let settings = AlertSettings::default();
+        let mut tracker = AlertTracker::default();
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut update = |status, details: Option<&StatusDetails>, now| {
+            tracker.update("G1", status, [], details, &settings, now)
+        };
+
+        // 首次检查仅记录状态
+        assert!(update(GameStatus::Running, Some(&details(100, 10, 9, 5)), t0).is_empty());
+        assert!(update(GameStatus::Running, Some(&details(100, 10, 9, 5)), t0).is_empty());
+        // 按恢复速度推算回满，仅提醒一次
+        let full = t0 + chrono::Duration::minutes(35 * 6);
+        assert_eq!(
+            update(GameStatus::Running, Some(&details(100, 10, 9, 5)), full),
+            [AlertEvent::ApFull { max_ap: 135 }]
+        );
+        assert!(update(GameStatus::Running, None, full).is_empty());
+        assert_eq!(
+            update(GameStatus::Running, Some(&details(100, 9, 10, 6)), full),
+            [
+                AlertEvent::RecruitLicenseLow { count: 9 },
+                AlertEvent::GachaTicketReached { count: 10 },
+                AlertEvent::DiamondChanged { from: 5, to: 6 },
+            ]
+        );
+        assert!(update(GameStatus::Running, Some(&details(100, 8, 11, 6)), full).is_empty());
+    }
+
+    #[test]
+    fn test_status_transitions() {
+        use GameStatus::*;
+        let settings = AlertSettings::default();
+        let mut tracker = AlertTracker::default();
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let t1 = t0 + chrono::Duration::minutes(1);
+        let t2 = t0 + chrono::Duration::minutes(30);
+        let mut update = |from, to, seq, now| {
+            let transition = StatusTransition { from, to, seq };
+            tracker.update("G1", to, [transition], None, &settings, now)
+        };
+
+        // 首次检查仅记录状态
+        assert!(update(Logging, Running, 1, t0).is_empty());
+        assert_eq!(
+            update(Running, ErrorLoggedOut, 2, t0),
+            [AlertEvent::StatusChanged {
+                from: Running,
+                to: ErrorLoggedOut
+            }]
+        );
+        // 同一次变化不重复提醒
+        assert!(update(Running, ErrorLoggedOut, 2, t0).is_empty());
+        assert!(update(ErrorLoggedOut, Logging, 3, t0).is_empty());
+        assert_eq!(
+            update(Logging, Running, 4, t0),
+            [AlertEvent::Recovered {
+                from: ErrorLoggedOut
+            }]
+        );
+        // 反复变化的状态在间隔内仅提醒一次，恢复与异常分别计算
+        assert_eq!(
+            update(Running, ErrorBattleFailed, 5, t1),
+            [AlertEvent::StatusChanged {
+                from: Running,
+                to: ErrorBattleFailed
+            }]
+        );
+        assert_eq!(
+            update(ErrorBattleFailed, Running, 6, t1),
+            [AlertEvent::Recovered {
+                from: ErrorBattleFailed
+            }]
+        );
+        assert!(update(Running, ErrorBattleFailed, 7, t1).is_empty());
+        assert!(update(ErrorBattleFailed, Running, 8, t1).is_empty());
+        // 被抑制的恢复在间隔后再次恢复时提醒
+        assert!(update(Running, Logging, 9, t2).is_empty());
+        assert_eq!(
+            update(Logging, Running, 10, t2),
+            [AlertEvent::Recovered {
+                from: ErrorBattleFailed
+            }]
+        );
+        // `Error` 不提醒，手动停止后不提醒恢复
+        assert!(update(Running, Error, 11, t2).is_empty());
+        assert_eq!(update(Error, Captcha, 12, t2).len(), 1);
+        assert!(update(Captcha, Pending, 13, t2).is_empty());
+        assert!(update(Pending, Running, 14, t2).is_empty());
+    }
+
+    #[test]
+    fn test_multiple_status_transitions() {
+        use GameStatus::*;
+        let settings = AlertSettings::default();
+        let mut tracker = AlertTracker::default();
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let transition = |from, to, seq| StatusTransition { from, to, seq };
+
+        let mut transitions = vec![transition(Logging, Running, 1)];
+        assert!(tracker
+            .update("G1", Running, transitions.clone(), None, &settings, t0)
+            .is_empty());
+        // 两次检查之间的异常状态不被遗漏
+        transitions.push(transition(Running, Captcha, 2));
+        transitions.push(transition(Captcha, Logging, 3));
+        assert_eq!(
+            tracker.update("G1", Logging, transitions.clone(), None, &settings, t0),
+            [AlertEvent::StatusChanged {
+                from: Running,
+                to: Captcha
+            }]
+        );
+        transitions.push(transition(Logging, Running, 4));
+        assert_eq!(
+            tracker.update("G1", Running, transitions, None, &settings, t0),
+            [AlertEvent::Recovered { from: Captcha }]
+        );
+    }
+
+    #[test]
+    fn test_alert_settings() {
+        let mut settings = AlertSettings {
+            enabled: true,
+            quiet_hours_enabled: true,
+            ..Default::default()
+        };
+        assert!(settings.in_quiet_hours(23));
+        assert!(settings.in_quiet_hours(0));
+        assert!(!settings.in_quiet_hours(7));
+        assert!(!settings.should_notify("G1", AlertKind::ApFull, 2));
+        assert!(settings.should_notify("G1", AlertKind::ApFull, 12));
+
+        settings.quiet_hours_start = 1;
+        settings.quiet_hours_end = 5;
+        assert!(settings.in_quiet_hours(1));
+        assert!(!settings.in_quiet_hours(5));
+        assert!(!settings.in_quiet_hours(23));
+
+        settings.disabled_accounts.insert("G1".into());
+        settings.diamond_changed = false;
+        assert!(!settings.should_notify("G1", AlertKind::ApFull, 12));
+        assert!(!settings.should_notify("G2", AlertKind::DiamondChanged, 12));
+        assert!(settings.should_notify("G2", AlertKind::StatusChanged, 12));
+    }
+}
@@ -0,0 +1,168 @@
+use arkhost_api::models::api_arkhost::StatusDetails;
+use chrono::{DateTime, Duration, Utc};
+
+/// 理智状态，`ap` 为 `last_ap_add_time` 时的理智
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApStatus {
+    pub ap: u32,
+    pub max_ap: u32,
+    pub last_ap_add_time: DateTime<Utc>,
+}
+
+impl From<&StatusDetails> for ApStatus {
+    fn from(details: &StatusDetails) -> Self {
+        Self {
+            ap: details.ap,
+            max_ap: details.max_ap,
+            last_ap_add_time: details.last_ap_add_time,
+        }
+    }
+}
+
+impl ApStatus {
+    /// 当前理智，自然恢复不超过上限；超出上限的理智（如使用药剂）保持不变
+    pub fn current_ap(&self, now: DateTime<Utc>) -> u32 {
+        if self.ap >= self.max_ap {
+            return self.ap;
+        }
+        let elapsed = (now - self.last_ap_add_time).num_seconds().max(0);
+        let recovered = u32::try_from(elapsed / consts::AP_RECOVERY_SECS).unwrap_or(u32::MAX);
+        self.ap.saturating_add(recovered).min(self.max_ap)
+    }
+
+    /// 自然恢复至上限的时间，`last_ap_add_time` 时已满则为 `None`
+    pub fn full_at(&self) -> Option<DateTime<Utc>> {
+        if self.ap >= self.max_ap {
+            return None;
+        }
+        let seconds = (self.max_ap - self.ap) as i64 * consts::AP_RECOVERY_SECS;
+        Some(self.last_ap_add_time + Duration::seconds(seconds))
+    }
+
+    /// 距离回满的时间，已满时为 0
+    pub fn time_until_full(&self, now: DateTime<Utc>) -> Duration {
+        self.full_at()
+            .map_or(Duration::zero(), |x| (x - now).max(Duration::zero()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApPlan {
+    pub current_ap: u32,
+    pub max_ap: u32,
+    pub time_until_full: Duration,
+    /// 规划时段内可用于作战的理智
+    pub budget: u32,
+}
+
+impl ApPlan {
+    ///
+    /// 规划 `period` 内可用于作战的理智：当前理智与期间恢复的理智之和中超出 `keeping_ap` 的部分
+    ///
+    /// 假设理智超出保留值时即被消耗，因此期间不会因回满而停止恢复；
+    /// 保留值不低于上限时不会作战
+    ///
+    pub fn new(status: &ApStatus, keeping_ap: i32, now: DateTime<Utc>, period: Duration) -> Self {
+        let current_ap = status.current_ap(now);
+        let keeping_ap = keeping_ap.max(0) as u32;
+        let budget = if keeping_ap >= status.max_ap {
+            0
+        } else {
+            let recovered = period.num_seconds().max(0) / consts::AP_RECOVERY_SECS;
+            current_ap
+                .saturating_add(u32::try_from(recovered).unwrap_or(u32::MAX))
+                .saturating_sub(keeping_ap)
+        };
+        Self {
+            current_ap,
+            max_ap: status.max_ap,
+            time_until_full: status.time_until_full(now),
+            budget,
+        }
+    }
+
+    /// 理智全部用于消耗 `ap_cost` 的关卡时的作战次数
+    pub fn runs(&self, ap_cost: i32) -> u32 {
+        match u32::try_from(ap_cost) {
+            Ok(ap_cost) if ap_cost > 0 => self.budget / ap_cost,
+            _ => 0,
+        }
+    }
+}
+
+pub mod consts {
+    /// 每恢复 1 点理智所需的秒数
+    pub const AP_RECOVERY_SECS: i64 = 6 * 60;
+
+    /// 默认的规划时段
+    pub fn plan_period() -> chrono::Duration {
+        chrono::Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(ap: u32, max_ap: u32) -> ApStatus {
+        ApStatus {
+            ap,
+            max_ap,
+            last_ap_add_time: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_current_ap() {
+        let status = status(100, 135);
+        let t0 = status.last_ap_add_time;
+        assert_eq!(status.current_ap(t0), 100);
+        assert_eq!(status.current_ap(t0 + Duration::seconds(359)), 100);
+        assert_eq!(status.current_ap(t0 + Duration::minutes(60)), 110);
+        assert_eq!(status.current_ap(t0 + Duration::days(1)), 135);
+        // 时钟偏差
+        assert_eq!(status.current_ap(t0 - Duration::minutes(60)), 100);
+        // 超出上限
+        let over = ApStatus { ap: 200, ..status };
+        assert_eq!(over.current_ap(t0 + Duration::days(1)), 200);
+        assert_eq!(over.full_at(), None);
+    }
+
+    #[test]
+    fn test_full_at() {
+        let status = status(100, 135);
+        let t0 = status.last_ap_add_time;
+        assert_eq!(status.full_at(), Some(t0 + Duration::minutes(35 * 6)));
+        assert_eq!(
+            status.time_until_full(t0 + Duration::minutes(10)),
+            Duration::minutes(200)
+        );
+        assert_eq!(
+            status.time_until_full(t0 + Duration::days(1)),
+            Duration::zero()
+        );
+    }
+
+    #[test]
+    fn test_ap_plan() {
+        let status = status(100, 135);
+        let t0 = status.last_ap_add_time;
+        let plan = ApPlan::new(&status, 20, t0, consts::plan_period());
+        assert_eq!(plan.current_ap, 100);
+        assert_eq!(plan.budget, 80 + 240);
+        assert_eq!(plan.runs(6), 53);
+        assert_eq!(plan.runs(21), 15);
+        assert_eq!(plan.runs(0), 0);
+        assert_eq!(plan.runs(-1), 0);
+
+        // 当前理智低于保留值，恢复的理智先补足保留值
+        let plan = ApPlan::new(&status, 120, t0, Duration::hours(1));
+        assert_eq!(plan.budget, 0);
+        let plan = ApPlan::new(&status, 120, t0, Duration::hours(3));
+        assert_eq!(plan.budget, 10);
+        // 保留值不低于上限
+        let plan = ApPlan::new(&status, 135, t0, consts::plan_period());
+        assert_eq!(plan.budget, 0);
+        assert_eq!(plan.runs(6), 0);
+    }
+}
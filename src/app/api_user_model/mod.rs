@@ -20,7 +20,17 @@ impl OrderedMapEntry<api_arkhost::GameInfo> for GameRef {
     }
 
     async fn update(&self, new_val: api_arkhost::GameInfo) -> () {
-        self.game.write().await.info = new_val;
+        let mut game = self.game.write().await;
+        let (from, to) = (game.info.status.code, new_val.status.code);
+        if from != to {
+            let seq = game.status_transitions.back().map_or(1, |x| x.seq + 1);
+            if game.status_transitions.len() >= consts::MAX_STATUS_TRANSITIONS {
+                game.status_transitions.pop_front();
+            }
+            game.status_transitions
+                .push_back(StatusTransition { from, to, seq });
+        }
+        game.info = new_val;
     }
 }
 
@@ -45,9 +55,19 @@ pub struct InnerGameRef {
     pub game: RwLock<GameEntry>,
 }
 
+/// 托管状态的变化，`seq` 为该账号状态变化的次数，用于判断变化是否已处理
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StatusTransition {
+    pub from: api_arkhost::GameStatus,
+    pub to: api_arkhost::GameStatus,
+    pub seq: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct GameEntry {
     pub info: api_arkhost::GameInfo,
+    /// 最近几次托管状态的变化，按 `seq` 递增；两次处理之间可能发生多次变化，需按 `seq` 逐一处理
+    pub status_transitions: VecDeque<StatusTransition>,
     pub details: Option<api_arkhost::GameDetails>,
     pub logs: VecDeque<api_arkhost::LogEntry>,
     pub log_cursor_back: u64,
@@ -59,6 +79,7 @@ impl GameEntry {
     pub fn new(info: api_arkhost::GameInfo) -> Self {
         Self {
             info,
+            status_transitions: VecDeque::with_capacity(consts::MAX_STATUS_TRANSITIONS),
             details: None,
             logs: VecDeque::with_capacity(512),
             log_cursor_back: 0,
@@ -237,3 +258,8 @@ impl ApiUser {
         }
     }
 }
+
+pub mod consts {
+    /// 每个账号保留的托管状态变化记录数
+    pub const MAX_STATUS_TRANSITIONS: usize = 16;
+}
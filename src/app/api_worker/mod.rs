@@ -1,7 +1,6 @@
 use anyhow::Context;
 use arkhost_api::clients::arkhost::EventSourceClient;
 use arkhost_api::clients::{self, common::ApiResult};
-use arkhost_api::consts;
 use arkhost_api::models::{api_arkhost, api_passport, api_quota};
 use derivative::Derivative;
 use std::sync::Arc;
@@ -9,6 +8,7 @@ use tokio::sync::{mpsc, oneshot};
 use tokio_util::sync::CancellationToken;
 
 use super::api_user_model::{ApiUser, GameRef};
+use super::{app_config, diagnostics, log_archive};
 
 #[derive(Debug)]
 pub enum RetrieveLogSpec {
@@ -103,6 +103,14 @@ pub enum Operation {
     },
 }
 
+/// 返回命令结果，并记录请求错误
+fn reply<T>(operation: &str, resp: Responder<T>, result: CommandResult<T>) {
+    if let Err(e) = &result {
+        diagnostics::record_api_error(operation, e);
+    }
+    _ = resp.send(result);
+}
+
 #[derive(Debug)]
 pub struct Command {
     pub user: Arc<ApiUser>,
@@ -118,18 +126,19 @@ pub struct Worker {
 
 impl Worker {
     pub fn new(auth_client: clients::id_server::AuthClient) -> Self {
+        let app_config = app_config::get();
         Self {
             auth_client: Arc::new(auth_client.clone()),
             arkhost_client: Arc::new(clients::arkhost::Client::new(
-                consts::arkhost::API_BASE_URL,
+                &app_config.arkhost_server.value,
                 auth_client.clone(),
             )),
             registry_client: Arc::new(clients::quota::Client::new(
-                consts::quota::API_BASE_URL,
+                &app_config.registry_server.value,
                 auth_client.clone(),
             )),
             eventsource_client: Arc::new(clients::arkhost::EventSourceClient::new(
-                consts::arkhost::API_BASE_URL,
+                &app_config.arkhost_server.value,
                 auth_client,
             )),
         }
@@ -208,7 +217,7 @@ impl Worker {
         let game_ref = user_model.find_game(&account).await?;
         let mut game = game_ref.game.write().await;
 
-        match spec {
+        let new_logs = match spec {
             RetrieveLogSpec::Latest {} => {
                 let mut latest_logs = vec![];
                 let mut latest_log_cursor_back = 0;
@@ -237,9 +246,10 @@ impl Worker {
                         .map_or(latest_logs_truncate_len, |(i, _x)| i);
                 }
                 latest_logs.truncate(latest_logs_truncate_len);
-                for log_entry in latest_logs.into_iter().rev() {
-                    game.logs.push_front(log_entry);
+                for log_entry in latest_logs.iter().rev() {
+                    game.logs.push_front(log_entry.clone());
                 }
+                latest_logs
             }
             RetrieveLogSpec::Former {} => {
                 let new_logs = self
@@ -247,15 +257,31 @@ impl Worker {
                     .get_logs(&account, game.log_cursor_back)
                     .await?;
 
-                for log_entry in new_logs.logs.into_iter() {
-                    game.logs.push_back(log_entry);
+                for log_entry in new_logs.logs.iter() {
+                    game.logs.push_back(log_entry.clone());
                 }
+                new_logs.logs
             }
-        }
+        };
         game.log_cursor_front = game.logs.front().map_or(0, |x| x.id);
         game.log_cursor_back = game.logs.back().map_or(0, |x| x.id);
         drop(game);
 
+        if !new_logs.is_empty() {
+            // 等待存档完成，保证之后的搜索及导出能读取到这些日志
+            let archive_account = account.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                log_archive::archive(&archive_account, &new_logs)?;
+                log_archive::prune(&archive_account, &log_archive::Retention::default())
+            })
+            .await;
+            match result {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => log::error!("retrieve_log: error archiving logs of {account}: {e}"),
+                Err(e) => log::error!("retrieve_log: error joining archive task: {e}"),
+            }
+        }
+
         Ok(game_ref)
     }
 
@@ -323,58 +349,94 @@ impl Worker {
                 email,
                 password,
                 resp,
-            } => _ = resp.send(self.login(email, password).await),
+            } => reply("Login", resp, self.login(email, password).await),
             Operation::GetUserStateData { resp } => {
-                _ = resp.send(
+                reply(
+                    "GetUserStateData",
+                    resp,
                     self.auth_client
                         .user_state_data()
                         .context("no user state data found"),
                 );
             }
-            Operation::Auth { resp } => _ = resp.send(self.auth().await),
+            Operation::Auth { resp } => reply("Auth", resp, self.auth().await),
             Operation::GetAuthServerUserInfo { resp } => {
-                _ = resp.send(self.get_auth_server_user_info().await);
+                reply(
+                    "GetAuthServerUserInfo",
+                    resp,
+                    self.get_auth_server_user_info().await,
+                );
             }
             Operation::SubmitSmsVerifyCode { code, resp } => {
-                _ = resp.send(self.submit_sms_verify_code(code).await);
+                reply(
+                    "SubmitSmsVerifyCode",
+                    resp,
+                    self.submit_sms_verify_code(code).await,
+                );
             }
             Operation::GetQQVerifyCode { resp } => {
-                _ = resp.send(self.get_qq_verify_code().await);
+                reply("GetQQVerifyCode", resp, self.get_qq_verify_code().await);
             }
             Operation::RefreshToken { resp } => {
-                _ = resp.send(self.refresh_token().await);
+                reply("RefreshToken", resp, self.refresh_token().await);
             }
-            Operation::Logout { resp } => _ = resp.send(self.logout().await),
-            Operation::RetrieveGames { resp } => _ = resp.send(self.retrieve_games(cmd.user).await),
-            Operation::RetrieveGameDetails { account, resp } => {
-                _ = resp.send(self.retrieve_game_details(cmd.user, account).await)
+            Operation::Logout { resp } => reply("Logout", resp, self.logout().await),
+            Operation::RetrieveGames { resp } => {
+                reply("RetrieveGames", resp, self.retrieve_games(cmd.user).await)
             }
+            Operation::RetrieveGameDetails { account, resp } => reply(
+                "RetrieveGameDetails",
+                resp,
+                self.retrieve_game_details(cmd.user, account).await,
+            ),
             Operation::RetrieveLog {
                 account,
                 spec,
                 resp,
-            } => _ = resp.send(self.retrieve_log(cmd.user, account, spec).await),
+            } => reply(
+                "RetrieveLog",
+                resp,
+                self.retrieve_log(cmd.user, account, spec).await,
+            ),
             Operation::StartGame {
                 account,
                 captcha_token,
                 resp,
-            } => _ = resp.send(self.start_game(account, captcha_token).await),
-            Operation::StopGame { account, resp } => _ = resp.send(self.stop_game(account).await),
+            } => reply(
+                "StartGame",
+                resp,
+                self.start_game(account, captcha_token).await,
+            ),
+            Operation::StopGame { account, resp } => {
+                reply("StopGame", resp, self.stop_game(account).await)
+            }
             Operation::RestartGame { account, resp } => {
-                _ = resp.send(self.restart_game(account).await)
+                reply("RestartGame", resp, self.restart_game(account).await)
             }
             Operation::UpdateGameSettings {
                 account,
                 config,
                 resp,
-            } => _ = resp.send(self.update_game_settings(account, config).await),
+            } => reply(
+                "UpdateGameSettings",
+                resp,
+                self.update_game_settings(account, config).await,
+            ),
             Operation::PreformCaptcha {
                 account,
                 captcha_info,
                 resp,
-            } => _ = resp.send(self.update_captcha_info(account, captcha_info).await),
+            } => reply(
+                "PreformCaptcha",
+                resp,
+                self.update_captcha_info(account, captcha_info).await,
+            ),
             Operation::GetRegistryUserInfo { resp } => {
-                _ = resp.send(self.get_registry_user_info().await);
+                reply(
+                    "GetRegistryUserInfo",
+                    resp,
+                    self.get_registry_user_info().await,
+                );
             }
             Operation::UpdateSlotAccount {
                 slot_uuid,
@@ -382,19 +444,21 @@ impl Worker {
                 request,
                 resp,
             } => {
-                _ = resp.send(
+                reply(
+                    "UpdateSlotAccount",
+                    resp,
                     self.update_slot_account(slot_uuid, captcha_token, request)
                         .await,
                 );
             }
-            Operation::ConnectGameEventSource { resp } => {
-                _ = resp.send(
-                    self.eventsource_client
-                        .connect_games_sse(EventSourceClient::build_default_client),
-                )
-            }
+            Operation::ConnectGameEventSource { resp } => reply(
+                "ConnectGameEventSource",
+                resp,
+                self.eventsource_client
+                    .connect_games_sse(EventSourceClient::build_default_client),
+            ),
             Operation::GetSiteConfig { resp } => {
-                _ = resp.send(self.get_site_config().await);
+                reply("GetSiteConfig", resp, self.get_site_config().await);
             }
         }
     }
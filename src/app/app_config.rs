@@ -0,0 +1,367 @@
+use super::{
+    app_state::model::UserConfig,
+    env,
+    program_options::LaunchArgs,
+    utils::{data_dir, notification},
+};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{fmt, path::PathBuf, sync::OnceLock};
+
+///
+/// 配置文件（TOML）内容，所有项均可省略
+///
+/// ```toml
+/// log_level = "info,closure_studio=debug"
+/// proxy = "http://127.0.0.1:7890"
+/// data_saver_mode = true
+/// beta_channel = false
+///
+/// [endpoints]
+/// asset = "https://assets.ltsc.vip/"
+///
+/// [notification]
+/// enabled = true
+/// timeout_ms = 4000
+/// ```
+///
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConfigFile {
+    /// env_logger 过滤规则，同 `RUST_LOG`
+    pub log_level: Option<String>,
+    /// HTTP 请求使用的代理
+    pub proxy: Option<String>,
+    /// 启动时覆盖应用内的流量节省模式设置
+    pub data_saver_mode: Option<bool>,
+    /// 启动时覆盖应用内的 Beta 版本更新设置
+    pub beta_channel: Option<bool>,
+    pub endpoints: EndpointsConfig,
+    pub notification: NotificationConfig,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EndpointsConfig {
+    pub passport: Option<String>,
+    pub arkhost: Option<String>,
+    pub registry: Option<String>,
+    pub asset: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub enabled: Option<bool>,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigSource {
+    Cli,
+    Env,
+    File,
+    Default,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cli => "命令行参数",
+            Self::Env => "环境变量",
+            Self::File => "配置文件",
+            Self::Default => "默认值",
+        })
+    }
+}
+
+/// 生效的配置值及其来源
+#[derive(Debug, Clone)]
+pub struct Layered<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+impl<T> Layered<T> {
+    /// 优先级：命令行参数 > 环境变量 > 配置文件 > 默认值
+    fn resolve(cli: Option<T>, env: Option<T>, file: Option<T>, default: T) -> Self {
+        [
+            (cli, ConfigSource::Cli),
+            (env, ConfigSource::Env),
+            (file, ConfigSource::File),
+        ]
+        .into_iter()
+        .find_map(|(value, source)| value.map(|value| Self { value, source }))
+        .unwrap_or(Self {
+            value: default,
+            source: ConfigSource::Default,
+        })
+    }
+}
+
+impl<T> Layered<Option<T>> {
+    fn resolve_optional(cli: Option<T>, env: Option<T>, file: Option<T>) -> Self {
+        Self::resolve(cli.map(Some), env.map(Some), file.map(Some), None)
+    }
+}
+
+///
+/// 合并命令行参数、环境变量、配置文件及默认值后生效的配置
+///
+/// 引导进程的命令行参数以环境变量的形式传递给子进程，故子进程中仅需读取环境变量
+///
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    pub path: PathBuf,
+    pub file_exists: bool,
+    /// 配置文件读取或解析失败的原因，失败时配置文件中的所有项均被忽略
+    pub file_error: Option<String>,
+    pub passport_server: Layered<String>,
+    pub arkhost_server: Layered<String>,
+    pub registry_server: Layered<String>,
+    pub asset_server: Layered<String>,
+    pub log_level: Layered<Option<String>>,
+    pub proxy: Layered<Option<String>>,
+    pub data_saver_mode: Layered<Option<bool>>,
+    pub beta_channel: Layered<Option<bool>>,
+    pub notification_enabled: Layered<bool>,
+    pub notification_timeout_ms: Layered<u64>,
+}
+
+impl AppConfig {
+    pub fn load(launch_args: Option<&LaunchArgs>) -> Self {
+        let path = launch_args
+            .and_then(|x| x.config.as_ref())
+            .map(PathBuf::from)
+            .or(env::config_path().map(PathBuf::from))
+            .unwrap_or_else(|| data_dir::data_dir().join(consts::CONFIG_FILE_NAME));
+        let (file, file_exists, file_error) = match std::fs::read_to_string(&path) {
+            Ok(content) => match toml::from_str::<ConfigFile>(&content) {
+                Ok(file) => (file, true, None),
+                Err(e) => (ConfigFile::default(), true, Some(e.to_string())),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                (ConfigFile::default(), false, None)
+            }
+            Err(e) => (ConfigFile::default(), true, Some(e.to_string())),
+        };
+        Self::resolve(path, file_exists, file_error, file, launch_args)
+    }
+
+    fn resolve(
+        path: PathBuf,
+        file_exists: bool,
+        file_error: Option<String>,
+        file: ConfigFile,
+        launch_args: Option<&LaunchArgs>,
+    ) -> Self {
+        // 地址无效时客户端无法创建，此处忽略配置文件中无效的值
+        let valid_url = |file: Option<String>| {
+            file.filter(|x| match url::Url::parse(x) {
+                Ok(_) => true,
+                Err(e) => {
+                    warn!("ignoring invalid endpoint '{x}' in config file: {e}");
+                    false
+                }
+            })
+        };
+        let endpoint = |file: Option<String>, default: &str| {
+            Layered::resolve(None, None, valid_url(file), default.to_owned())
+        };
+        let asset_server = Layered::resolve(
+            launch_args.and_then(|x| x.asset_server.clone()),
+            env::override_asset_server().map(|x| x.to_owned()),
+            valid_url(file.endpoints.asset),
+            arkhost_api::consts::asset::API_BASE_URL.to_owned(),
+        );
+
+        Self {
+            path,
+            file_exists,
+            file_error,
+            passport_server: endpoint(
+                file.endpoints.passport,
+                arkhost_api::consts::passport::API_BASE_URL,
+            ),
+            arkhost_server: endpoint(
+                file.endpoints.arkhost,
+                arkhost_api::consts::arkhost::API_BASE_URL,
+            ),
+            registry_server: endpoint(
+                file.endpoints.registry,
+                arkhost_api::consts::quota::API_BASE_URL,
+            ),
+            asset_server,
+            log_level: Layered::resolve_optional(
+                None,
+                std::env::var(consts::RUST_LOG).ok(),
+                file.log_level,
+            ),
+            proxy: Layered::resolve_optional(
+                launch_args.and_then(|x| x.proxy.clone()),
+                env::proxy().map(|x| x.to_owned()),
+                file.proxy,
+            ),
+            data_saver_mode: Layered::resolve_optional(None, None, file.data_saver_mode),
+            beta_channel: Layered::resolve_optional(None, None, file.beta_channel),
+            notification_enabled: Layered::resolve(None, None, file.notification.enabled, true),
+            notification_timeout_ms: Layered::resolve(
+                None,
+                None,
+                file.notification.timeout_ms,
+                notification::consts::DEFAULT_TIMEOUT_MS,
+            ),
+        }
+    }
+
+    /// HTTP 客户端使用的代理，无效时忽略
+    pub fn reqwest_proxy(&self) -> Option<reqwest::Proxy> {
+        let proxy = self.proxy.value.as_ref()?;
+        reqwest::Proxy::all(proxy)
+            .map_err(|e| warn!("ignoring invalid proxy '{proxy}': {e}"))
+            .ok()
+    }
+
+    /// 以配置文件中的值覆盖应用内设置
+    pub fn override_user_config(&self, config: &mut UserConfig) {
+        if let Some(val) = self.data_saver_mode.value {
+            config.data_saver_mode_enabled = val;
+        }
+        if let Some(val) = self.beta_channel.value {
+            config.beta_channel_enabled = val;
+        }
+    }
+}
+
+fn write_entry(
+    f: &mut fmt::Formatter<'_>,
+    key: &str,
+    value: Option<String>,
+    source: ConfigSource,
+) -> fmt::Result {
+    match value {
+        Some(value) => writeln!(f, "{key} = {value} # {source}"),
+        None => writeln!(f, "# {key} = （未设置）"),
+    }
+}
+
+/// 以配置文件格式输出生效的配置，每项注明来源
+impl fmt::Display for AppConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match (&self.file_error, self.file_exists) {
+            (Some(e), _) => format!("（读取失败，已忽略：{}）", e.trim()),
+            (None, true) => String::new(),
+            (None, false) => "（不存在）".to_owned(),
+        };
+        writeln!(f, "# 配置文件：{}{status}", self.path.display())?;
+        writeln!(f, "# 优先级：命令行参数 > 环境变量 > 配置文件 > 默认值")?;
+        let string = |x: &Option<String>| x.as_ref().map(|x| format!("{x:?}"));
+        let boolean = |x: &Option<bool>| x.map(|x| x.to_string());
+
+        write_entry(
+            f,
+            "log_level",
+            string(&self.log_level.value),
+            self.log_level.source,
+        )?;
+        write_entry(f, "proxy", string(&self.proxy.value), self.proxy.source)?;
+        write_entry(
+            f,
+            "data_saver_mode",
+            boolean(&self.data_saver_mode.value),
+            self.data_saver_mode.source,
+        )?;
+        write_entry(
+            f,
+            "beta_channel",
+            boolean(&self.beta_channel.value),
+            self.beta_channel.source,
+        )?;
+
+        writeln!(f, "\n[endpoints]")?;
+        for (key, layered) in [
+            ("passport", &self.passport_server),
+            ("arkhost", &self.arkhost_server),
+            ("registry", &self.registry_server),
+            ("asset", &self.asset_server),
+        ] {
+            write_entry(f, key, string(&Some(layered.value.clone())), layered.source)?;
+        }
+
+        writeln!(f, "\n[notification]")?;
+        write_entry(
+            f,
+            "enabled",
+            boolean(&Some(self.notification_enabled.value)),
+            self.notification_enabled.source,
+        )?;
+        write_entry(
+            f,
+            "timeout_ms",
+            Some(self.notification_timeout_ms.value.to_string()),
+            self.notification_timeout_ms.source,
+        )
+    }
+}
+
+static APP_CONFIG: OnceLock<AppConfig> = OnceLock::new();
+
+/// 使用命令行参数初始化配置，需在首次调用 [`get`] 之前调用
+pub fn init(launch_args: &LaunchArgs) -> &'static AppConfig {
+    APP_CONFIG.get_or_init(|| AppConfig::load(Some(launch_args)))
+}
+
+pub fn get() -> &'static AppConfig {
+    APP_CONFIG.get_or_init(|| AppConfig::load(None))
+}
+
+pub mod consts {
+    pub const CONFIG_FILE_NAME: &str = "config.toml";
+    pub const RUST_LOG: &str = "RUST_LOG";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layered_precedence() {
+        let layered = Layered::resolve(Some(1), Some(2), Some(3), 4);
+        assert_eq!((layered.value, layered.source), (1, ConfigSource::Cli));
+        let layered = Layered::resolve(None, Some(2), Some(3), 4);
+        assert_eq!((layered.value, layered.source), (2, ConfigSource::Env));
+        let layered = Layered::resolve(None, None, Some(3), 4);
+        assert_eq!((layered.value, layered.source), (3, ConfigSource::File));
+        let layered = Layered::<Option<i32>>::resolve_optional(None, None, None);
+        assert_eq!(
+            (layered.value, layered.source),
+            (None, ConfigSource::Default)
+        );
+    }
+
+    #[test]
+    fn test_parse_config_file() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            proxy = "socks5://127.0.0.1:1080"
+            unknown_key = 1
+
+            [endpoints]
+            arkhost = "not a url"
+
+            [notification]
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        let config = AppConfig::resolve(PathBuf::new(), true, None, file, None);
+        assert_eq!(config.proxy.source, ConfigSource::File);
+        assert!(!config.notification_enabled.value);
+        assert_eq!(
+            config.notification_timeout_ms.value,
+            notification::consts::DEFAULT_TIMEOUT_MS
+        );
+        assert_eq!(config.arkhost_server.source, ConfigSource::Default);
+        assert_eq!(config.data_saver_mode.value, None);
+    }
+}
@@ -1,5 +1,8 @@
 use std::rc::Rc;
 
+use super::model::ImageData;
+use crate::app::alerts::AlertSettings;
+use crate::app::ap_planner::ApPlan;
 use crate::app::api_user_model::GameEntry;
 use crate::app::api_user_model::{SlotEntry, SlotSyncState};
 use crate::app::ui::*;
@@ -127,25 +130,25 @@ impl<'a> GameLogMapping<'a> {
         };
 
         let it = self.logs.0.iter().chain(self.logs.1.iter());
-        let logs: Vec<GameLogEntry> = it
-            .map(|x| {
-                let attributes = if x.log_level.bits() == api_arkhost::LogLevel::NOTICE.bits() {
-                    SharedString::new()
-                } else {
-                    x.log_level.attributes_tag().into()
-                };
-
-                let mut str = x.content.to_string();
-                str.push(' '); // bug: 在开启word-wrap时，字符串尾部是中文标点会导致错误换行
-                GameLogEntry {
-                    timestamp: x.local_ts().format("%m-%d.%H:%M:%S").to_string().into(),
-                    content: str.into(),
-                    attributes,
-                }
-            })
-            .collect();
+        let logs: Vec<GameLogEntry> = it.map(Self::create_log_entry).collect();
         game_info.logs = ModelRc::from(Rc::new(VecModel::from(logs)));
     }
+
+    pub fn create_log_entry(log: &api_arkhost::LogEntry) -> GameLogEntry {
+        let attributes = if log.log_level.bits() == api_arkhost::LogLevel::NOTICE.bits() {
+            SharedString::new()
+        } else {
+            log.log_level.attributes_tag().into()
+        };
+
+        let mut str = log.content.to_string();
+        str.push(' '); // bug: 在开启word-wrap时，字符串尾部是中文标点会导致错误换行
+        GameLogEntry {
+            timestamp: log.local_ts().format("%m-%d.%H:%M:%S").to_string().into(),
+            content: str.into(),
+            attributes,
+        }
+    }
 }
 
 pub struct GameOptionsMapping {
@@ -203,6 +206,59 @@ impl GameOptionsMapping {
     }
 }
 
+pub struct AlertSettingsMapping {
+    settings: AlertSettings,
+}
+
+impl AlertSettingsMapping {
+    pub fn from(settings: &AlertSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+        }
+    }
+
+    /// UI 中不含按账号的开关，沿用 `current` 中的值
+    pub fn from_ui(info: &AlertSettingsInfo, current: &AlertSettings) -> Self {
+        let hour = |x: i32| x.clamp(0, 23) as u32;
+        Self {
+            settings: AlertSettings {
+                enabled: info.enabled,
+                ap_full: info.ap_full,
+                recruit_license: info.recruit_license,
+                recruit_license_threshold: info.recruit_license_threshold.max(0) as u32,
+                gacha_ticket: info.gacha_ticket,
+                gacha_ticket_threshold: info.gacha_ticket_threshold.max(0) as u32,
+                diamond_changed: info.diamond_changed,
+                status_changed: info.status_changed,
+                disabled_accounts: current.disabled_accounts.clone(),
+                quiet_hours_enabled: info.quiet_hours_enabled,
+                quiet_hours_start: hour(info.quiet_hours_start),
+                quiet_hours_end: hour(info.quiet_hours_end),
+            },
+        }
+    }
+
+    pub fn to_alert_settings(&self) -> AlertSettings {
+        self.settings.clone()
+    }
+
+    pub fn create_alert_settings_info(&self) -> AlertSettingsInfo {
+        AlertSettingsInfo {
+            enabled: self.settings.enabled,
+            ap_full: self.settings.ap_full,
+            recruit_license: self.settings.recruit_license,
+            recruit_license_threshold: self.settings.recruit_license_threshold as i32,
+            gacha_ticket: self.settings.gacha_ticket,
+            gacha_ticket_threshold: self.settings.gacha_ticket_threshold as i32,
+            diamond_changed: self.settings.diamond_changed,
+            status_changed: self.settings.status_changed,
+            quiet_hours_enabled: self.settings.quiet_hours_enabled,
+            quiet_hours_start: self.settings.quiet_hours_start as i32,
+            quiet_hours_end: self.settings.quiet_hours_end as i32,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SlotInfoMapping {
     pub slot_entry: SlotEntry,
@@ -436,10 +492,40 @@ impl UserInfoMapping {
 }
 
 #[derive(Debug, Clone)]
+pub struct BattleMapDropMapping {
+    pub item_id: String,
+    pub name: String,
+    /// 特殊掉落，否则为常规掉落
+    pub special: bool,
+    pub icon: Option<ImageData>,
+}
+
+impl BattleMapDropMapping {
+    pub fn create_battle_map_drop(&self) -> BattleMapDrop {
+        BattleMapDrop {
+            item_id: self.item_id.clone().into(),
+            name: self.name.clone().into(),
+            special: self.special,
+            icon: self
+                .icon
+                .as_ref()
+                .and_then(|x| x.to_slint_image())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct BattleMapMapping {
     pub map_id: String,
     pub code_name: String,
     pub display_name: String,
+    pub ap_cost: i32,
+    pub drops: Vec<BattleMapDropMapping>,
+    pub availability: BattleMapAvailability,
+    pub availability_text: String,
+    /// 已关闭或即将关闭时的替代关卡 ID 及代号
+    pub fallback: Option<(String, String)>,
 }
 
 impl BattleMapMapping {
@@ -453,10 +539,57 @@ impl BattleMapMapping {
         battle_map.map_id = self.map_id.clone().into();
         battle_map.code_name = self.code_name.clone().into();
         battle_map.display_name = self.display_name.clone().into();
+        battle_map.ap_cost = self.ap_cost;
+        let drops: Vec<BattleMapDrop> = self
+            .drops
+            .iter()
+            .map(|x| x.create_battle_map_drop())
+            .collect();
+        battle_map.drops = ModelRc::new(VecModel::from(drops));
+        battle_map.availability = self.availability;
+        battle_map.availability_text = self.availability_text.clone().into();
+        let (fallback_map_id, fallback_code_name) = self.fallback.clone().unwrap_or_default();
+        battle_map.fallback_map_id = fallback_map_id.into();
+        battle_map.fallback_code_name = fallback_code_name.into();
+    }
+}
+
+pub struct ApPlanMapping {
+    pub plan: ApPlan,
+    /// 作战列表中各关卡的代号及理智消耗
+    pub maps: Vec<(String, i32)>,
+}
+
+impl ApPlanMapping {
+    pub fn create_ap_plan_info(&self) -> ApPlanInfo {
+        let runs: Vec<ApPlanRun> = self
+            .maps
+            .iter()
+            .map(|(code_name, ap_cost)| ApPlanRun {
+                code_name: code_name.clone().into(),
+                runs: self.plan.runs(*ap_cost) as i32,
+            })
+            .collect();
+        ApPlanInfo {
+            loaded: true,
+            current_ap: self.plan.current_ap as i32,
+            max_ap: self.plan.max_ap as i32,
+            full_in: if self.plan.time_until_full.is_zero() {
+                "已回满".into()
+            } else {
+                format!(
+                    "{}后回满",
+                    crate::app::utils::time::approximate_period_str(self.plan.time_until_full)
+                )
+                .into()
+            },
+            budget: self.plan.budget as i32,
+            runs: ModelRc::new(VecModel::from(runs)),
+        }
     }
 }
 
-mod utils {
+pub mod utils {
     pub fn redact_account(account: &str) -> String {
         const MASK: char = '-';
         const PREFIX_LEN: usize = 1; // [GB]前缀
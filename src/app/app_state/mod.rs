@@ -69,6 +69,26 @@ impl AppState {
         })
     }
 
+    /// 显示主窗口并定位到该账号的游戏卡片
+    pub fn focus_game(&self, id: String) -> AppStateAsyncOp {
+        self.exec_in_event_loop(move |ui| {
+            ui.window().set_minimized(false);
+            _ = ui.show().map_err(|e| error!("unable to show window: {e}"));
+            ui.invoke_show_dashboard();
+            ui.global::<StateGlobals>().set_focused_game_id(id.into());
+        })
+    }
+
+    /// 取消游戏卡片的定位高亮，已定位到其他卡片时不变
+    pub fn clear_focused_game(&self, id: String) -> AppStateAsyncOp {
+        self.exec_in_event_loop(move |ui| {
+            let state_globals = ui.global::<StateGlobals>();
+            if state_globals.get_focused_game_id() == id.as_str() {
+                state_globals.set_focused_game_id(Default::default());
+            }
+        })
+    }
+
     #[allow(unused)]
     pub fn hide(&self) -> AppStateAsyncOp {
         self.exec_in_event_loop(move |ui| {
@@ -4,7 +4,7 @@ use std::{collections::HashMap, sync::Arc};
 use tokio::sync::RwLock;
 use url::Url;
 
-use crate::app::game_data;
+use crate::app::{alerts::AlertSettings, game_data};
 
 #[derive(Debug, Clone)]
 pub enum AssetPath {
@@ -43,6 +43,8 @@ pub struct ImageData {
     pub asset_path: AssetPath,
     pub cache_key: Option<String>,
     pub format: Option<image::ImageFormat>,
+    /// 缩小图片使长边不超过该值
+    pub max_dimension: Option<u32>,
     pub loaded_image: ImageDataRaw,
 }
 
@@ -74,4 +76,13 @@ pub struct UserConfig {
     #[serde(default)]
     /// 缓存的作战截图URL
     pub cached_battle_screenshots: HashMap<String, Vec<url::Url>>,
+    #[serde(default)]
+    /// 是否接收 Beta 版本更新
+    pub beta_channel_enabled: bool,
+    #[serde(default)]
+    /// 资源缓存上限（MB），未设置时使用默认值
+    pub http_cache_budget_mb: Option<u64>,
+    #[serde(default)]
+    /// 桌面提醒设置
+    pub alert_settings: AlertSettings,
 }
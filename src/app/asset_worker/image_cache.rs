@@ -0,0 +1,269 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use sha2::Digest;
+
+use super::AssetRef;
+use crate::app::{app_state::model::AssetPath, utils::data_dir::data_dir_create_all};
+
+///
+/// 解码（及缩小）后的图片在磁盘上的缓存，以资源路径及目标尺寸为键，命中时无需下载源文件
+///
+/// 文件内容为 `MAGIC`、写入时间（u64 LE，秒）、宽、高（u32 LE）及 RGBA8 数据；
+/// 写入超过 [`consts::MAX_AGE`] 的文件视为未命中，以获取同一路径下更新后的资源。
+/// 读取时更新修改时间，清理时按修改时间淘汰最久未使用的文件
+///
+/// 所有方法均为阻塞操作，应在阻塞线程中调用
+///
+pub struct ImageDiskCache {
+    dir: PathBuf,
+    budget: u64,
+    /// 估算的总大小，写入后超出 `budget` 时清理
+    size: AtomicU64,
+    trimming: AtomicBool,
+}
+
+impl ImageDiskCache {
+    pub fn new() -> Self {
+        Self::with_dir(cache_dir(), consts::DISK_CACHE_BUDGET)
+    }
+
+    pub fn with_dir(dir: PathBuf, budget: u64) -> Self {
+        Self {
+            dir,
+            budget,
+            size: AtomicU64::new(0),
+            trimming: AtomicBool::new(false),
+        }
+    }
+
+    pub fn key(path: &AssetPath, max_dimension: Option<u32>) -> String {
+        let hash = sha2::Sha256::digest(path.inner_path().as_bytes());
+        let size = max_dimension.map_or("full".to_owned(), |x| x.to_string());
+        format!("{}-{size}", hex::encode(&hash[..consts::KEY_HASH_BYTES]))
+    }
+
+    pub fn load(&self, key: &str) -> io::Result<Option<AssetRef>> {
+        let path = self.dir.join(key);
+        let mut file = match fs::File::options().read(true).write(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut header = [0u8; consts::MAGIC.len() + 16];
+        file.read_exact(&mut header)?;
+        let Some(fields) = header.strip_prefix(consts::MAGIC) else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid image cache header: {}", path.display()),
+            ));
+        };
+        let created_at = u64::from_le_bytes(fields[..8].try_into().unwrap());
+        if unix_now().saturating_sub(created_at) > consts::MAX_AGE.as_secs() {
+            return Ok(None);
+        }
+        let width = u32::from_le_bytes(fields[8..12].try_into().unwrap());
+        let height = u32::from_le_bytes(fields[12..].try_into().unwrap());
+        let mut raw = Vec::with_capacity(width as usize * height as usize * 4);
+        file.read_to_end(&mut raw)?;
+        if raw.len() != width as usize * height as usize * 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("image cache size mismatch: {}", path.display()),
+            ));
+        }
+        file.set_modified(SystemTime::now())?;
+        Ok(Some(AssetRef::Rgba8Image {
+            raw: raw.into(),
+            width,
+            height,
+        }))
+    }
+
+    pub fn store(&self, key: &str, asset: &AssetRef) -> io::Result<()> {
+        let AssetRef::Rgba8Image { raw, width, height } = asset else {
+            return Ok(());
+        };
+        fs::create_dir_all(&self.dir)?;
+        // 先写入临时文件再重命名，避免读取到写入一半的文件；
+        // 同一图片可能同时被加载，临时文件名不能相同
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+        let tmp_path = self.dir.join(format!(
+            "{key}.{}-{}{}",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed),
+            consts::TMP_EXTENSION
+        ));
+        let result = write_file(&tmp_path, *width, *height, raw)
+            .and_then(|_| fs::rename(&tmp_path, self.dir.join(key)));
+        if result.is_err() {
+            _ = fs::remove_file(&tmp_path);
+        }
+        result?;
+
+        let size = (consts::MAGIC.len() + 16 + raw.len()) as u64;
+        if self.size.fetch_add(size, Ordering::Relaxed) + size > self.budget {
+            self.trim()?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// 删除最久未使用的文件，直至总大小不超过 `budget`；返回删除的文件数量
+    ///
+    /// 同时只进行一次清理，已有清理进行中时直接返回
+    ///
+    pub fn trim(&self) -> io::Result<usize> {
+        if self.trimming.swap(true, Ordering::Acquire) {
+            return Ok(0);
+        }
+        let result = self.trim_files();
+        self.trimming.store(false, Ordering::Release);
+        result
+    }
+
+    fn trim_files(&self) -> io::Result<usize> {
+        let mut files = Vec::new();
+        let mut total_size = 0u64;
+        let read_dir = match fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e),
+        };
+        let now = SystemTime::now();
+        for entry in read_dir {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified()?;
+            // 跳过可能正在写入的临时文件
+            if entry
+                .path()
+                .to_string_lossy()
+                .ends_with(consts::TMP_EXTENSION)
+                && now.duration_since(modified).unwrap_or_default() < consts::TMP_FILE_MAX_AGE
+            {
+                continue;
+            }
+            total_size += metadata.len();
+            files.push((modified, metadata.len(), entry.path()));
+        }
+        files.sort_by_key(|(modified, _, _)| *modified);
+
+        let mut removed = 0;
+        for (_, size, path) in files {
+            if total_size <= self.budget {
+                break;
+            }
+            fs::remove_file(path)?;
+            total_size -= size;
+            removed += 1;
+        }
+        self.size.store(total_size, Ordering::Relaxed);
+        Ok(removed)
+    }
+}
+
+fn write_file(path: &Path, width: u32, height: u32, raw: &[u8]) -> io::Result<()> {
+    let mut file = io::BufWriter::new(fs::File::create(path)?);
+    file.write_all(consts::MAGIC)?;
+    file.write_all(&unix_now().to_le_bytes())?;
+    file.write_all(&width.to_le_bytes())?;
+    file.write_all(&height.to_le_bytes())?;
+    file.write_all(raw)?;
+    file.flush()
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |x| x.as_secs())
+}
+
+pub fn cache_dir() -> PathBuf {
+    data_dir_create_all().join(consts::IMAGE_CACHE_DIR)
+}
+
+/// 缓存目录占用的磁盘空间
+pub fn disk_usage() -> io::Result<u64> {
+    let read_dir = match fs::read_dir(cache_dir()) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut size = 0;
+    for entry in read_dir {
+        size += entry?.metadata()?.len();
+    }
+    Ok(size)
+}
+
+pub mod consts {
+    use std::time::Duration;
+
+    pub const IMAGE_CACHE_DIR: &str = "image_cache";
+    /// 文件格式变更时更新，旧格式的文件视为无效并被覆盖
+    pub const MAGIC: &[u8] = b"ARKRGBA2";
+    pub const KEY_HASH_BYTES: usize = 16;
+    pub const DISK_CACHE_BUDGET: u64 = 512 << 20;
+    /// 缓存以资源路径为键，超过该时间后重新下载，以获取同一路径下更新后的资源
+    pub const MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+    pub const TMP_EXTENSION: &str = ".tmp";
+    /// 超过该时间的临时文件视为写入中断后遗留的文件，清理时一并删除
+    pub const TMP_FILE_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32) -> AssetRef {
+        AssetRef::Rgba8Image {
+            raw: vec![0u8; (width * height * 4) as usize].into(),
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_image_disk_cache() {
+        let dir = std::env::temp_dir().join(format!("image_cache_test_{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        // 每个文件 24 + 64 字节，最多保留 2 个
+        let cache = ImageDiskCache::with_dir(dir.clone(), 200);
+        let path = AssetPath::GameAsset("avatar/char_002_amiya.webp".into());
+        let key = ImageDiskCache::key(&path, Some(64));
+        assert_ne!(key, ImageDiskCache::key(&path, None));
+        assert!(cache.load(&key).unwrap().is_none());
+
+        cache.store(&key, &image(4, 4)).unwrap();
+        assert!(matches!(
+            cache.load(&key).unwrap(),
+            Some(AssetRef::Rgba8Image {
+                width: 4,
+                height: 4,
+                ..
+            })
+        ));
+
+        // 写入后超出上限时淘汰最久未使用的文件，不留下临时文件
+        for i in 0..3 {
+            let path = AssetPath::GameAsset(format!("avatar/{i}.webp"));
+            cache
+                .store(&ImageDiskCache::key(&path, Some(64)), &image(4, 4))
+                .unwrap();
+        }
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 2);
+        assert!(cache.load(&key).unwrap().is_none());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::AssetRef;
+
+///
+/// 按内存占用限制大小的 LRU 缓存，超出 `budget` 时淘汰最久未访问的资源
+///
+/// 图片的占用按解码后的 RGBA8 大小（`width * height * 4`）计算
+///
+pub struct MemoryCache {
+    budget: usize,
+    used: usize,
+    tick: u64,
+    entries: HashMap<String, Entry>,
+    /// 访问序号 -> 缓存键，序号越小越久未访问
+    order: BTreeMap<u64, String>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+struct Entry {
+    asset: AssetRef,
+    weight: usize,
+    tick: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCacheStats {
+    pub entries: usize,
+    pub used: usize,
+    pub budget: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+impl std::fmt::Display for MemoryCacheStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "entries: {}, used: {}B / {}B, hits: {}, misses: {}, evictions: {}",
+            self.entries, self.used, self.budget, self.hits, self.misses, self.evictions
+        )
+    }
+}
+
+impl MemoryCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<AssetRef> {
+        let tick = self.next_tick();
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                self.order.remove(&entry.tick);
+                entry.tick = tick;
+                self.order.insert(tick, key.to_owned());
+                self.hits += 1;
+                Some(entry.asset.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// 插入资源并返回被淘汰的数量，超出 `budget` 的单个资源不会被缓存
+    pub fn insert(&mut self, key: String, asset: AssetRef) -> usize {
+        self.remove(&key);
+        let weight = asset.weight();
+        if weight > self.budget {
+            return 0;
+        }
+
+        let mut evicted = 0;
+        while self.used + weight > self.budget {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.used -= entry.weight;
+                evicted += 1;
+            }
+        }
+        self.evictions += evicted as u64;
+
+        let tick = self.next_tick();
+        self.order.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            Entry {
+                asset,
+                weight,
+                tick,
+            },
+        );
+        self.used += weight;
+        evicted
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.order.remove(&entry.tick);
+            self.used -= entry.weight;
+        }
+    }
+
+    pub fn stats(&self) -> MemoryCacheStats {
+        MemoryCacheStats {
+            entries: self.entries.len(),
+            used: self.used,
+            budget: self.budget,
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32) -> AssetRef {
+        AssetRef::Rgba8Image {
+            raw: bytes::Bytes::new(),
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn test_memory_cache_lru() {
+        // 每张 2x2 图片占用 16 字节，最多容纳 3 张
+        let mut cache = MemoryCache::new(48);
+        cache.insert("a".into(), image(2, 2));
+        cache.insert("b".into(), image(2, 2));
+        cache.insert("c".into(), image(2, 2));
+        assert!(cache.get("a").is_some());
+
+        assert_eq!(cache.insert("d".into(), image(2, 2)), 1);
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some());
+
+        // 超出上限的资源不缓存
+        assert_eq!(cache.insert("e".into(), image(4, 4)), 0);
+        assert!(cache.get("e").is_none());
+
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 3);
+        assert_eq!(stats.used, 48);
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 2, 1));
+    }
+}
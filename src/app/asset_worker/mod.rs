@@ -1,16 +1,28 @@
-use std::{collections::HashMap, sync::Arc};
+/// 解码后图片的磁盘缓存
+pub mod image_cache;
+/// 按内存占用限制大小的资源缓存
+pub mod memory_cache;
+
+use std::sync::Arc;
 
 use anyhow::bail;
 use arkhost_api::clients::asset::AssetClient;
 use derivative::Derivative;
+use image_cache::ImageDiskCache;
+use log::{debug, info, warn};
+use memory_cache::{MemoryCache, MemoryCacheStats};
 use reqwest::Response;
 use semver::Version;
-use tokio::sync::{mpsc, oneshot, RwLock};
+use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio_util::sync::CancellationToken;
 
-use arkhost_ota::{Release, ReleaseIndexV1};
+use arkhost_ota::{
+    bin_diff::PatchFormat,
+    keys::{KeyRotationListV1, TrustedKeys},
+    Artifact, Release, ReleaseIndexV1, ReleaseManifestV1,
+};
 
-use super::{app_state::model::AssetPath, utils::app_metadata};
+use super::{app_state::model::AssetPath, env, utils::app_metadata};
 
 pub type CommandResult<T> = anyhow::Result<T>;
 pub type Responder<T> = oneshot::Sender<CommandResult<T>>;
@@ -25,6 +37,16 @@ pub enum AssetRef {
     },
 }
 
+impl AssetRef {
+    /// 在内存缓存中的占用
+    pub fn weight(&self) -> usize {
+        match self {
+            AssetRef::Bytes(bytes) => bytes.len(),
+            AssetRef::Rgba8Image { width, height, .. } => *width as usize * *height as usize * 4,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ReleaseUpdateType {
     Delta,
@@ -44,6 +66,8 @@ pub enum Command {
         cache_key: Option<String>,
         path: AssetPath,
         src_format: Option<image::ImageFormat>,
+        /// 缩小图片使长边不超过该值，用于缩略图等，缩小后的图片单独缓存
+        max_dimension: Option<u32>,
         resp: Responder<AssetRef>,
     },
     RetrieveCache {
@@ -53,32 +77,45 @@ pub enum Command {
     DeleteCache {
         cache_key: String,
     },
+    RetrieveCacheStats {
+        resp: oneshot::Sender<MemoryCacheStats>,
+    },
     CheckReleaseUpdate {
         branch: Option<String>,
         mode: ReleaseUpdateType,
-        resp: Responder<Option<(Release, usize, String)>>,
+        resp: Responder<Option<(Release, Artifact, String)>>,
     },
     DownloadReleaseUpdate {
         branch: Option<String>,
         mode: ReleaseUpdateType,
-        resp: Responder<(Release, usize, String, Response)>,
+        resp: Responder<(Release, Artifact, String, Response)>,
     },
 }
 
 pub struct AssetWorker {
     pub asset_client: Arc<AssetClient>,
-    pub cache: Arc<RwLock<HashMap<String, AssetRef>>>,
+    pub cache: Arc<Mutex<MemoryCache>>,
+    pub image_cache: Arc<ImageDiskCache>,
 }
 
 impl AssetWorker {
     pub fn new(asset_client: AssetClient) -> Self {
+        let budget = env::asset_memory_cache_mb().map_or(consts::MEMORY_CACHE_BUDGET, |x| x << 20);
         Self {
             asset_client: Arc::new(asset_client),
-            cache: Arc::new(RwLock::new(HashMap::new())),
+            cache: Arc::new(Mutex::new(MemoryCache::new(budget))),
+            image_cache: Arc::new(ImageDiskCache::new()),
         }
     }
 
     pub async fn run(self: Arc<Self>, mut recv: mpsc::Receiver<Command>, stop: CancellationToken) {
+        tokio::task::spawn_blocking({
+            let image_cache = self.image_cache.clone();
+            move || match image_cache.trim() {
+                Ok(removed) => debug!("run: removed {removed} file(s) from image cache"),
+                Err(e) => warn!("run: error trimming image cache: {e}"),
+            }
+        });
         tokio::select! {
             _ = async {
                 while let Some(cmd) = recv.recv().await {
@@ -121,11 +158,33 @@ impl AssetWorker {
         cache_key: Option<String>,
         path: &AssetPath,
         src_format: Option<image::ImageFormat>,
+        max_dimension: Option<u32>,
     ) -> CommandResult<AssetRef> {
+        let cache_key = match max_dimension {
+            Some(max_dimension) => cache_key.map(|x| format!("{x}@{max_dimension}")),
+            None => cache_key,
+        };
         if let Some(bytes) = self.read_cache_by_key(&cache_key).await {
             return Ok(bytes);
         }
 
+        // 磁盘缓存以资源路径为键，命中时无需下载及解码
+        let disk_cache_key = ImageDiskCache::key(path, max_dimension);
+        let cached = tokio::task::spawn_blocking({
+            let image_cache = self.image_cache.clone();
+            let disk_cache_key = disk_cache_key.clone();
+            move || image_cache.load(&disk_cache_key)
+        })
+        .await?;
+        match cached {
+            Ok(Some(asset)) => {
+                self.write_cache_by_key(cache_key, &asset).await;
+                return Ok(asset);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("load_image_rgba8: error reading image cache: {e}"),
+        }
+
         let src_bytes = match path {
             AssetPath::GameAsset(path) => self.asset_client.get_content(path, |x| x).await?,
             AssetPath::External(url) => {
@@ -134,45 +193,68 @@ impl AssetWorker {
                     .await?
             }
         };
-        let image = match src_format {
-            Some(fmt) => image::load_from_memory_with_format(&src_bytes, fmt)?,
-            None => image::load_from_memory(&src_bytes)?,
-        };
-        let (width, height) = (image.width(), image.height());
-        let bytes = bytes::Bytes::from(image.into_rgba8().into_raw());
-        let asset = AssetRef::Rgba8Image {
-            raw: bytes,
-            width,
-            height,
-        };
+        let image_cache = self.image_cache.clone();
+        let asset = tokio::task::spawn_blocking(move || {
+            let mut image = match src_format {
+                Some(fmt) => image::load_from_memory_with_format(&src_bytes, fmt)?,
+                None => image::load_from_memory(&src_bytes)?,
+            };
+            if let Some(max_dimension) = max_dimension {
+                if image.width().max(image.height()) > max_dimension {
+                    image = image.thumbnail(max_dimension, max_dimension);
+                }
+            }
+            let (width, height) = (image.width(), image.height());
+            let bytes = bytes::Bytes::from(image.into_rgba8().into_raw());
+            let asset = AssetRef::Rgba8Image {
+                raw: bytes,
+                width,
+                height,
+            };
+            if let Err(e) = image_cache.store(&disk_cache_key, &asset) {
+                warn!("load_image_rgba8: error writing image cache: {e}");
+            }
+            Ok(asset)
+        })
+        .await??;
         self.write_cache_by_key(cache_key, &asset).await;
         Ok(asset)
     }
 
     pub async fn read_cache_by_key(&self, cache_key: &Option<String>) -> Option<AssetRef> {
         if let Some(key) = cache_key {
-            return self.cache.read().await.get(key).cloned();
+            return self.cache.lock().await.get(key);
         }
         None
     }
 
     pub async fn write_cache_by_key(&self, cache_key: Option<String>, asset: &AssetRef) {
         if let Some(key) = cache_key {
-            self.cache.write().await.insert(key, asset.clone());
+            let mut cache = self.cache.lock().await;
+            if cache.insert(key, asset.clone()) > 0 {
+                debug!(
+                    "write_cache_by_key: evicted assets, stats: {}",
+                    cache.stats()
+                );
+            }
         }
     }
 
     pub async fn delete_cache_by_key(&self, cache_key: &Option<String>) {
         if let Some(key) = cache_key {
-            self.cache.write().await.remove(key);
+            self.cache.lock().await.remove(key);
         }
     }
 
+    pub async fn cache_stats(&self) -> MemoryCacheStats {
+        self.cache.lock().await.stats()
+    }
+
     pub async fn check_release_update(
         &self,
         branch: &str,
         mode: ReleaseUpdateType,
-    ) -> CommandResult<Option<(Release, usize, String)>> {
+    ) -> CommandResult<Option<(Release, Artifact, String)>> {
         let force_update = crate::app::env::force_update();
         if !force_update && cfg!(debug_assertions) {
             return Ok(None);
@@ -189,11 +271,16 @@ impl AssetWorker {
             .asset_client
             .get_content(arkhost_ota::consts::url::asset::ui_ota_v1::INDEX_SIG, |x| x)
             .await?;
-        let sig = arkhost_ota::try_parse_signature(&sig_bytes)?;
-        arkhost_ota::release_public_key().verify_strict(&index_bytes, &sig)?;
-
-        let index: ReleaseIndexV1 = serde_json::de::from_slice(&index_bytes)?;
-        let release = match index.branches.get(branch) {
+        let trusted_keys = self.trusted_release_keys().await;
+        let index: ReleaseIndexV1 =
+            arkhost_ota::parse_signed_json(&trusted_keys, &index_bytes, &sig_bytes)?;
+        let last_seen_serial = crate::app::ota::last_seen_index_serial()?;
+        index.verify_freshness(
+            last_seen_serial,
+            chrono::Utc::now().timestamp().max(0) as u64,
+        )?;
+        crate::app::ota::update_last_seen_index_serial(index.serial)?;
+        let release = match index.release_for_branch(branch) {
             Some(release) => release,
             None => bail!("unable to find release of branch '{}'", branch),
         };
@@ -202,40 +289,134 @@ impl AssetWorker {
         if !force_update && (self_hash[..] == release_hash[..] || release.version <= cur_version) {
             return Ok(None);
         }
-        let mut path = arkhost_ota::consts::url::asset::ui_ota_v1::FILES.to_owned();
-        match mode {
+        if !force_update
+            && matches!(crate::app::ota::rolled_back(), Ok(Some(rolled_back)) if rolled_back.sha256[..] == release_hash[..])
+        {
+            info!(
+                "skipping release {} which has been rolled back",
+                &release.version
+            );
+            return Ok(None);
+        }
+        if !force_update && !release.rollout_eligible(&crate::app::ota::install_id()?) {
+            info!(
+                "release {} is not rolled out to this install yet ({}%)",
+                &release.version,
+                release.rollout_percentage.unwrap_or(100)
+            );
+            return Ok(None);
+        }
+        let manifest = self.release_manifest(&trusted_keys, &release.file).await?;
+        manifest.verify_release(release)?;
+        let artifact_path = match mode {
             ReleaseUpdateType::Delta => {
-                path.push_str(&arkhost_ota::file_bspatch_path(
-                    &release.file,
-                    &hex::encode(*self_hash),
-                ));
-            }
-            ReleaseUpdateType::FullDownload => {
-                path.push_str(&arkhost_ota::file_path(&release.file));
+                match release.select_patch(&manifest, &hex::encode(*self_hash)) {
+                    Some((_, path)) => path,
+                    None => bail!(
+                        "no patch available for current version, supported formats: {:?}; release formats: {:?}",
+                        PatchFormat::SUPPORTED,
+                        &release.patch_formats
+                    ),
+                }
             }
+            ReleaseUpdateType::FullDownload => arkhost_ota::file_path(&release.file),
         };
+        let artifact = manifest.artifact(&artifact_path)?;
+        let path = format!(
+            "{}{}",
+            arkhost_ota::consts::url::asset::ui_ota_v1::FILES,
+            artifact_path
+        );
         let resp = self
             .asset_client
             .head_content(&path, |x| x)
             .await?
             .error_for_status()?;
-        Ok(Some((
-            release.clone(),
-            resp.headers()
-                .get(reqwest::header::CONTENT_LENGTH)
-                .and_then(|x| x.to_str().ok())
-                .and_then(|x| x.parse().ok())
-                .unwrap_or(0usize),
-            path,
-        )))
+        // 下载前先确认服务端文件大小与清单一致
+        if let Some(content_length) = resp
+            .headers()
+            .get(reqwest::header::CONTENT_LENGTH)
+            .and_then(|x| x.to_str().ok())
+            .and_then(|x| x.parse::<u64>().ok())
+        {
+            artifact.verify_size(&path, content_length)?;
+        }
+        Ok(Some((release.clone(), artifact.clone(), path)))
+    }
+
+    async fn release_manifest(
+        &self,
+        trusted_keys: &TrustedKeys,
+        file: &arkhost_ota::Resource,
+    ) -> CommandResult<ReleaseManifestV1> {
+        let manifest_bytes = self
+            .asset_client
+            .get_content(
+                &format!(
+                    "{}{}",
+                    arkhost_ota::consts::url::asset::ui_ota_v1::FILES,
+                    arkhost_ota::manifest_path(file)
+                ),
+                |x| x,
+            )
+            .await?;
+        let sig_bytes = self
+            .asset_client
+            .get_content(
+                &format!(
+                    "{}{}",
+                    arkhost_ota::consts::url::asset::ui_ota_v1::FILES,
+                    arkhost_ota::manifest_sig_path(file)
+                ),
+                |x| x,
+            )
+            .await?;
+        arkhost_ota::parse_signed_json(trusted_keys, &manifest_bytes, &sig_bytes)
+    }
+
+    ///
+    /// 内置公钥 + 已保存的轮换声明 + 远程获取的轮换声明；
+    /// 远程获取失败时仅使用本地已有的公钥。
+    ///
+    async fn trusted_release_keys(&self) -> TrustedKeys {
+        let mut trusted_keys = TrustedKeys::builtin();
+        let mut rotations = crate::app::ota::key_rotations().unwrap_or_else(|e| {
+            warn!("trusted_release_keys: unable to read saved key rotations: {e}");
+            vec![]
+        });
+        match self
+            .asset_client
+            .get_content(arkhost_ota::consts::url::asset::ui_ota_v1::KEYS, |x| x)
+            .await
+            .and_then(|x| Ok(serde_json::de::from_slice::<KeyRotationListV1>(&x)?))
+        {
+            Ok(remote) => {
+                for rotation in remote.rotations {
+                    if !rotations.contains(&rotation) {
+                        rotations.push(rotation);
+                    }
+                }
+            }
+            Err(e) => warn!("trusted_release_keys: unable to fetch key rotations: {e}"),
+        }
+
+        let applied: Vec<_> = trusted_keys
+            .apply_rotations(&rotations)
+            .into_iter()
+            .cloned()
+            .collect();
+        if let Err(e) = crate::app::ota::upsert_key_rotations(&applied) {
+            warn!("trusted_release_keys: unable to save key rotations: {e}");
+        }
+        trusted_keys
     }
 
     pub async fn download_release_update(
         &self,
         branch: &str,
         mode: ReleaseUpdateType,
-    ) -> CommandResult<(Release, usize, String, Response)> {
-        let (release, download_size, path) = match self.check_release_update(branch, mode).await? {
+    ) -> CommandResult<(Release, Artifact, String, Response)> {
+        let (release, artifact, path) = match self.check_release_update(branch, mode).await? {
             Some(x) => x,
             None => bail!(
                 "unable to update with params: branch: '{}', mode: {:?}",
@@ -245,7 +426,7 @@ impl AssetWorker {
         };
 
         let response = self.asset_client.get_content_response(&path, |x| x).await?;
-        Ok((release, download_size, path, response))
+        Ok((release, artifact, path, response))
     }
 
     async fn exec_command(&self, cmd: Command) {
@@ -259,12 +440,19 @@ impl AssetWorker {
                 cache_key,
                 path,
                 src_format,
+                max_dimension,
                 resp,
-            } => _ = resp.send(self.load_image_rgba8(cache_key, &path, src_format).await),
+            } => {
+                _ = resp.send(
+                    self.load_image_rgba8(cache_key, &path, src_format, max_dimension)
+                        .await,
+                )
+            }
             Command::RetrieveCache { cache_key, resp } => {
                 _ = resp.send(self.read_cache_by_key(&Some(cache_key)).await)
             }
             Command::DeleteCache { cache_key } => self.delete_cache_by_key(&Some(cache_key)).await,
+            Command::RetrieveCacheStats { resp } => _ = resp.send(self.cache_stats().await),
             Command::CheckReleaseUpdate { branch, mode, resp } => {
                 _ = resp.send(
                     self.check_release_update(
@@ -290,3 +478,8 @@ impl AssetWorker {
         }
     }
 }
+
+pub mod consts {
+    /// 内存缓存的默认上限
+    pub const MEMORY_CACHE_BUDGET: usize = 256 << 20;
+}
@@ -232,7 +232,10 @@ impl IpcAuthWorker {
                 OsStr::new("--ipc"),
                 OsStr::new(&ipc_server_name),
             ],
-            None,
+            Some(vec![(
+                crate::app::env::consts::DATA_DIR.into(),
+                crate::app::utils::data_dir::data_dir().into_os_string(),
+            )]),
             true,
             None,
             None,
@@ -1,17 +1,37 @@
 use super::app_state_controller::AppStateController;
-use crate::app::{app_state::model::UserConfig, utils::db};
+use crate::app::{
+    alerts::AlertSettings,
+    app_config,
+    app_state::{mapping::AlertSettingsMapping, model::UserConfig},
+    asset_worker::image_cache,
+    data_bundle::{self, ImportSummary},
+    utils::{
+        cache_manager, db,
+        db_maintenance::{self, DbReport, MaintenanceTarget},
+        user_state::UserStateDBStore,
+    },
+};
+use arkhost_api::clients::common::UserState;
 use chrono::{DateTime, Utc};
-use log::error;
-use std::sync::{Arc, RwLock};
+use log::{debug, error};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
 
 pub struct ConfigController {
     app_state_controller: Arc<AppStateController>,
     db: heed::Database<heed::types::Str, heed::types::SerdeJson<UserConfig>>,
     pub config: RwLock<UserConfig>,
+    /// 运行中使用的登录状态，导入登录凭据后需同步
+    user_state: Arc<RwLock<dyn UserState>>,
 }
 
 impl ConfigController {
-    pub fn new(app_state_controller: Arc<AppStateController>) -> Self {
+    pub fn new(
+        app_state_controller: Arc<AppStateController>,
+        user_state: Arc<RwLock<dyn UserState>>,
+    ) -> Self {
         let db =
             db::database(Some(db::consts::db::USER_CONFIG)).expect("Failed to load user config DB");
         let config = db::env().read_txn().ok().and_then(|rtxn| {
@@ -23,6 +43,7 @@ impl ConfigController {
             app_state_controller,
             db,
             config: RwLock::new(config.unwrap_or_default()),
+            user_state,
         }
     }
 
@@ -57,6 +78,42 @@ impl ConfigController {
         Ok(())
     }
 
+    /// 请求在 App 退出后压缩数据库
+    pub fn set_compaction(&self, val: bool) -> heed::Result<()> {
+        db::request_compaction(val)?;
+        self.app_state_controller.exec(move |x| {
+            x.state_globals(move |x| {
+                x.set_compaction_requested(val);
+            })
+        });
+        Ok(())
+    }
+
+    /// 校验各数据库中的条目，并删除无法解码的条目；会进行写事务，应在阻塞线程中调用
+    pub fn verify_db(&self) -> anyhow::Result<(Vec<DbReport>, usize)> {
+        let env = db::env();
+        let reports = db_maintenance::verify(&env);
+        let removed = db_maintenance::remove_corrupted(&env, &reports)?;
+        Ok((reports, removed))
+    }
+
+    /// 清除指定的数据，清除设置后恢复为默认设置；应在阻塞线程中调用
+    pub fn clear_db(&self, target: MaintenanceTarget) -> anyhow::Result<()> {
+        db_maintenance::clear(&db::env(), target)?;
+        match target {
+            MaintenanceTarget::Config => {
+                let mut config = self.config.write().unwrap();
+                *config = UserConfig::default();
+                app_config::get().override_user_config(&mut config);
+                drop(config);
+                self.sync_to_ui();
+            }
+            MaintenanceTarget::Cache => self.recalculate_disk_usage(),
+            _ => {}
+        }
+        Ok(())
+    }
+
     pub fn data_saver_mode_enabled(&self) -> bool {
         self.config.read().unwrap().data_saver_mode_enabled
     }
@@ -66,6 +123,67 @@ impl ConfigController {
         self.sync_to_ui();
     }
 
+    pub fn beta_channel_enabled(&self) -> bool {
+        self.config.read().unwrap().beta_channel_enabled
+    }
+
+    pub fn set_beta_channel_enabled(&self, val: bool) {
+        self.update_config(|c| c.beta_channel_enabled = val);
+        self.sync_to_ui();
+    }
+
+    /// 资源缓存上限（字节）
+    pub fn http_cache_budget(&self) -> u64 {
+        self.config
+            .read()
+            .unwrap()
+            .http_cache_budget_mb
+            .unwrap_or(cache_manager::consts::DEFAULT_BUDGET_MB)
+            << 20
+    }
+
+    /// `index` 为 UI 中缓存上限选项的下标
+    pub fn set_http_cache_budget_index(&self, index: usize) {
+        let Some(budget_mb) = cache_manager::consts::BUDGET_OPTIONS_MB.get(index) else {
+            error!("set_http_cache_budget_index: invalid index {index}");
+            return;
+        };
+        self.update_config(|c| c.http_cache_budget_mb = Some(*budget_mb));
+        self.sync_to_ui();
+    }
+
+    /// 按缓存上限淘汰资源缓存，会进行写事务，应在阻塞线程中调用
+    pub fn sweep_http_cache(&self) {
+        let budget = self.http_cache_budget();
+        match cache_manager::DBCacheManager::new().sweep(budget) {
+            Ok(result) => debug!(
+                "sweep_http_cache: evicted {} entries ({}B), remaining: {}B, budget: {budget}B",
+                result.evicted, result.evicted_size, result.remaining_size
+            ),
+            Err(e) => error!("sweep_http_cache: error sweeping HTTP cache: {e}"),
+        }
+    }
+
+    pub fn alert_settings(&self) -> AlertSettings {
+        self.config.read().unwrap().alert_settings.clone()
+    }
+
+    pub fn set_alert_settings(&self, val: AlertSettings) {
+        self.update_config(|c| c.alert_settings = val);
+        self.sync_to_ui();
+    }
+
+    pub fn set_game_alerts_enabled(&self, game_id: String, val: bool) {
+        self.update_config(|c| {
+            let disabled_accounts = &mut c.alert_settings.disabled_accounts;
+            if val {
+                disabled_accounts.remove(&game_id);
+            } else {
+                disabled_accounts.insert(game_id);
+            }
+        });
+    }
+
     pub fn last_ssr_record_ts(&self) -> DateTime<Utc> {
         self.config.read().unwrap().last_ssr_record_ts
     }
@@ -89,15 +207,44 @@ impl ConfigController {
         });
     }
 
-    #[allow(unused)]
+    /// 导出设置及数据，`path` 未指定时导出到数据目录下
+    pub fn export_data(
+        &self,
+        path: Option<&Path>,
+        include_credentials: bool,
+    ) -> anyhow::Result<PathBuf> {
+        data_bundle::export_to_file(path, include_credentials)
+    }
+
+    ///
+    /// 导入设置及数据并重新加载设置
+    ///
+    /// 导入登录凭据时同时更新运行中的登录状态，否则刷新凭据时会以旧凭据覆盖导入的凭据
+    ///
+    pub fn import_data(&self, path: &Path) -> anyhow::Result<ImportSummary> {
+        let summary = data_bundle::import_from_file(path)?;
+        if summary.credentials {
+            let mut stored = UserStateDBStore::new();
+            stored.load_from_db()?;
+            if let Some(jwt) = stored.login_state() {
+                self.user_state.write().unwrap().set_login_state(jwt);
+            }
+        }
+        self.load_from_db()?;
+        self.sync_to_ui();
+        Ok(summary)
+    }
+
     pub fn load_from_db(&self) -> heed::Result<()> {
         let env = db::env();
         let rtxn = env.read_txn()?;
-        let config: Option<UserConfig> =
+        let stored: Option<UserConfig> =
             self.db.get(&rtxn, db::consts::user_config::DEFAULT_USER)?;
-        if let Some(config) = config {
-            *self.config.write().unwrap() = config;
+        let mut config = self.config.write().unwrap();
+        if let Some(stored) = stored {
+            *config = stored;
         }
+        app_config::get().override_user_config(&mut config);
         Ok(())
     }
 
@@ -112,9 +259,24 @@ impl ConfigController {
     pub fn sync_to_ui(&self) {
         let config = self.config.read().unwrap();
         let data_saver_mode_enabled = config.data_saver_mode_enabled;
+        let beta_channel_enabled = config.beta_channel_enabled;
+        let alert_settings =
+            AlertSettingsMapping::from(&config.alert_settings).create_alert_settings_info();
+        let budget_mb = config
+            .http_cache_budget_mb
+            .unwrap_or(cache_manager::consts::DEFAULT_BUDGET_MB);
+        // 非预设的上限值显示为最接近的选项
+        let http_cache_budget_index = cache_manager::consts::BUDGET_OPTIONS_MB
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, x)| x.abs_diff(budget_mb))
+            .map_or(0, |(i, _)| i as i32);
         self.app_state_controller.exec(move |x| {
             x.state_globals(move |x| {
                 x.set_data_saver_mode_enabled(data_saver_mode_enabled);
+                x.set_beta_channel_enabled(beta_channel_enabled);
+                x.set_http_cache_budget_index(http_cache_budget_index);
+                x.set_alert_settings(alert_settings);
             })
         });
     }
@@ -127,6 +289,7 @@ impl ConfigController {
         });
     }
 
+    /// HTTP 缓存及图片缓存的大小
     fn cache_size() -> heed::Result<u64> {
         let mut size = 0u64;
         let env = db::env();
@@ -138,6 +301,10 @@ impl ConfigController {
             size += k.len() as u64;
             size += v.len() as u64;
         }
+        size += image_cache::disk_usage().unwrap_or_else(|e| {
+            error!("cache_size: error calculating image cache size: {e}");
+            0
+        });
         Ok(size)
     }
 }
@@ -0,0 +1,128 @@
+use super::{app_state_controller::AppStateController, sender::Sender, AssetCommand};
+use crate::app::{
+    diagnostics,
+    utils::{client_log, notification},
+};
+use log::error;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use tokio::sync::oneshot;
+
+pub struct DiagnosticsController {
+    app_state_controller: Arc<AppStateController>,
+    sender: Arc<Sender>,
+    following: AtomicBool,
+}
+
+impl DiagnosticsController {
+    pub fn new(app_state_controller: Arc<AppStateController>, sender: Arc<Sender>) -> Self {
+        Self {
+            app_state_controller,
+            sender,
+            following: AtomicBool::new(false),
+        }
+    }
+
+    /// 读取本进程日志文件末尾的内容并显示，应在阻塞线程中调用
+    pub fn refresh_client_log(&self) {
+        let (path, content) = match client_log::log_file_path() {
+            Some(path) => (
+                path.display().to_string(),
+                client_log::tail(path, client_log::consts::VIEWER_TAIL_BYTES).unwrap_or_else(|e| {
+                    error!("refresh_client_log: error reading log file: {e}");
+                    format!("读取日志文件失败：{e}")
+                }),
+            ),
+            None => (String::new(), "未启用日志文件".to_owned()),
+        };
+        self.app_state_controller.exec(move |x| {
+            x.state_globals(move |s| {
+                s.set_client_log_path(path.into());
+                s.set_client_log(content.into());
+            })
+        });
+    }
+
+    /// 获取图片内存缓存的使用情况并显示
+    pub async fn refresh_asset_cache_stats(&self) {
+        let (resp, rx) = oneshot::channel();
+        let stats = match self
+            .sender
+            .send_asset_command(AssetCommand::RetrieveCacheStats { resp })
+            .await
+        {
+            Ok(()) => match rx.await {
+                Ok(stats) => format!(
+                    "图片内存缓存：{} 项，{} / {}，命中 {} 次，未命中 {} 次，淘汰 {} 项",
+                    stats.entries,
+                    humansize::format_size(stats.used, humansize::DECIMAL),
+                    humansize::format_size(stats.budget, humansize::DECIMAL),
+                    stats.hits,
+                    stats.misses,
+                    stats.evictions
+                ),
+                Err(e) => {
+                    error!("refresh_asset_cache_stats: error receiving cache stats: {e}");
+                    return;
+                }
+            },
+            Err(e) => {
+                error!("refresh_asset_cache_stats: error sending command: {e}");
+                return;
+            }
+        };
+        self.app_state_controller
+            .exec(move |x| x.state_globals(move |s| s.set_asset_cache_stats(stats.into())));
+    }
+
+    /// 持续刷新日志，直至取消
+    pub fn set_following(self: Arc<Self>, val: bool) {
+        if self.following.swap(val, Ordering::Relaxed) || !val {
+            return;
+        }
+        tokio::spawn(async move {
+            while self.following.load(Ordering::Relaxed) {
+                let this = self.clone();
+                _ = tokio::task::spawn_blocking(move || this.refresh_client_log()).await;
+                self.refresh_asset_cache_stats().await;
+                tokio::time::sleep(consts::FOLLOW_INTERVAL).await;
+            }
+        });
+    }
+
+    /// 生成诊断信息包，应在阻塞线程中调用
+    pub fn create_diagnostics_bundle(&self) {
+        match diagnostics::create_bundle(None, None) {
+            Ok(path) => {
+                let path_str = path.display().to_string();
+                notification::toast("已生成诊断信息包", None, &path_str, None);
+                self.app_state_controller.exec(move |x| {
+                    x.state_globals(move |s| s.set_diagnostics_bundle_path(path_str.into()))
+                });
+            }
+            Err(e) => {
+                error!("create_diagnostics_bundle: error creating diagnostics bundle: {e:?}");
+                notification::toast("生成诊断信息包失败", None, &format!("{e}"), None);
+            }
+        }
+    }
+
+    /// `index` 为 UI 中日志等级选项的下标
+    pub fn set_log_level_index(&self, index: usize) {
+        let Some(level) = client_log::consts::LEVEL_OPTIONS.get(index) else {
+            error!("set_log_level_index: invalid index {index}");
+            return;
+        };
+        client_log::set_level(*level);
+        self.app_state_controller
+            .exec(move |x| x.state_globals(move |s| s.set_log_level_index(index as i32)));
+    }
+}
+
+mod consts {
+    use std::time::Duration;
+
+    pub const FOLLOW_INTERVAL: Duration = Duration::from_secs(2);
+}
@@ -5,25 +5,34 @@ use super::{
     ApiOperation, AssetCommand,
 };
 use crate::app::{
+    alerts::AlertTracker,
+    ap_planner::{self, ApPlan, ApStatus},
     api_user_model,
     api_worker::RetrieveLogSpec,
     app_state::{
-        mapping::{BattleMapMapping, GameInfoMapping},
+        mapping::{
+            utils::redact_account, ApPlanMapping, BattleMapDropMapping, BattleMapMapping,
+            GameInfoMapping, GameLogMapping,
+        },
         model::{AssetPath, CharIllust, ImageData, ImageDataRaw, ImageDataRef},
     },
     asset_worker::AssetRef,
     controller::RefreshLogsCondition,
-    game_data::{CharPack, CharPackSummaryTable, Stage, StageDropType, StageTable, StageType},
-    ui::*,
-    utils::{
-        cache_manager::DBCacheManager,
-        levenshtein_distance::{self, ResultEntry},
-        notification,
+    game_data::{
+        stage_availability::{OpeningTimes, StageAvailability},
+        stage_search::StageSearchIndex,
+        ActivityTable, CharPack, CharPackSummaryTable, ItemTable, RetroTable, Stage, StageDropType,
+        StageTable, ZoneTable,
     },
+    log_archive,
+    log_export::{self, LogExportFormat},
+    ui::*,
+    utils::{cache_manager::DBCacheManager, notification},
 };
 use anyhow::anyhow;
 use arkhost_api::models::api_arkhost::{self, GameConfigFields, GameSseEvent, GameStatus};
 use async_scoped::TokioScope;
+use chrono::Timelike;
 use futures_util::TryStreamExt;
 use http_cache::CacheManager;
 use log::{debug, error, warn};
@@ -31,7 +40,7 @@ use serde::Deserialize;
 use slint::{Model, ModelRc, VecModel};
 use std::{
     cmp,
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, HashMap, HashSet},
     mem,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -51,10 +60,16 @@ struct GameResourceEntry {
 pub struct GameController {
     game_resource_map: RwLock<HashMap<String, Arc<GameResourceEntry>>>,
     stage_data: RwLock<Option<StageTable>>,
-    stage_search_tree: RwLock<levenshtein_distance::Trie<Arc<String>>>,
+    item_data: RwLock<Option<ItemTable>>,
+    item_icons: RwLock<HashMap<String, ImageDataRef>>,
+    zone_data: RwLock<Option<ZoneTable>>,
+    activity_data: RwLock<Option<ActivityTable>>,
+    retro_data: RwLock<Option<RetroTable>>,
+    stage_search_index: RwLock<StageSearchIndex>,
     char_pack_summaries: RwLock<Option<CharPackSummaryTable>>,
     refreshing: AtomicBool,
     current_last_gacha_record_ts: RwLock<chrono::DateTime<chrono::Utc>>,
+    alert_tracker: RwLock<AlertTracker>,
     cache_manager: Box<dyn CacheManager>,
 
     api_user_model: Arc<ApiUserModel>,
@@ -79,10 +94,16 @@ impl GameController {
         Self {
             game_resource_map: RwLock::new(HashMap::new()),
             stage_data: RwLock::new(None),
-            stage_search_tree: Default::default(),
+            item_data: RwLock::new(None),
+            item_icons: RwLock::new(HashMap::new()),
+            zone_data: RwLock::new(None),
+            activity_data: RwLock::new(None),
+            retro_data: RwLock::new(None),
+            stage_search_index: Default::default(),
             char_pack_summaries: RwLock::new(None),
             refreshing: AtomicBool::new(false),
             current_last_gacha_record_ts: RwLock::new(chrono::DateTime::<chrono::Utc>::MIN_UTC),
+            alert_tracker: RwLock::new(AlertTracker::default()),
             cache_manager: Box::new(DBCacheManager::new()),
 
             api_user_model,
@@ -325,8 +346,14 @@ impl GameController {
                 let game_entry = game_ref.game.read().await;
                 self.set_selected_maps(id.clone(), &game_entry.info, false)
                     .await;
+                self.update_ap_plan(id.clone(), &game_entry).await;
+                self.process_alerts(id.clone(), &game_entry).await;
             }
         }
+        self.alert_tracker
+            .write()
+            .await
+            .retain(&game_ids.iter().cloned().collect::<HashSet<_>>());
         let refresh_log_tasks = game_ids
             .iter()
             .map(|x| self.refresh_logs_if_needed(x.clone(), refresh_log_cond.clone()))
@@ -413,101 +440,175 @@ impl GameController {
         }
     }
 
-    // TODO: 模糊搜索优化 & 关键词搜索
-    pub async fn on_search_map(&self, id: String, term: String, fuzzy: bool) {
-        let search_tree = self.stage_search_tree.read().await;
-        let battle_map_mappings = match self.stage_data.read().await.as_ref() {
-            Some(stage_data) if !term.is_empty() => {
-                let chars: Vec<char> = term.chars().collect();
-                let results: Vec<ResultEntry<Arc<String>>> = search_tree
-                    .search(
-                        if fuzzy {
-                            consts::SEARCH_MAP_MAX_LEVENSHTEIN_DISTANCE_FUZZY
-                        } else {
-                            consts::SEARCH_MAP_MAX_LEVENSHTEIN_DISTANCE
-                        },
-                        &chars,
-                    )
-                    .into_iter()
-                    .collect();
-                let mut stages: Vec<(i32, &String, &Stage)> = results
-                    .iter()
-                    .filter_map(|ResultEntry(dist, map_id)| {
-                        stage_data
-                            .stages
-                            .get(map_id.as_ref())
-                            .map(|s| (*dist, map_id.as_ref(), s))
-                    })
-                    .collect();
-                stages.sort_by(
-                    |(lhs_dist, lhs_map_id, lhs_stage), (rhs_dist, rhs_map_id, rhs_stage)| {
-                        match (
-                            lhs_stage.name.as_ref().and_then(|x| x.find(&term)),
-                            rhs_stage.name.as_ref().and_then(|x| x.find(&term)),
-                        ) {
-                            (Some(_), None) => return cmp::Ordering::Less,
-                            (None, Some(_)) => return cmp::Ordering::Greater,
-                            (Some(lhs), Some(rhs)) if lhs != rhs => return lhs.cmp(&rhs),
-                            _ => {}
-                        };
+    pub async fn search_logs(&self, id: String, term: String) {
+        let query = log_archive::LogQuery {
+            text: Some(term),
+            limit: Some(log_archive::consts::SEARCH_LIMIT),
+            ..Default::default()
+        };
+        let account = id.clone();
+        let logs =
+            match tokio::task::spawn_blocking(move || log_archive::query(&account, &query)).await {
+                Ok(Ok(logs)) => logs,
+                Ok(Err(e)) => {
+                    error!("error searching archived logs for game with id {id}: {e}");
+                    vec![]
+                }
+                Err(e) => {
+                    error!("error joining log search task: {e}");
+                    vec![]
+                }
+            };
+        let entries: Vec<GameLogEntry> =
+            logs.iter().map(GameLogMapping::create_log_entry).collect();
 
-                        match (lhs_stage.code.find(&term), rhs_stage.code.find(&term)) {
-                            (Some(_), None) => return cmp::Ordering::Less,
-                            (None, Some(_)) => return cmp::Ordering::Greater,
-                            (Some(lhs), Some(rhs)) if lhs != rhs => return lhs.cmp(&rhs),
-                            _ => {}
-                        };
+        self.app_state_controller.exec(move |x| {
+            x.exec_with_game_by_id(id, |game_info_list, i, mut game_info| {
+                game_info.log_search_results = ModelRc::new(VecModel::from(entries));
+                game_info_list.set_row_data(i, game_info);
+            })
+        });
+    }
 
-                        match lhs_dist.cmp(rhs_dist) {
-                            cmp::Ordering::Equal => {}
-                            ord => return ord,
-                        }
+    ///
+    /// 导出日志，`range_days` 为空时导出全部，`level_tag` 为 [`api_arkhost::LogLevel::attributes_tag`] 中的字母
+    ///
+    /// 导出前先获取最新日志，再向前获取直至覆盖时间范围（或没有更早的日志）；
+    /// 导出全部时仅向前获取 [`log_export::consts::FETCH_DAYS_FOR_ALL`] 天，更早的日志以本地存档为准
+    ///
+    pub async fn export_logs(
+        &self,
+        id: String,
+        format: LogExportFormat,
+        range_days: Option<i64>,
+        level_tag: String,
+    ) {
+        let since = range_days.map(|days| chrono::Utc::now() - chrono::Duration::days(days));
+        let fetch_since = since.unwrap_or_else(|| {
+            chrono::Utc::now() - chrono::Duration::days(log_export::consts::FETCH_DAYS_FOR_ALL)
+        });
+        if let Err(e) = self.retrieve_logs_since(&id, fetch_since).await {
+            warn!("error retrieving logs for export for game with id {id}: {e}");
+            notification::toast(&format!("{id} 获取日志失败"), None, &format!("{e}"), None);
+        }
+        match self.api_user_model.user.find_game(&id).await {
+            Ok(game_ref) => {
+                let game = &game_ref.game.read().await;
+                let mapping = GameInfoMapping::from(game);
+                self.app_state_controller
+                    .exec(|x| x.update_game_view(id.clone(), Some(mapping), true));
+            }
+            Err(_) => self
+                .app_state_controller
+                .exec(|x| x.update_game_view(id.clone(), None, true)),
+        }
 
-                        match lhs_stage.cmp(rhs_stage) {
-                            cmp::Ordering::Equal => lhs_map_id.cmp(rhs_map_id),
-                            ord => ord,
-                        }
+        let levels = api_arkhost::LogLevel::from_attributes_tag(&level_tag);
+        let query = log_archive::LogQuery {
+            levels: (!levels.is_empty()).then_some(levels),
+            since,
+            ..Default::default()
+        };
+        let account = id.clone();
+        match tokio::task::spawn_blocking(move || {
+            log_export::export_to_file(&account, &query, format, None)
+        })
+        .await
+        {
+            Ok(Ok((path, count))) => notification::toast(
+                &format!("{id} 日志已导出"),
+                None,
+                &format!("共 {count} 条，已保存至 {}", path.display()),
+                None,
+            ),
+            Ok(Err(e)) => {
+                error!("error exporting logs for game with id {id}: {e}");
+                notification::toast(&format!("{id} 导出日志失败"), None, &format!("{e}"), None);
+            }
+            Err(e) => error!("error joining log export task: {e}"),
+        }
+    }
+
+    async fn retrieve_logs_since(
+        &self,
+        id: &str,
+        since: chrono::DateTime<chrono::Utc>,
+    ) -> anyhow::Result<()> {
+        let mut spec = RetrieveLogSpec::Latest {};
+        let mut last_cursor_back = None;
+        for _ in 0..log_export::consts::MAX_FORMER_PAGES {
+            let (resp, mut rx) = oneshot::channel();
+            let game_ref = self
+                .sender
+                .send_api_request(
+                    ApiOperation::RetrieveLog {
+                        account: id.to_owned(),
+                        spec,
+                        resp,
                     },
-                );
-                stages.truncate(consts::SEARCH_MAP_RESULT_LIMIT);
-                stages
-                    .into_iter()
-                    .map(|(_, map_id, stage)| {
-                        BattleMapMapping {
-                            map_id: map_id.clone(),
-                            code_name: stage.code.clone(),
-                            display_name: stage.display(),
-                        }
-                        .create_battle_map()
-                    })
-                    .collect()
+                    &mut rx,
+                )
+                .await?;
+            let game = game_ref.game.read().await;
+            let covered = matches!(game.logs.back(), Some(oldest) if oldest.ts <= since);
+            if covered || last_cursor_back == Some(game.log_cursor_back) {
+                break;
             }
-            _ => vec![],
+            last_cursor_back = Some(game.log_cursor_back);
+            spec = RetrieveLogSpec::Former {};
+        }
+        Ok(())
+    }
+
+    pub async fn on_search_map(&self, id: String, term: String, fuzzy: bool) {
+        let search_index = self.stage_search_index.read().await;
+        let mut battle_map_mappings = match self.stage_data.read().await.as_ref() {
+            Some(stage_data) => search_index
+                .search(
+                    &term,
+                    if fuzzy {
+                        consts::SEARCH_MAP_MAX_LEVENSHTEIN_DISTANCE_FUZZY
+                    } else {
+                        consts::SEARCH_MAP_MAX_LEVENSHTEIN_DISTANCE
+                    },
+                    consts::SEARCH_MAP_RESULT_LIMIT,
+                )
+                .into_iter()
+                .filter_map(|map_id| {
+                    stage_data
+                        .stages
+                        .get(map_id)
+                        .map(|stage| Self::battle_map_mapping(map_id, Some(stage), None))
+                })
+                .collect(),
+            None => vec![],
         };
+        self.attach_availability(&mut battle_map_mappings).await;
 
         self.app_state_controller.exec(move |x| {
-            x.exec_with_game_by_id(id, |game_info_list, i, mut game_info| {
-                game_info.map_search_results = ModelRc::new(VecModel::from(battle_map_mappings));
+            x.exec_with_game_by_id(id, move |game_info_list, i, mut game_info| {
+                let battle_maps: Vec<BattleMap> = battle_map_mappings
+                    .iter()
+                    .map(|x| x.create_battle_map())
+                    .collect();
+                game_info.map_search_results = ModelRc::new(VecModel::from(battle_maps));
                 game_info_list.set_row_data(i, game_info);
             })
         });
     }
 
     pub async fn on_select_map(&self, id: String, map_id: String, selected: bool) {
-        let battle_map = if selected {
-            self.stage_data
+        let battle_map = if selected
+            && self
+                .stage_data
                 .read()
                 .await
                 .as_ref()
-                .and_then(|x| x.stages.get(&map_id))
-                .map(|x| {
-                    BattleMapMapping {
-                        map_id: map_id.clone(),
-                        code_name: x.code.clone(),
-                        display_name: x.display(),
-                    }
-                    .create_battle_map()
-                })
+                .is_some_and(|x| x.stages.contains_key(&map_id))
+        {
+            self.selected_map_mappings(std::slice::from_ref(&map_id))
+                .await
+                .pop()
         } else {
             None
         };
@@ -537,7 +638,7 @@ impl GameController {
                     }
 
                     if let Some(battle_map) = battle_map {
-                        selected_maps.push(battle_map);
+                        selected_maps.push(battle_map.create_battle_map());
                     }
                 }
 
@@ -546,6 +647,73 @@ impl GameController {
         })
     }
 
+    /// 将作战列表中的关卡替换为另一关卡，新关卡已在列表中时仅移除原关卡
+    pub async fn on_replace_map(&self, id: String, map_id: String, new_map_id: String) {
+        let Some(new_battle_map) = self
+            .selected_map_mappings(std::slice::from_ref(&new_map_id))
+            .await
+            .pop()
+        else {
+            return;
+        };
+
+        self.app_state_controller.exec(move |x| {
+            x.exec_with_game_by_id(id, move |game_info_list, i, game_info| {
+                let Some(selected_maps) = game_info
+                    .selected_maps
+                    .as_any()
+                    .downcast_ref::<VecModel<BattleMap>>()
+                else {
+                    return;
+                };
+                let Some(map_index) = selected_maps.iter().position(|x| x.map_id == map_id) else {
+                    return;
+                };
+                if selected_maps.iter().any(|x| x.map_id == new_map_id) {
+                    selected_maps.remove(map_index);
+                } else {
+                    selected_maps.set_row_data(map_index, new_battle_map.create_battle_map());
+                }
+                game_info_list.set_row_data(i, game_info);
+            })
+        })
+    }
+
+    /// 保存作战列表时提示已关闭或即将关闭的关卡
+    pub async fn warn_unavailable_maps(&self, map_ids: &[String]) {
+        let mut battle_maps: Vec<BattleMapMapping> = {
+            let stage_data = self.stage_data.read().await;
+            map_ids
+                .iter()
+                .map(|map_id| {
+                    let stage = stage_data.as_ref().and_then(|x| x.stages.get(map_id));
+                    Self::battle_map_mapping(map_id, stage, None)
+                })
+                .collect()
+        };
+        self.attach_availability(&mut battle_maps).await;
+
+        let unavailable: Vec<String> = battle_maps
+            .iter()
+            .filter(|x| x.availability != BattleMapAvailability::Open)
+            .map(|x| {
+                let mut line = format!("{}（{}）", x.code_name, x.availability_text);
+                if let Some((_, fallback_code_name)) = &x.fallback {
+                    line.push_str(&format!("，可替换为 {fallback_code_name}"));
+                }
+                line
+            })
+            .collect();
+        if !unavailable.is_empty() {
+            notification::toast(
+                "作战列表中有关卡已关闭或即将关闭",
+                None,
+                &unavailable.join("\n"),
+                None,
+            );
+        }
+    }
+
     pub async fn reset_selected_maps(&self, id: String) {
         let game_map = self.api_user_model.game_map_read().await;
         let game_ref = game_map.get(&id);
@@ -561,46 +729,120 @@ impl GameController {
         game_info: &api_arkhost::GameInfo,
         override_existing: bool,
     ) {
-        let mut battle_maps_to_set = vec![];
-        let stage_data = self.stage_data.read().await;
-        if let Some(battle_maps) = &game_info.game_config.battle_maps {
-            for map_id in battle_maps {
-                let battle_map = stage_data
-                    .as_ref()
-                    .and_then(|x| x.stages.get(map_id))
-                    .map_or_else(
-                        || {
-                            BattleMapMapping {
-                                map_id: map_id.clone(),
-                                code_name: "".into(),
-                                display_name: map_id.clone(),
-                            }
-                            .create_battle_map()
-                        },
-                        |x| {
-                            BattleMapMapping {
-                                map_id: map_id.clone(),
-                                code_name: x.code.clone(),
-                                display_name: x.display(),
-                            }
-                            .create_battle_map()
-                        },
-                    );
+        let battle_maps_to_set = match &game_info.game_config.battle_maps {
+            Some(battle_maps) => self.selected_map_mappings(battle_maps).await,
+            None => vec![],
+        };
 
-                battle_maps_to_set.push(battle_map);
+        self.app_state_controller.exec(|x| {
+            x.exec_with_game_by_id(id, move |game_info_list, i, mut game_info| {
+                if override_existing || game_info.selected_maps.row_count() == 0 {
+                    let battle_maps: Vec<BattleMap> = battle_maps_to_set
+                        .iter()
+                        .map(|x| x.create_battle_map())
+                        .collect();
+                    game_info.selected_maps = ModelRc::new(VecModel::from(battle_maps));
+                    game_info_list.set_row_data(i, game_info);
+                }
+            })
+        });
+    }
+
+    /// 根据游戏详情中的理智及已保存的作战列表更新理智规划
+    async fn update_ap_plan(&self, id: String, game: &api_user_model::GameEntry) {
+        let Some(details) = &game.details else {
+            return;
+        };
+        let plan = ApPlan::new(
+            &ApStatus::from(&details.status),
+            game.info.game_config.keeping_ap.unwrap_or(0),
+            chrono::Utc::now(),
+            ap_planner::consts::plan_period(),
+        );
+        let maps = match (
+            self.stage_data.read().await.as_ref(),
+            &game.info.game_config.battle_maps,
+        ) {
+            (Some(stage_data), Some(battle_maps)) => battle_maps
+                .iter()
+                .filter_map(|x| stage_data.stages.get(x))
+                .map(|x| (x.code.clone(), x.ap_cost))
+                .collect(),
+            _ => vec![],
+        };
+        let mapping = ApPlanMapping { plan, maps };
+
+        self.app_state_controller.exec(move |x| {
+            x.exec_with_game_by_id(id, move |game_info_list, i, mut game_info| {
+                game_info.ap_plan = mapping.create_ap_plan_info();
+                game_info_list.set_row_data(i, game_info);
+            })
+        });
+    }
+
+    /// 与上次刷新时的状态比较并发送桌面提醒，同时更新 UI 中该账号的提醒开关
+    async fn process_alerts(&self, id: String, game: &api_user_model::GameEntry) {
+        let settings = self.config_controller.alert_settings();
+        let events = self.alert_tracker.write().await.update(
+            &id,
+            game.info.status.code,
+            game.status_transitions.iter().copied(),
+            game.details.as_ref().map(|x| &x.status),
+            &settings,
+            chrono::Utc::now(),
+        );
+        let hour = chrono::Local::now().hour();
+        for event in events {
+            if !settings.should_notify(&id, event.kind(), hour) {
+                debug!("alert suppressed for {}: {event:?}", redact_account(&id));
+                continue;
             }
+            let doctor = match game.info.status.nick_name.as_str() {
+                "" => redact_account(&id),
+                nickname => format!("Dr. {nickname}"),
+            };
+            let app_state_controller = self.app_state_controller.clone();
+            let runtime = tokio::runtime::Handle::current();
+            let id = id.clone();
+            notification::toast_with_click(
+                event.summary(),
+                None,
+                &format!("{doctor}：{}", event.body()),
+                None,
+                move || {
+                    runtime.spawn(async move {
+                        app_state_controller
+                            .exec_wait(|x| x.focus_game(id.clone()))
+                            .await;
+                        tokio::time::sleep(consts::FOCUSED_GAME_HIGHLIGHT_DURATION).await;
+                        app_state_controller.exec(|x| x.clear_focused_game(id));
+                    });
+                },
+            );
         }
 
-        self.app_state_controller.exec(|x| {
+        let alerts_enabled = settings.is_account_enabled(&id);
+        self.app_state_controller.exec(move |x| {
             x.exec_with_game_by_id(id, move |game_info_list, i, mut game_info| {
-                if override_existing || game_info.selected_maps.row_count() == 0 {
-                    game_info.selected_maps = ModelRc::new(VecModel::from(battle_maps_to_set));
+                if game_info.alerts_enabled != alerts_enabled {
+                    game_info.alerts_enabled = alerts_enabled;
                     game_info_list.set_row_data(i, game_info);
                 }
             })
         });
     }
 
+    pub fn set_game_alerts_enabled(&self, id: String, enabled: bool) {
+        self.config_controller
+            .set_game_alerts_enabled(id.clone(), enabled);
+        self.app_state_controller.exec(move |x| {
+            x.exec_with_game_by_id(id, move |game_info_list, i, mut game_info| {
+                game_info.alerts_enabled = enabled;
+                game_info_list.set_row_data(i, game_info);
+            })
+        });
+    }
+
     pub async fn try_ensure_game_images(&self, game: &api_user_model::GameEntry, id: String) {
         let game_resource_entry;
         {
@@ -680,13 +922,23 @@ impl GameController {
     }
 
     pub async fn try_ensure_resources(&self) {
-        if self.stage_data.read().await.is_some() && self.char_pack_summaries.read().await.is_some()
+        if self.stage_data.read().await.is_some()
+            && self.item_data.read().await.is_some()
+            && self.zone_data.read().await.is_some()
+            && self.activity_data.read().await.is_some()
+            && self.retro_data.read().await.is_some()
+            && self.char_pack_summaries.read().await.is_some()
         {
             return;
         }
 
         let mut stage_data_lock = self.stage_data.write().await;
+        let mut item_data_lock = self.item_data.write().await;
+        let mut zone_data_lock = self.zone_data.write().await;
+        let mut activity_data_lock = self.activity_data.write().await;
+        let mut retro_data_lock = self.retro_data.write().await;
         let mut char_pack_summary_lock = self.char_pack_summaries.write().await;
+        let search_index_outdated = stage_data_lock.is_none() || item_data_lock.is_none();
 
         _ = TokioScope::scope_and_block(|s| {
             if stage_data_lock.is_none() {
@@ -695,12 +947,55 @@ impl GameController {
                         arkhost_api::consts::asset::assets::gamedata("excel/stage_table.json");
                     let stage_data = self.load_json_table::<StageTable>(path, None).await;
                     if let Some(stage_data) = stage_data {
-                        self.build_stage_search_tree(&stage_data).await;
                         _ = stage_data_lock.insert(stage_data);
                     }
                 });
             }
 
+            if item_data_lock.is_none() {
+                s.spawn(async {
+                    let path =
+                        arkhost_api::consts::asset::assets::gamedata("excel/item_table.json");
+                    let item_data = self.load_json_table::<ItemTable>(path, None).await;
+                    if let Some(item_data) = item_data {
+                        _ = item_data_lock.insert(item_data);
+                    }
+                });
+            }
+
+            if zone_data_lock.is_none() {
+                s.spawn(async {
+                    let path =
+                        arkhost_api::consts::asset::assets::gamedata("excel/zone_table.json");
+                    let zone_data = self.load_json_table::<ZoneTable>(path, None).await;
+                    if let Some(zone_data) = zone_data {
+                        _ = zone_data_lock.insert(zone_data);
+                    }
+                });
+            }
+
+            if activity_data_lock.is_none() {
+                s.spawn(async {
+                    let path =
+                        arkhost_api::consts::asset::assets::gamedata("excel/activity_table.json");
+                    let activity_data = self.load_json_table::<ActivityTable>(path, None).await;
+                    if let Some(activity_data) = activity_data {
+                        _ = activity_data_lock.insert(activity_data);
+                    }
+                });
+            }
+
+            if retro_data_lock.is_none() {
+                s.spawn(async {
+                    let path =
+                        arkhost_api::consts::asset::assets::gamedata("excel/retro_table.json");
+                    let retro_data = self.load_json_table::<RetroTable>(path, None).await;
+                    if let Some(retro_data) = retro_data {
+                        _ = retro_data_lock.insert(retro_data);
+                    }
+                });
+            }
+
             if !self.config_controller.data_saver_mode_enabled() && char_pack_summary_lock.is_none()
             {
                 s.spawn(async {
@@ -714,6 +1009,13 @@ impl GameController {
                 });
             }
         });
+
+        if search_index_outdated {
+            if let Some(stage_data) = stage_data_lock.as_ref() {
+                *self.stage_search_index.write().await =
+                    StageSearchIndex::new(stage_data, item_data_lock.as_ref());
+            }
+        }
     }
 
     pub async fn confirm_gacha_records(&self) {
@@ -1054,47 +1356,143 @@ impl GameController {
         }
     }
 
-    async fn build_stage_search_tree(&self, stage_data: &StageTable) {
-        let mut stage_search_tree = self.stage_search_tree.write().await;
-        stage_search_tree.clear();
-
-        let mut entries: u32 = 0;
-        for (id, stage) in
-            stage_data.stages.iter().filter(|(_, stage)| {
-                stage.can_battle_replay
-                    && !matches!(
-                        stage.stage_type,
-                        StageType::Training
-                            | StageType::Guide
-                            | StageType::SpecialStory
-                            | StageType::Campaign
-                            | StageType::HandbookBattle
-                            | StageType::ClimbTower
-                    )
-                    && stage.stage_drop_info.display_rewards.iter().any(|x| {
-                        matches!(x.drop_type, StageDropType::Normal | StageDropType::Special)
+    /// 未找到关卡数据时以关卡 ID 作为名称；未指定物品数据时不含掉落
+    fn battle_map_mapping(
+        map_id: &str,
+        stage: Option<&Stage>,
+        item_data: Option<&ItemTable>,
+    ) -> BattleMapMapping {
+        let Some(stage) = stage else {
+            return BattleMapMapping {
+                map_id: map_id.to_owned(),
+                display_name: map_id.to_owned(),
+                ..Default::default()
+            };
+        };
+        let mut drops: Vec<BattleMapDropMapping> = match item_data {
+            Some(item_data) => stage
+                .stage_drop_info
+                .display_rewards
+                .iter()
+                .filter(|x| matches!(x.drop_type, StageDropType::Normal | StageDropType::Special))
+                .filter_map(|x| {
+                    item_data.items.get(&x.id).map(|item| BattleMapDropMapping {
+                        item_id: x.id.clone(),
+                        name: item.name.clone(),
+                        special: x.drop_type == StageDropType::Special,
+                        icon: None,
                     })
-                    && stage.ap_cost > 0
-                    && !stage.is_predefined
-                    && !stage.is_hard_predefined
-                    && !stage.is_skill_selectable_predefined
-                    && !stage.is_story_only
-            })
-        {
-            let id: Arc<String> = Arc::new(id.clone());
-            if !stage.code.is_empty() {
-                let code_name: Vec<char> = stage.code.chars().collect();
-                stage_search_tree.insert(&code_name, id.clone());
-                entries += 1;
-            }
-            if let Some(name) = &stage.name {
-                let name: Vec<char> = name.chars().collect();
-                stage_search_tree.insert(&name, id);
-                entries += 1;
+                })
+                .collect(),
+            None => vec![],
+        };
+        drops.sort_by_key(|x| x.special);
+        BattleMapMapping {
+            map_id: map_id.to_owned(),
+            code_name: stage.code.clone(),
+            display_name: stage.display(),
+            ap_cost: stage.ap_cost,
+            drops,
+            ..Default::default()
+        }
+    }
+
+    /// 作战列表中的关卡，含掉落、图标及开放状态
+    async fn selected_map_mappings(&self, map_ids: &[String]) -> Vec<BattleMapMapping> {
+        let mut battle_maps: Vec<BattleMapMapping> = {
+            let stage_data = self.stage_data.read().await;
+            let item_data = self.item_data.read().await;
+            map_ids
+                .iter()
+                .map(|map_id| {
+                    let stage = stage_data.as_ref().and_then(|x| x.stages.get(map_id));
+                    Self::battle_map_mapping(map_id, stage, item_data.as_ref())
+                })
+                .collect()
+        };
+        self.attach_item_icons(&mut battle_maps).await;
+        self.attach_availability(&mut battle_maps).await;
+        battle_maps
+    }
+
+    /// 标记限时关卡的开放状态，已关闭、未开放或即将关闭时附带替代关卡
+    async fn attach_availability(&self, battle_maps: &mut [BattleMapMapping]) {
+        let stage_data = self.stage_data.read().await;
+        let Some(stage_data) = stage_data.as_ref() else {
+            return;
+        };
+        let zone_data = self.zone_data.read().await;
+        let activity_data = self.activity_data.read().await;
+        let retro_data = self.retro_data.read().await;
+        let opening_times = OpeningTimes {
+            zone_table: zone_data.as_ref(),
+            activity_table: activity_data.as_ref(),
+            retro_table: retro_data.as_ref(),
+        };
+        let now = chrono::Utc::now();
+
+        for battle_map in battle_maps {
+            let Some(stage) = stage_data.stages.get(&battle_map.map_id) else {
+                continue;
+            };
+            let availability = opening_times.availability(stage, now);
+            battle_map.availability_text = availability.description(now);
+            battle_map.availability = match availability {
+                StageAvailability::Closed | StageAvailability::NotYetOpen { .. } => {
+                    BattleMapAvailability::Closed
+                }
+                _ if availability.needs_attention(now) => BattleMapAvailability::ClosingSoon,
+                _ => BattleMapAvailability::Open,
+            };
+            if availability.needs_attention(now) {
+                battle_map.fallback = opening_times
+                    .fallback(stage_data, stage, now)
+                    .filter(|(map_id, _)| *map_id != battle_map.map_id)
+                    .map(|(map_id, x)| (map_id.to_owned(), x.code.clone()));
             }
         }
+    }
+
+    /// 加载掉落物品的图标，省流模式下不加载
+    async fn attach_item_icons(&self, battle_maps: &mut [BattleMapMapping]) {
+        if self.config_controller.data_saver_mode_enabled() {
+            return;
+        }
+        let item_data = self.item_data.read().await;
+        let Some(item_data) = item_data.as_ref() else {
+            return;
+        };
 
-        debug!("inserted {entries} entries into stage search tree");
+        let icon_refs: HashMap<String, ImageDataRef> = {
+            let mut item_icons = self.item_icons.write().await;
+            battle_maps
+                .iter()
+                .flat_map(|x| &x.drops)
+                .map(|x| {
+                    let image_ref = item_icons.entry(x.item_id.clone()).or_default().clone();
+                    (x.item_id.clone(), image_ref)
+                })
+                .collect()
+        };
+        TokioScope::scope_and_block(|s| {
+            for (item_id, image_ref) in &icon_refs {
+                if let Some(item) = item_data.items.get(item_id) {
+                    s.spawn(
+                        self.image_controller
+                            .load_item_icon_if_empty(item, image_ref.clone()),
+                    );
+                }
+            }
+        });
+
+        for drop in battle_maps.iter_mut().flat_map(|x| x.drops.iter_mut()) {
+            if let Some(image_ref) = icon_refs.get(&drop.item_id) {
+                let image_data = image_ref.read().await;
+                if !matches!(image_data.loaded_image, ImageDataRaw::Empty) {
+                    drop.icon = Some(image_data.clone());
+                }
+            }
+        }
     }
 
     fn battle_screenshot_urls(game: &api_arkhost::GameDetails) -> Vec<url::Url> {
@@ -1131,4 +1529,6 @@ mod consts {
     pub const SSE_RECOVER_INITIAL_INTERVAL: Duration = Duration::from_secs(1);
     pub const SSE_RECOVER_MAX_INTERVAL: Duration = Duration::from_secs(60);
     pub const SSE_RECOVER_INTERVAL_BASE: u32 = 2;
+    /// 点击通知后游戏卡片的高亮时长
+    pub const FOCUSED_GAME_HIGHLIGHT_DURATION: Duration = Duration::from_secs(3);
 }
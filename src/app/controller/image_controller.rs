@@ -3,6 +3,7 @@ use crate::app::{
     api_user_model,
     app_state::model::{AssetPath, ImageData, ImageDataRaw, ImageDataRef},
     asset_worker::AssetRef,
+    game_data::Item,
 };
 use anyhow::anyhow;
 use arkhost_api::models::api_arkhost::Avatar;
@@ -54,10 +55,12 @@ impl ImageController {
                 let mut skin_id = details.status.sanitize_secretary_skin_id_for_url();
                 skin_id.push_str(".png");
                 let path: String = arkhost_api::consts::asset::assets::charpack(&skin_id);
+                // 立绘的定位信息与图片尺寸成比例，可直接缩小
                 self.load_image_to_ref(
-                    AssetPath::GameAsset(path),
-                    None,
+                    AssetPath::GameAsset(path.clone()),
+                    Some(path),
                     Some(ImageFormat::Png),
+                    Some(consts::CHAR_ILLUST_MAX_DIMENSION),
                     image_ref,
                 )
                 .await;
@@ -72,9 +75,32 @@ impl ImageController {
         );
         path.push_str(".png");
         self.load_image_to_ref(
-            AssetPath::GameAsset(path),
-            None,
+            AssetPath::GameAsset(path.clone()),
+            Some(path),
             Some(ImageFormat::Png),
+            Some(consts::AVATAR_MAX_DIMENSION),
+            image_ref,
+        )
+        .await;
+    }
+
+    pub async fn load_item_icon_if_empty(&self, item: &Item, image_ref: ImageDataRef) {
+        if !matches!(image_ref.read().await.loaded_image, ImageDataRaw::Empty)
+            || item.icon_id.is_empty()
+        {
+            return;
+        }
+
+        let path = arkhost_api::consts::asset::assets::item(&format!("{}.png", item.icon_id));
+        // 选中的关卡每次刷新时都会加载图标，不再重试加载失败的图标
+        if self.errored_resource_urls.read().await.contains(&path) {
+            return;
+        }
+        self.load_image_to_ref(
+            AssetPath::GameAsset(path.clone()),
+            Some(path),
+            Some(ImageFormat::Png),
+            Some(consts::ITEM_ICON_MAX_DIMENSION),
             image_ref,
         )
         .await;
@@ -85,21 +111,24 @@ impl ImageController {
         path: AssetPath,
         cache_key: Option<String>,
         image_format: Option<ImageFormat>,
+        max_dimension: Option<u32>,
         image_ref: ImageDataRef,
     ) {
         let mut image_ref: tokio::sync::RwLockWriteGuard<ImageData> = image_ref.write().await;
         image_ref.asset_path = path;
         image_ref.cache_key = cache_key;
         image_ref.format = image_format;
+        image_ref.max_dimension = max_dimension;
 
         self.load_image_to_data(&mut image_ref).await;
     }
 
     pub async fn load_image_to_data(&self, image_data: &mut ImageData) {
-        let (path, cache_key, src_format) = (
+        let (path, cache_key, src_format, max_dimension) = (
             image_data.asset_path.clone(),
             image_data.cache_key.clone(),
             image_data.format,
+            image_data.max_dimension,
         );
         let (resp, mut rx) = oneshot::channel();
         match self
@@ -109,6 +138,7 @@ impl ImageController {
                     path,
                     cache_key,
                     src_format,
+                    max_dimension,
                     resp,
                 },
                 &mut rx,
@@ -141,3 +171,12 @@ impl ImageController {
         }
     }
 }
+
+pub mod consts {
+    /// 头像显示尺寸为 64px，保留高分屏下的清晰度
+    pub const AVATAR_MAX_DIMENSION: u32 = 128;
+    /// 立绘显示宽度为 500px
+    pub const CHAR_ILLUST_MAX_DIMENSION: u32 = 1024;
+    /// 掉落物品图标显示尺寸为 24px
+    pub const ITEM_ICON_MAX_DIMENSION: u32 = 64;
+}
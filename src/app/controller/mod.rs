@@ -1,6 +1,7 @@
 pub mod api_user_model;
 pub mod app_state_controller;
 pub mod config_controller;
+pub mod diagnostics_controller;
 pub mod game_controller;
 pub mod game_operation_controller;
 pub mod image_controller;
@@ -14,6 +15,7 @@ extern crate alloc;
 
 use self::api_user_model::ApiUserModel;
 use self::config_controller::ConfigController;
+use self::diagnostics_controller::DiagnosticsController;
 use self::game_controller::GameController;
 use self::game_operation_controller::GameOperationController;
 use self::image_controller::ImageController;
@@ -22,18 +24,20 @@ use self::sender::Sender;
 use self::slot_controller::SlotController;
 use self::user_controller::UserController;
 use self::{app_state_controller::AppStateController, session_controller::SessionController};
-use super::app_state::mapping::{GameOptionsMapping, SlotUpdateDraftMapping};
+use super::app_state::mapping::{AlertSettingsMapping, GameOptionsMapping, SlotUpdateDraftMapping};
 use super::app_state::{AppState, LoginWindowState};
 use super::auth_worker::AuthContext;
+use super::log_export::{self, LogExportFormat};
 use super::ui::*;
-use super::utils::ext_link;
+use super::utils::{db_maintenance::MaintenanceTarget, ext_link, notification};
+use arkhost_api::clients::common::UserState;
 use arkhost_api::models::api_quota::user_tier_availability_rank;
 use log::error;
 use login_window_context::LoginWindowContext;
 use slint::{Model, SharedString};
 use std::fmt::Debug;
 use std::rc::Rc;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 
@@ -71,6 +75,7 @@ pub struct UIContext {
     pub login_window_state: Arc<Mutex<LoginWindowState>>,
     pub app_state_controller: Arc<AppStateController>,
     pub config_controller: Arc<ConfigController>,
+    pub diagnostics_controller: Arc<DiagnosticsController>,
     pub image_controller: Arc<ImageController>,
     pub session_controller: Arc<SessionController>,
     pub game_controller: Arc<GameController>,
@@ -92,19 +97,27 @@ impl UIContext {
         tx_api_worker: mpsc::Sender<ApiCommand>,
         tx_auth_worker: mpsc::Sender<AuthContext>,
         tx_asset_worker: mpsc::Sender<AssetCommand>,
+        user_state: Arc<RwLock<dyn UserState>>,
     ) -> Self {
         let app_state = Arc::new(Mutex::new(app_state));
         let login_window_state = Arc::new(Mutex::new(LoginWindowState::new()));
         let app_state_controller = Arc::new(AppStateController {
             app_state: app_state.clone(),
         });
-        let config_controller = Arc::new(ConfigController::new(app_state_controller.clone()));
+        let config_controller = Arc::new(ConfigController::new(
+            app_state_controller.clone(),
+            user_state,
+        ));
         let sender = Arc::new(Sender {
             api_user_model: api_user_model.clone(),
             tx_api_worker,
             tx_auth_worker,
             tx_asset_worker,
         });
+        let diagnostics_controller = Arc::new(DiagnosticsController::new(
+            app_state_controller.clone(),
+            sender.clone(),
+        ));
         let image_controller = Arc::new(ImageController::new(sender.clone()));
         let game_operation_controller = Arc::new(GameOperationController::new(
             app_state_controller.clone(),
@@ -126,6 +139,7 @@ impl UIContext {
         ));
         let ota_controller = Arc::new(OtaController::new(
             app_state_controller.clone(),
+            config_controller.clone(),
             sender.clone(),
         ));
         let session_controller = Arc::new(SessionController::new(
@@ -148,6 +162,7 @@ impl UIContext {
             app_state_controller,
             image_controller,
             config_controller,
+            diagnostics_controller,
             session_controller,
             game_controller,
             slot_controller,
@@ -188,6 +203,41 @@ impl UIContext {
                 });
             });
         }
+        {
+            let this = self.clone();
+            app.on_export_logs(move |id, format, range, level_tag| {
+                let Some(format) = LogExportFormat::from_index(format as usize) else {
+                    return;
+                };
+                let range_days = log_export::consts::RANGE_OPTIONS_DAYS
+                    .get(range as usize)
+                    .copied()
+                    .flatten();
+                this.app_state_controller
+                    .exec(|x| x.set_log_load_state(id.clone().into(), GameLogLoadState::Loading));
+
+                let this = this.clone();
+                tokio::spawn(async move {
+                    this.game_controller
+                        .export_logs(id.into(), format, range_days, level_tag.into())
+                        .await;
+                });
+            });
+        }
+        {
+            let this = self.clone();
+            app.on_search_logs(move |id, term| {
+                let this = this.clone();
+                let term = term.trim().to_owned();
+                if term.is_empty() {
+                    return;
+                }
+
+                tokio::spawn(async move {
+                    this.game_controller.search_logs(id.into(), term).await;
+                });
+            });
+        }
 
         {
             let this = self.clone();
@@ -440,6 +490,18 @@ impl UIContext {
             });
         }
 
+        {
+            let this = self.clone();
+            app.on_replace_map(move |id, map_id, new_map_id| {
+                let this = this.clone();
+                tokio::spawn(async move {
+                    this.game_controller
+                        .on_replace_map(id.into(), map_id.into(), new_map_id.into())
+                        .await;
+                });
+            });
+        }
+
         {
             let this = self.clone();
             app.on_reset_selected_maps(move |id| {
@@ -458,12 +520,15 @@ impl UIContext {
                 });
 
                 let this = this.clone();
-                let battle_maps = battle_update_fields
+                let battle_maps: Vec<String> = battle_update_fields
                     .maps
                     .iter()
                     .map(|x| x.map_id.into())
                     .collect();
                 tokio::spawn(async move {
+                    this.game_controller
+                        .warn_unavailable_maps(&battle_maps)
+                        .await;
                     this.game_controller
                         .update_game_settings(
                             id.into(),
@@ -506,6 +571,99 @@ impl UIContext {
             });
         }
 
+        {
+            let this = self.clone();
+            app.on_set_beta_channel(move |val| {
+                this.config_controller.set_beta_channel_enabled(val);
+                let this = this.clone();
+                tokio::spawn(async move {
+                    this.ota_controller.check_release_update().await;
+                });
+            });
+        }
+
+        {
+            let this = self.clone();
+            app.on_set_http_cache_budget(move |index| {
+                this.config_controller
+                    .set_http_cache_budget_index(index as usize);
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || {
+                    this.config_controller.sweep_http_cache();
+                    this.config_controller.recalculate_disk_usage();
+                });
+            });
+        }
+
+        {
+            let this = self.clone();
+            app.on_set_alert_settings(move |info| {
+                let current = this.config_controller.alert_settings();
+                let mapping = AlertSettingsMapping::from_ui(&info, &current);
+                this.config_controller
+                    .set_alert_settings(mapping.to_alert_settings());
+            });
+        }
+
+        {
+            let this = self.clone();
+            app.on_set_game_alerts_enabled(move |id, enabled| {
+                this.game_controller
+                    .set_game_alerts_enabled(id.into(), enabled);
+            });
+        }
+
+        {
+            let this = self.clone();
+            app.on_export_data(move |path, include_credentials| {
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || {
+                    let path = (!path.is_empty()).then(|| std::path::PathBuf::from(path.as_str()));
+                    match this
+                        .config_controller
+                        .export_data(path.as_deref(), include_credentials)
+                    {
+                        Ok(path) => {
+                            let path_str = path.display().to_string();
+                            notification::toast("导出数据成功", None, &path_str, None);
+                            this.app_state_controller.exec(move |x| {
+                                x.state_globals(move |s| s.set_data_bundle_path(path_str.into()))
+                            });
+                        }
+                        Err(e) => {
+                            error!("error exporting data: {e:?}");
+                            notification::toast("导出数据失败", None, &format!("{e}"), None);
+                        }
+                    }
+                });
+            });
+        }
+
+        {
+            let this = self.clone();
+            app.on_import_data(move |path| {
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || {
+                    if path.is_empty() {
+                        notification::toast("导入数据失败", None, "请填写导入文件路径", None);
+                        return;
+                    }
+                    match this
+                        .config_controller
+                        .import_data(std::path::Path::new(path.as_str()))
+                    {
+                        Ok(summary) => {
+                            notification::toast("导入数据成功", None, &summary.to_string(), None)
+                        }
+                        Err(e) => {
+                            error!("error importing data: {e:?}");
+                            notification::toast("导入数据失败", None, &format!("{e}"), None);
+                        }
+                    }
+                });
+            });
+        }
+
         {
             let this = self.clone();
             app.on_set_clean_data(move |val| {
@@ -516,6 +674,109 @@ impl UIContext {
             })
         }
 
+        {
+            let this = self.clone();
+            app.on_set_compaction(move |val| {
+                _ = this
+                    .config_controller
+                    .set_compaction(val)
+                    .map_err(|e| error!("error requesting compaction: {e}"));
+            })
+        }
+
+        {
+            let this = self.clone();
+            app.on_verify_db(move || {
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || match this.config_controller.verify_db() {
+                    Ok((reports, removed)) => {
+                        let mut body = reports
+                            .iter()
+                            .filter(|x| !x.is_ok())
+                            .map(|x| x.to_string())
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        if body.is_empty() {
+                            body = "所有数据均正常".to_owned();
+                        } else if removed > 0 {
+                            body += &format!("\n已删除 {removed} 条无法解码的数据");
+                        }
+                        notification::toast("数据库校验完成", None, &body, None);
+                    }
+                    Err(e) => {
+                        error!("error verifying DB: {e:?}");
+                        notification::toast("数据库校验失败", None, &format!("{e}"), None);
+                    }
+                });
+            })
+        }
+
+        {
+            let this = self.clone();
+            app.on_clear_db(move |index| {
+                let Some(target) = MaintenanceTarget::IN_APP.get(index as usize).copied() else {
+                    error!("on_clear_db: invalid target index {index}");
+                    return;
+                };
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || {
+                    match this.config_controller.clear_db(target) {
+                        Ok(()) => notification::toast(&format!("已清除{target}"), None, "", None),
+                        Err(e) => {
+                            error!("error clearing {}: {e:?}", target.name());
+                            notification::toast(
+                                &format!("清除{target}失败"),
+                                None,
+                                &format!("{e}"),
+                                None,
+                            );
+                        }
+                    }
+                });
+            })
+        }
+
+        {
+            let this = self.clone();
+            app.on_refresh_client_log(move || {
+                let this = this.clone();
+                tokio::spawn(async move {
+                    this.diagnostics_controller
+                        .refresh_asset_cache_stats()
+                        .await;
+                });
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || {
+                    this.diagnostics_controller.refresh_client_log();
+                });
+            })
+        }
+
+        {
+            let this = self.clone();
+            app.on_set_client_log_following(move |val| {
+                this.diagnostics_controller.clone().set_following(val);
+            })
+        }
+
+        {
+            let this = self.clone();
+            app.on_create_diagnostics_bundle(move || {
+                let this = this.clone();
+                tokio::task::spawn_blocking(move || {
+                    this.diagnostics_controller.create_diagnostics_bundle();
+                });
+            })
+        }
+
+        {
+            let this = self.clone();
+            app.on_set_log_level(move |index| {
+                this.diagnostics_controller
+                    .set_log_level_index(index as usize);
+            })
+        }
+
         {
             let this = self.clone();
             app.on_recalculate_data_disk_usage(move || {
@@ -1,4 +1,5 @@
 use super::app_state_controller::AppStateController;
+use super::config_controller::ConfigController;
 use super::{AssetCommand, Sender};
 use crate::app::ota::ReleaseRecord;
 use crate::app::utils::{app_metadata, data_dir, notification};
@@ -21,6 +22,7 @@ use url::Url;
 
 pub struct OtaController {
     app_state_controller: Arc<AppStateController>,
+    config_controller: Arc<ConfigController>,
     sender: Arc<Sender>,
 
     cur_update_mode: Mutex<Option<asset_worker::ReleaseUpdateType>>,
@@ -46,9 +48,14 @@ impl<R: Read> Read for DownloadReader<R> {
 }
 
 impl OtaController {
-    pub fn new(app_state_controller: Arc<AppStateController>, sender: Arc<Sender>) -> Self {
+    pub fn new(
+        app_state_controller: Arc<AppStateController>,
+        config_controller: Arc<ConfigController>,
+        sender: Arc<Sender>,
+    ) -> Self {
         Self {
             app_state_controller,
+            config_controller,
             sender,
 
             cur_update_mode: Mutex::new(None),
@@ -65,11 +72,14 @@ impl OtaController {
         let (mode, release, download_size) = loop {
             let mode = match attempts.next() {
                 Some(mode) => mode,
-                None => return,
+                None => {
+                    self.clear_release_info().await;
+                    return;
+                }
             };
 
             let (resp, mut rx) = oneshot::channel();
-            let branch = app_metadata::RELEASE_UPDATE_BRANCH;
+            let branch = self.release_branch();
             match self
                 .sender
                 .send_asset_request(
@@ -84,9 +94,10 @@ impl OtaController {
             {
                 Ok(None) => {
                     info!("no updates from branch '{branch}'.");
+                    self.clear_release_info().await;
                     return;
                 }
-                Ok(Some((release, download_size, _))) => break (mode, release, download_size),
+                Ok(Some((release, artifact, _))) => break (mode, release, artifact.size as usize),
                 Err(e) => {
                     warn!("unable to check update from branch '{branch}' with type {mode:?}: {e}");
                     continue;
@@ -147,9 +158,9 @@ impl OtaController {
             })
         });
 
-        let branch = app_metadata::RELEASE_UPDATE_BRANCH;
+        let branch = self.release_branch();
         let (resp, mut rx) = oneshot::channel();
-        let (release, download_size, asset_path) = self
+        let (release, artifact, asset_path) = self
             .sender
             .send_asset_request(
                 AssetCommand::CheckReleaseUpdate {
@@ -161,7 +172,7 @@ impl OtaController {
             )
             .await?
             .ok_or(anyhow::anyhow!("unable to get release"))?;
-        self.update_release_info(mode, &release, download_size);
+        self.update_release_info(mode, &release, artifact.size as usize);
         let target_file_path =
             data_dir::data_dir().join(arkhost_ota::consts::TMP_PATCH_EXECUTABLE_NAME);
         let target_hash = hex::decode(&release.file.hash)?;
@@ -197,22 +208,20 @@ impl OtaController {
         };
         debug!("download file path: {}", download_file_path.display());
 
-        let download_url = Url::parse(
-            app::env::override_asset_server().unwrap_or(arkhost_api::consts::asset::API_BASE_URL),
-        )?
-        .join(&asset_path)?;
-        if !download_file_exists(mode, &download_file_path, &target_hash).await {
+        let download_url =
+            Url::parse(&app::app_config::get().asset_server.value)?.join(&asset_path)?;
+        let artifact_hash = hex::decode(&artifact.sha256)?;
+        if !download_file_exists(&download_file_path, &artifact_hash).await {
             self.try_download_and_save(
-                mode,
                 download_url,
                 &download_file_path,
-                download_size,
-                &target_hash,
+                artifact.size,
+                &artifact_hash,
             )
             .await?;
         } else {
             debug!(
-                "skipping downloading on existing tmp file hash matches target hash: {}",
+                "skipping downloading on existing tmp file hash matches artifact hash: {}",
                 download_file_path.display()
             );
         }
@@ -225,8 +234,15 @@ impl OtaController {
 
         match mode {
             asset_worker::ReleaseUpdateType::Delta => {
-                self.try_patch_self_executable(&download_file_path, &target_file_path, target_hash)
-                    .await?;
+                let format = arkhost_ota::bin_diff::PatchFormat::from_filename(&asset_path)
+                    .ok_or(anyhow::anyhow!("unknown patch format: {asset_path}"))?;
+                self.try_patch_self_executable(
+                    format,
+                    &download_file_path,
+                    &target_file_path,
+                    target_hash,
+                )
+                .await?;
                 // 移除patch临时文件
                 _ = tokio::fs::remove_file(download_file_path).await;
             }
@@ -237,14 +253,17 @@ impl OtaController {
         Ok(())
     }
 
+    ///
+    /// 下载清单中的文件，下载前检查大小，下载后检查大小及哈希值
+    ///
     async fn try_download_and_save(
         &self,
-        mode: asset_worker::ReleaseUpdateType,
         url: Url,
         download_file_path: &Path,
-        total_size: usize,
-        target_hash: &[u8],
+        artifact_size: u64,
+        artifact_hash: &[u8],
     ) -> Result<(), anyhow::Error> {
+        let total_size = artifact_size as usize;
         let mut file = match std::fs::File::create(download_file_path) {
             Ok(file) => file,
             Err(e) => {
@@ -284,9 +303,17 @@ impl OtaController {
                         warn!("download failed: error on sending request: {e}");
                         e
                     })?;
+                if matches!(response.content_length(), Some(len) if len != artifact_size) {
+                    notification::toast("更新失败", None, "文件大小校验失败！请尝试重新下载", None);
+                    bail!(
+                        "download failed: size mismatch: expected: {artifact_size}; got: {:?}",
+                        response.content_length()
+                    );
+                }
 
                 let mut download_reader = DownloadReader {
-                    inner: response,
+                    // 多读一个字节以检测超出清单大小的文件
+                    inner: response.take(artifact_size + 1),
                     tot_bytes_read: 0usize,
                     tx_bytes_read,
                     hasher: sha2::Sha256::new(),
@@ -341,15 +368,20 @@ impl OtaController {
             humansize::format_size(bytes_read, humansize::DECIMAL)
         );
 
-        if matches!(mode, asset_worker::ReleaseUpdateType::FullDownload)
-            && hash[..] != target_hash[..]
-        {
+        if bytes_read != artifact_size {
+            notification::toast("更新失败", None, "文件大小校验失败！请尝试重新下载", None);
+            _ = tokio::fs::remove_file(download_file_path).await;
+            bail!(
+                "failed to verify downloaded file {download_file_path:?} size: expected: {artifact_size}; downloaded: {bytes_read}",
+            );
+        }
+        if hash[..] != artifact_hash[..] {
             notification::toast("更新失败", None, "哈希值校验失败！请尝试重新下载", None);
             _ = tokio::fs::remove_file(download_file_path).await;
             bail!(
                 "failed to verify downloaded file {download_file_path:?} integrity: expected: {}; downloaded: {}",
-                &hex::encode(hash),
-                &hex::encode(target_hash)
+                &hex::encode(artifact_hash),
+                &hex::encode(hash)
             );
         };
         Ok(())
@@ -357,6 +389,7 @@ impl OtaController {
 
     async fn try_patch_self_executable(
         &self,
+        format: arkhost_ota::bin_diff::PatchFormat,
         patch_file_path: &Path,
         target_file_path: &Path,
         target_hash: Vec<u8>,
@@ -387,7 +420,7 @@ impl OtaController {
         let mut source = tokio::fs::File::open(env::current_exe()?).await?;
         let mut source_bytes = Vec::new();
         source.read_to_end(&mut source_bytes).await?;
-        let target_bytes = match arkhost_ota::bin_diff::bspatch_check_integrity(
+        let target_bytes = match format.patch_check_integrity(
             &source_bytes,
             &patch_bytes,
             &target_hash,
@@ -421,6 +454,30 @@ impl OtaController {
         Ok(())
     }
 
+    /// 切换分支等情况下更新不再可用时，隐藏更新提示（正在下载时除外）
+    async fn clear_release_info(&self) {
+        if self.updating.load(Ordering::Acquire) {
+            return;
+        }
+        let mut cur_update_mode = self.cur_update_mode.lock().await;
+        if cur_update_mode.take().is_some() {
+            self.app_state_controller.exec(move |x| {
+                x.state_globals(move |x| {
+                    x.set_update_version("".into());
+                })
+            });
+        }
+    }
+
+    /// 开启 Beta 版本更新时检查 Beta 分支（若没有 Beta 分支或主分支版本更新则仍使用主分支）
+    fn release_branch(&self) -> &'static str {
+        if self.config_controller.beta_channel_enabled() {
+            arkhost_ota::consts::BETA_BRANCH
+        } else {
+            app_metadata::RELEASE_UPDATE_BRANCH
+        }
+    }
+
     fn update_release_info(
         &self,
         mode: asset_worker::ReleaseUpdateType,
@@ -474,12 +531,10 @@ impl OtaController {
 }
 
 async fn download_file_exists(
-    mode: asset_worker::ReleaseUpdateType,
     download_file_path: &std::path::PathBuf,
-    target_hash: &Vec<u8>,
+    artifact_hash: &[u8],
 ) -> bool {
-    matches!(mode, asset_worker::ReleaseUpdateType::FullDownload)
-        && matches!(tokio::fs::try_exists(&download_file_path).await, Ok(true))
+    matches!(tokio::fs::try_exists(&download_file_path).await, Ok(true))
         && tokio::fs::File::open(download_file_path)
             .and_then(|f| async move {
                 let mut reader = tokio::io::BufReader::new(f);
@@ -498,7 +553,7 @@ async fn download_file_exists(
             })
             .await
             .ok()
-            .map_or(false, |x| x[..] == *target_hash)
+            .map_or(false, |x| x[..] == *artifact_hash)
 }
 
 fn blocking_client() -> reqwest::blocking::Client {
@@ -508,7 +563,7 @@ fn blocking_client() -> reqwest::blocking::Client {
         reqwest::header::HeaderValue::from_static(arkhost_api::consts::asset::REFERER_URL),
     );
 
-    reqwest::blocking::ClientBuilder::new()
+    let mut client_builder = reqwest::blocking::ClientBuilder::new()
         .default_headers(headers)
         .min_tls_version(reqwest::tls::Version::TLS_1_2)
         .max_tls_version(reqwest::tls::Version::TLS_1_3)
@@ -516,7 +571,9 @@ fn blocking_client() -> reqwest::blocking::Client {
         .use_rustls_tls()
         .gzip(true)
         .brotli(true)
-        .connect_timeout(Duration::from_secs(8))
-        .build()
-        .unwrap()
+        .connect_timeout(Duration::from_secs(8));
+    if let Some(proxy) = app::app_config::get().reqwest_proxy() {
+        client_builder = client_builder.proxy(proxy);
+    }
+    client_builder.build().unwrap()
 }
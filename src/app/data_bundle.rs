@@ -0,0 +1,236 @@
+use super::{
+    app_state::model::UserConfig,
+    ota,
+    utils::{app_metadata, data_dir::data_dir_create_all, db, user_state::UserStateDBStore},
+};
+use anyhow::bail;
+use arkhost_api::clients::common::UserState;
+use arkhost_ota::keys::KeyRotation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+///
+/// 导出的设置及数据，以 JSON 格式保存
+///
+/// 不包含资源缓存，以及更新备份等与本机文件相关的记录
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataBundle {
+    /// 导出格式版本，见 [`consts::FORMAT_VERSION`]
+    pub format_version: u32,
+    pub app_version: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub user_config: Option<UserConfig>,
+    /// 导出时选择不包含登录凭据则为空
+    #[serde(default)]
+    pub credentials: Option<Credentials>,
+    #[serde(default)]
+    pub ota: Option<OtaState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credentials {
+    pub jwt: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OtaState {
+    pub last_seen_index_serial: Option<u64>,
+    pub key_rotations: Vec<KeyRotation>,
+}
+
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub user_config: bool,
+    pub credentials: bool,
+    pub ota: bool,
+}
+
+impl std::fmt::Display for ImportSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut items = Vec::new();
+        if self.user_config {
+            items.push("设置");
+        }
+        if self.credentials {
+            items.push("登录凭据");
+        }
+        if self.ota {
+            items.push("更新记录");
+        }
+        if items.is_empty() {
+            write!(f, "无可导入的数据")
+        } else {
+            write!(f, "已导入：{}", items.join("、"))
+        }
+    }
+}
+
+impl DataBundle {
+    /// 从数据库中读取当前数据
+    pub fn collect(include_credentials: bool) -> anyhow::Result<Self> {
+        let env = db::env();
+        let user_config = {
+            let config_db: heed::Database<heed::types::Str, heed::types::SerdeJson<UserConfig>> =
+                db::database(Some(db::consts::db::USER_CONFIG))?;
+            let rtxn = env.read_txn()?;
+            config_db.get(&rtxn, db::consts::user_config::DEFAULT_USER)?
+        };
+        let credentials = if include_credentials {
+            let mut user_state = UserStateDBStore::new();
+            user_state.load_from_db()?;
+            user_state.login_state().map(|jwt| Credentials { jwt })
+        } else {
+            None
+        };
+        let ota = OtaState {
+            last_seen_index_serial: ota::last_seen_index_serial()?,
+            key_rotations: ota::key_rotations()?,
+        };
+
+        Ok(Self {
+            format_version: consts::FORMAT_VERSION,
+            app_version: app_metadata::CARGO_PKG_VERSION.map(|x| x.to_owned()),
+            created_at: Utc::now(),
+            user_config,
+            credentials,
+            ota: Some(ota),
+        })
+    }
+
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::de::from_slice(bytes)?;
+        match value.get("format_version").and_then(|x| x.as_u64()) {
+            Some(version) if version == consts::FORMAT_VERSION as u64 => {}
+            Some(version) => bail!(
+                "unsupported format version {version}, expected {}",
+                consts::FORMAT_VERSION
+            ),
+            None => bail!("missing format version, not an exported data file"),
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    ///
+    /// 将数据合并到数据库中
+    ///
+    /// - 设置：以导入的数据为准，作战截图缓存记录合并，抽卡记录时间取较新者
+    /// - 登录凭据：覆盖当前凭据
+    /// - 更新记录：索引序号取较大者，公钥轮换声明合并（使用时仍会校验签名）
+    ///
+    pub fn merge_into_db(self) -> anyhow::Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        let env = db::env();
+        if let Some(imported) = self.user_config {
+            let config_db: heed::Database<heed::types::Str, heed::types::SerdeJson<UserConfig>> =
+                db::database(Some(db::consts::db::USER_CONFIG))?;
+            let mut wtxn = env.write_txn()?;
+            let current = config_db.get(&wtxn, db::consts::user_config::DEFAULT_USER)?;
+            let merged = merge_user_config(current.unwrap_or_default(), imported);
+            config_db.put(&mut wtxn, db::consts::user_config::DEFAULT_USER, &merged)?;
+            wtxn.commit()?;
+            summary.user_config = true;
+        }
+        if let Some(credentials) = self.credentials {
+            UserStateDBStore::save_login_state(credentials.jwt)?;
+            summary.credentials = true;
+        }
+        if let Some(ota_state) = self.ota {
+            if let Some(serial) = ota_state.last_seen_index_serial {
+                ota::update_last_seen_index_serial(serial)?;
+            }
+            let mut rotations = ota::key_rotations()?;
+            for rotation in ota_state.key_rotations {
+                if !rotations.contains(&rotation) {
+                    rotations.push(rotation);
+                }
+            }
+            ota::upsert_key_rotations(&rotations)?;
+            summary.ota = true;
+        }
+        Ok(summary)
+    }
+}
+
+fn merge_user_config(current: UserConfig, mut imported: UserConfig) -> UserConfig {
+    imported.last_ssr_record_ts = imported.last_ssr_record_ts.max(current.last_ssr_record_ts);
+    for (game_id, urls) in current.cached_battle_screenshots {
+        imported
+            .cached_battle_screenshots
+            .entry(game_id)
+            .or_insert(urls);
+    }
+    imported
+}
+
+/// 导出到 `path`，未指定时导出到数据目录下，返回实际导出的路径
+pub fn export_to_file(path: Option<&Path>, include_credentials: bool) -> anyhow::Result<PathBuf> {
+    let bundle = DataBundle::collect(include_credentials)?;
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => {
+            let dir = data_dir_create_all().join(consts::EXPORT_DIR);
+            std::fs::create_dir_all(&dir)?;
+            dir.join(format!(
+                "closure-studio-{}.json",
+                bundle.created_at.format("%Y%m%d-%H%M%S")
+            ))
+        }
+    };
+    std::fs::write(&path, serde_json::ser::to_vec_pretty(&bundle)?)?;
+    Ok(path)
+}
+
+pub fn import_from_file(path: &Path) -> anyhow::Result<ImportSummary> {
+    DataBundle::parse(&std::fs::read(path)?)?.merge_into_db()
+}
+
+pub mod consts {
+    pub const FORMAT_VERSION: u32 = 1;
+    pub const EXPORT_DIR: &str = "export";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_format_version() {
+        let bundle = DataBundle {
+            format_version: consts::FORMAT_VERSION,
+            app_version: None,
+            created_at: Utc::now(),
+            user_config: Some(UserConfig::default()),
+            credentials: None,
+            ota: None,
+        };
+        let mut value = serde_json::to_value(&bundle).unwrap();
+        assert!(DataBundle::parse(value.to_string().as_bytes()).is_ok());
+
+        value["format_version"] = (consts::FORMAT_VERSION + 1).into();
+        assert!(DataBundle::parse(value.to_string().as_bytes()).is_err());
+        assert!(DataBundle::parse(b"{}").is_err());
+    }
+
+    #[test]
+    fn test_merge_user_config() {
+        let url = url::Url::parse("https://example.com/a.png").unwrap();
+        let current = UserConfig {
+            data_saver_mode_enabled: true,
+            last_ssr_record_ts: DateTime::from_timestamp(200, 0).unwrap(),
+            cached_battle_screenshots: [("current".to_owned(), vec![url.clone()])].into(),
+            ..Default::default()
+        };
+        let imported = UserConfig {
+            last_ssr_record_ts: DateTime::from_timestamp(100, 0).unwrap(),
+            cached_battle_screenshots: [("imported".to_owned(), vec![url])].into(),
+            ..Default::default()
+        };
+
+        let merged = merge_user_config(current, imported);
+        assert_eq!(merged.last_ssr_record_ts.timestamp(), 200);
+        assert_eq!(merged.cached_battle_screenshots.len(), 2);
+        assert!(!merged.data_saver_mode_enabled);
+    }
+}
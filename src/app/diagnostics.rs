@@ -0,0 +1,332 @@
+use super::{
+    app_config::{self, AppConfig},
+    app_state::model::UserConfig,
+    data_bundle,
+    utils::{
+        app_metadata,
+        client_log::{self, RotatingFile},
+        data_dir, db, db_maintenance,
+    },
+};
+use chrono::Local;
+use heed::types::{SerdeJson, Str};
+use std::{
+    fmt::Write as _,
+    io::{Seek, Write},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+};
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// 崩溃时的退出状态及错误信息
+pub struct CrashInfo<'a> {
+    pub exit_status: &'a str,
+    pub error_info: &'a str,
+}
+
+static API_ERRORS: OnceLock<Option<Mutex<RotatingFile>>> = OnceLock::new();
+
+/// 记录 API 请求错误，供诊断信息包汇总
+pub fn record_api_error(operation: &str, err: &anyhow::Error) {
+    let file = API_ERRORS.get_or_init(|| {
+        RotatingFile::open(
+            client_log::log_dir().join(consts::API_ERROR_LOG_FILE),
+            consts::API_ERROR_LOG_MAX_SIZE,
+            1,
+        )
+        .map_err(|e| log::error!("record_api_error: unable to open API error log: {e}"))
+        .ok()
+        .map(Mutex::new)
+    });
+    if let Some(file) = file {
+        let line = format!(
+            "[{}] {operation}: {}\n",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            format!("{err:#}").replace(['\r', '\n'], " ")
+        );
+        _ = file.lock().unwrap().write_all(line.as_bytes());
+    }
+}
+
+fn recent_api_errors() -> Vec<String> {
+    let path = client_log::log_dir().join(consts::API_ERROR_LOG_FILE);
+    let Ok(content) = client_log::tail(&path, consts::API_ERROR_LOG_MAX_SIZE) else {
+        return vec![];
+    };
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(consts::RECENT_API_ERRORS)..]
+        .iter()
+        .map(|x| x.to_string())
+        .collect()
+}
+
+fn os_info() -> String {
+    let mut info = format!("{} {}", std::env::consts::OS, std::env::consts::ARCH);
+    #[cfg(target_os = "windows")]
+    {
+        use winreg::{enums::HKEY_LOCAL_MACHINE, RegKey};
+        if let Ok(key) = RegKey::predef(HKEY_LOCAL_MACHINE)
+            .open_subkey(r#"SOFTWARE\Microsoft\Windows NT\CurrentVersion"#)
+        {
+            for name in ["ProductName", "DisplayVersion", "CurrentBuild"] {
+                if let Ok(val) = key.get_value::<String, _>(name) {
+                    info += &format!(" {val}");
+                }
+            }
+        }
+    }
+    #[cfg(target_os = "linux")]
+    if let Ok(os_release) = std::fs::read_to_string("/etc/os-release") {
+        if let Some(name) = os_release
+            .lines()
+            .find_map(|x| x.strip_prefix("PRETTY_NAME="))
+        {
+            info += &format!(" {}", name.trim_matches('"'));
+        }
+    }
+    info
+}
+
+fn renderer_info() -> String {
+    let mut renderers = vec![];
+    if cfg!(feature = "renderer-skia") {
+        renderers.push("skia");
+    }
+    if cfg!(feature = "renderer-femtovg") {
+        renderers.push("femtovg");
+    }
+    let mut info = renderers.join(", ");
+    for var in ["SLINT_BACKEND", "SLINT_RENDERER"] {
+        if let Ok(val) = std::env::var(var) {
+            info += &format!(" ({var}={val})");
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        info += &format!(
+            "\nWebView2: {}",
+            super::utils::webview2::test_installation_ver().unwrap_or("未安装".into())
+        );
+    }
+    info
+}
+
+fn info_txt(crash: Option<&CrashInfo>) -> String {
+    let mut info = String::new();
+    _ = writeln!(
+        info,
+        "APP 版本号: {}",
+        app_metadata::CARGO_PKG_VERSION.unwrap_or("not found")
+    );
+    _ = writeln!(
+        info,
+        "SHA256: {}",
+        app_metadata::executable_sha256().map_or("unable to hash".into(), |x| hex::encode(*x))
+    );
+    _ = writeln!(info, "系统: {}", os_info());
+    _ = writeln!(info, "渲染器: {}", renderer_info());
+    _ = writeln!(info, "数据目录: {}", data_dir::data_dir().display());
+    _ = writeln!(
+        info,
+        "便携模式: {}",
+        data_dir::portable_data_dir().is_some()
+    );
+    _ = writeln!(info, "生成时间: {}", Local::now().to_rfc3339());
+    if let Some(crash) = crash {
+        _ = writeln!(info, "\n[崩溃信息]");
+        _ = writeln!(info, "ExitStatus: {}", crash.exit_status);
+        _ = writeln!(info, "{}", crash.error_info);
+    }
+    _ = writeln!(info, "\n[最近的 API 错误]");
+    for line in recent_api_errors() {
+        _ = writeln!(info, "{line}");
+    }
+    info
+}
+
+/// 隐藏代理地址中的用户名及密码
+fn redact_app_config(config: &AppConfig) -> AppConfig {
+    let mut config = config.clone();
+    if let Some(proxy) = config.proxy.value.as_mut() {
+        if let Ok(mut url) = url::Url::parse(proxy) {
+            if !url.username().is_empty() || url.password().is_some() {
+                _ = url.set_username(consts::REDACTED);
+                _ = url.set_password(None);
+                *proxy = url.to_string();
+            }
+        }
+    }
+    config
+}
+
+/// 作战截图的缓存及不提醒的账号均含账号，仅保留数量
+fn redact_user_config(config: &UserConfig) -> serde_json::Result<serde_json::Value> {
+    let mut value = serde_json::to_value(config)?;
+    if let Some(screenshots) = value.get_mut("cached_battle_screenshots") {
+        let count = screenshots.as_object().map_or(0, |x| x.len());
+        *screenshots = format!("{} ({count} accounts)", consts::REDACTED).into();
+    }
+    if let Some(accounts) = value.pointer_mut("/alert_settings/disabled_accounts") {
+        let count = accounts.as_array().map_or(0, |x| x.len());
+        *accounts = format!("{} ({count} accounts)", consts::REDACTED).into();
+    }
+    Ok(value)
+}
+
+/// `verify` 为 `false` 时跳过耗时的完整校验
+fn db_txt(verify: bool) -> String {
+    let env = db::env_or_none().read().unwrap();
+    let Some(env) = env.as_ref() else {
+        return "数据库未打开\n".into();
+    };
+    let mut info = String::new();
+    _ = writeln!(
+        info,
+        "路径: {}\n占用: {}",
+        db::env_path().display(),
+        env.real_disk_size()
+            .map_or_else(|e| e.to_string(), |x| x.to_string())
+    );
+    _ = writeln!(info, "\n[{}]", db::consts::db::SCHEMA_INFO);
+    let schema_info = env.read_txn().and_then(|rtxn| {
+        let Some(db) = env.open_database::<Str, Str>(&rtxn, Some(db::consts::db::SCHEMA_INFO))?
+        else {
+            return Ok(vec![]);
+        };
+        db.iter(&rtxn)?
+            .map(|x| x.map(|(k, v)| format!("{k} = {v}")))
+            .collect::<heed::Result<Vec<_>>>()
+    });
+    match schema_info {
+        Ok(lines) => lines.iter().for_each(|x| _ = writeln!(info, "{x}")),
+        Err(e) => _ = writeln!(info, "读取失败：{e}"),
+    }
+    _ = writeln!(info, "\n[校验]");
+    if !verify {
+        _ = writeln!(info, "已跳过");
+        return info;
+    }
+    for report in db_maintenance::verify(env) {
+        _ = writeln!(info, "{report}");
+    }
+    info
+}
+
+fn stored_user_config() -> anyhow::Result<Option<UserConfig>> {
+    let env = db::env_or_none().read().unwrap();
+    let Some(env) = env.as_ref() else {
+        return Ok(None);
+    };
+    let rtxn = env.read_txn()?;
+    let Some(db) =
+        env.open_database::<Str, SerdeJson<UserConfig>>(&rtxn, Some(db::consts::db::USER_CONFIG))?
+    else {
+        return Ok(None);
+    };
+    Ok(db.get(&rtxn, db::consts::user_config::DEFAULT_USER)?)
+}
+
+fn append_bytes<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    zip.start_file(
+        name,
+        SimpleFileOptions::default().compression_method(CompressionMethod::Deflated),
+    )?;
+    zip.write_all(bytes)?;
+    Ok(())
+}
+
+///
+/// 生成诊断信息包（`.zip`），包含日志文件、版本、系统信息、隐藏敏感信息后的设置、数据库信息及最近的 API 错误
+///
+/// 未指定 `path` 时生成到数据目录下；返回实际生成的路径。
+/// 崩溃时（`crash` 不为 `None`）跳过数据库的完整校验，以免延迟显示错误信息
+///
+pub fn create_bundle(crash: Option<&CrashInfo>, path: Option<&Path>) -> anyhow::Result<PathBuf> {
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => {
+            let dir = data_dir::data_dir_create_all().join(data_bundle::consts::EXPORT_DIR);
+            std::fs::create_dir_all(&dir)?;
+            dir.join(format!(
+                "diagnostics-{}.zip",
+                Local::now().format("%Y%m%d-%H%M%S")
+            ))
+        }
+    };
+    let file = std::fs::File::create(&path)?;
+    let mut zip = ZipWriter::new(file);
+
+    append_bytes(&mut zip, "info.txt", info_txt(crash).as_bytes())?;
+    append_bytes(
+        &mut zip,
+        "config.txt",
+        redact_app_config(app_config::get()).to_string().as_bytes(),
+    )?;
+    let user_config = match stored_user_config() {
+        Ok(Some(config)) => serde_json::to_string_pretty(&redact_user_config(&config)?)?,
+        Ok(None) => "null".into(),
+        Err(e) => format!("\"读取失败：{e}\""),
+    };
+    append_bytes(&mut zip, "user_config.json", user_config.as_bytes())?;
+    append_bytes(&mut zip, "db.txt", db_txt(crash.is_none()).as_bytes())?;
+
+    // 日志文件可能正在写入，先完整读取以免与记录的大小不一致
+    for entry in std::fs::read_dir(client_log::log_dir())? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        // zip 中的路径分隔符为 `/`
+        let name = format!(
+            "{}/{}",
+            client_log::consts::LOG_DIR,
+            entry.file_name().to_string_lossy()
+        );
+        match std::fs::read(entry.path()) {
+            Ok(bytes) => append_bytes(&mut zip, &name, &bytes)?,
+            Err(e) => log::warn!("create_bundle: skipped {name}: {e}"),
+        }
+    }
+
+    zip.finish()?;
+    Ok(path)
+}
+
+pub mod consts {
+    pub const API_ERROR_LOG_FILE: &str = "api_errors.log";
+    pub const API_ERROR_LOG_MAX_SIZE: u64 = 256 << 10;
+    /// 诊断信息包中汇总的 API 错误数量
+    pub const RECENT_API_ERRORS: usize = 50;
+    pub const REDACTED: &str = "***";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_user_config() {
+        let mut config = UserConfig::default();
+        config.cached_battle_screenshots.insert(
+            "13800000000".into(),
+            vec![url::Url::parse("https://example.com/a.png").unwrap()],
+        );
+        config
+            .alert_settings
+            .disabled_accounts
+            .insert("13800000001".into());
+        let value = redact_user_config(&config).unwrap();
+        let text = value.to_string();
+        assert!(!text.contains("13800000000"));
+        assert!(!text.contains("example.com"));
+        assert_eq!(
+            value["cached_battle_screenshots"],
+            format!("{} (1 accounts)", consts::REDACTED)
+        );
+        assert!(!text.contains("13800000001"));
+    }
+}
@@ -9,6 +9,23 @@ pub fn data_dir() -> Option<&'static str> {
         .map(|x| x.as_str())
 }
 
+/// 配置文件路径
+pub fn config_path() -> Option<&'static str> {
+    static CONFIG_PATH: OnceLock<Option<String>> = OnceLock::new();
+    CONFIG_PATH
+        .get_or_init(|| std::env::var(consts::CONFIG).ok())
+        .as_ref()
+        .map(|x| x.as_str())
+}
+
+pub fn proxy() -> Option<&'static str> {
+    static PROXY: OnceLock<Option<String>> = OnceLock::new();
+    PROXY
+        .get_or_init(|| std::env::var(consts::PROXY).ok())
+        .as_ref()
+        .map(|x| x.as_str())
+}
+
 pub fn attach_console() -> bool {
     static ATTACH_CONSOLE: OnceLock<bool> = OnceLock::new();
     *ATTACH_CONSOLE.get_or_init(|| std::env::var(consts::ATTACH_CONSOLE).is_ok())
@@ -35,10 +52,33 @@ pub fn user_token() -> Option<&'static str> {
         .map(|x| x.as_str())
 }
 
+pub fn ota_rollback_threshold() -> Option<u32> {
+    static OTA_ROLLBACK_THRESHOLD: OnceLock<Option<u32>> = OnceLock::new();
+    *OTA_ROLLBACK_THRESHOLD.get_or_init(|| {
+        std::env::var(consts::OTA_ROLLBACK_THRESHOLD)
+            .ok()
+            .and_then(|x| x.parse().ok())
+    })
+}
+
+/// 资源内存缓存上限（MB）
+pub fn asset_memory_cache_mb() -> Option<usize> {
+    static ASSET_MEMORY_CACHE_MB: OnceLock<Option<usize>> = OnceLock::new();
+    *ASSET_MEMORY_CACHE_MB.get_or_init(|| {
+        std::env::var(consts::ASSET_MEMORY_CACHE_MB)
+            .ok()
+            .and_then(|x| x.parse().ok())
+    })
+}
+
 pub mod consts {
     pub const DATA_DIR: &str = "ARKHOST_APP_DATA_DIR";
+    pub const CONFIG: &str = "ARKHOST_APP_CONFIG";
+    pub const PROXY: &str = "ARKHOST_APP_PROXY";
     pub const ATTACH_CONSOLE: &str = "ARKHOST_APP_ATTACH_CONSOLE";
     pub const FORCE_UPDATE: &str = "ARKHOST_APP_FORCE_UPDATE";
     pub const OVERRIDE_ASSET_SERVER: &str = "ARKHOST_APP_OVERRIDE_ASSET_SERVER";
     pub const USER_TOKEN: &str = "ARKHOST_APP_USER_TOKEN";
+    pub const OTA_ROLLBACK_THRESHOLD: &str = "ARKHOST_APP_OTA_ROLLBACK_THRESHOLD";
+    pub const ASSET_MEMORY_CACHE_MB: &str = "ARKHOST_APP_ASSET_MEMORY_CACHE_MB";
 }
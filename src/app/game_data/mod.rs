@@ -1,3 +1,6 @@
+pub mod stage_availability;
+pub mod stage_search;
+
 use std::{
     cmp,
     collections::{BTreeMap, HashMap},
@@ -88,6 +91,8 @@ pub struct Stage {
     pub stage_type: StageType,
     pub stage_drop_info: StageDropInfo,
     pub diff_group: StageDiffGroup,
+    #[serde(default)]
+    pub zone_id: String,
     pub code: String,
     pub ap_cost: i32,
     pub is_predefined: bool,
@@ -163,6 +168,59 @@ impl Ord for Stage {
     }
 }
 
+#[derive(Default, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemTable {
+    pub items: HashMap<String, Item>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    pub name: String,
+    pub icon_id: String,
+}
+
+#[derive(Default, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneTable {
+    #[serde(default)]
+    pub zone_valid_info: HashMap<String, ZoneValidInfo>,
+}
+
+/// 开放时间，单位为秒的时间戳，非正数表示不限
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneValidInfo {
+    pub start_ts: i64,
+    pub end_ts: i64,
+}
+
+#[derive(Default, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityTable {
+    #[serde(default)]
+    pub basic_info: HashMap<String, ActivityBasicInfo>,
+    #[serde(default)]
+    pub zone_to_activity: HashMap<String, String>,
+}
+
+/// 开放时间，单位为秒的时间戳
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityBasicInfo {
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+/// 复刻为常驻的支线故事（故事集等），`zone_to_retro` 中的区域常驻开放
+#[derive(Default, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetroTable {
+    #[serde(default)]
+    pub zone_to_retro: HashMap<String, String>,
+}
+
 pub type CharPackSummaryTable = HashMap<String, CharPack>;
 
 #[derive(Default, Deserialize, Debug, Clone)]
@@ -0,0 +1,274 @@
+use super::{
+    stage_search, ActivityTable, RetroTable, Stage, StageDropType, StageTable, StageType, ZoneTable,
+};
+use crate::app::utils::time::approximate_period_str;
+use chrono::{DateTime, Utc};
+
+/// 关卡的开放状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageAvailability {
+    /// 常驻，或未找到开放时间
+    Permanent,
+    /// 限时开放中，将于 `end` 关闭
+    Open {
+        end: DateTime<Utc>,
+    },
+    NotYetOpen {
+        start: DateTime<Utc>,
+    },
+    Closed,
+}
+
+impl StageAvailability {
+    /// 已关闭、未开放或即将关闭
+    pub fn needs_attention(&self, now: DateTime<Utc>) -> bool {
+        match self {
+            Self::Permanent => false,
+            Self::Open { end } => *end - now < consts::closing_soon(),
+            Self::NotYetOpen { .. } | Self::Closed => true,
+        }
+    }
+
+    pub fn description(&self, now: DateTime<Utc>) -> String {
+        match self {
+            Self::Permanent => String::new(),
+            Self::Open { end } => format!("剩余 {}", approximate_period_str(*end - now)),
+            Self::NotYetOpen { start } => {
+                format!("{}后开放", approximate_period_str(*start - now))
+            }
+            Self::Closed => "已关闭".into(),
+        }
+    }
+}
+
+/// 查询关卡开放时间所需的数据，未加载的表视为无限制
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpeningTimes<'a> {
+    pub zone_table: Option<&'a ZoneTable>,
+    pub activity_table: Option<&'a ActivityTable>,
+    pub retro_table: Option<&'a RetroTable>,
+}
+
+impl<'a> OpeningTimes<'a> {
+    ///
+    /// 优先使用关卡所属活动的开放时间，其次为关卡所属区域的开放时间；
+    /// 已复刻为常驻的活动区域不受活动结束时间限制
+    ///
+    pub fn availability(&self, stage: &Stage, now: DateTime<Utc>) -> StageAvailability {
+        if self
+            .retro_table
+            .is_some_and(|table| table.zone_to_retro.contains_key(&stage.zone_id))
+        {
+            return StageAvailability::Permanent;
+        }
+        let window = self
+            .activity_table
+            .and_then(|table| {
+                let activity = table
+                    .basic_info
+                    .get(table.zone_to_activity.get(&stage.zone_id)?)?;
+                Some((activity.start_time, activity.end_time))
+            })
+            .or_else(|| {
+                self.zone_table
+                    .and_then(|table| table.zone_valid_info.get(&stage.zone_id))
+                    .map(|x| (x.start_ts, x.end_ts))
+            });
+        let Some((start, end)) = window else {
+            return StageAvailability::Permanent;
+        };
+
+        match (timestamp(start), timestamp(end)) {
+            (Some(start), _) if now < start => StageAvailability::NotYetOpen { start },
+            (_, Some(end)) if now >= end => StageAvailability::Closed,
+            (_, Some(end)) => StageAvailability::Open { end },
+            _ => StageAvailability::Permanent,
+        }
+    }
+
+    ///
+    /// 替代关卡：常驻且常规掉落中含 `stage` 主要掉落物品的关卡，依次按该物品在掉落中的位置、
+    /// 理智消耗及关卡代号选择；找不到时为 [`consts::DEFAULT_FALLBACK_MAP_ID`]
+    ///
+    pub fn fallback<'t>(
+        &self,
+        stage_table: &'t StageTable,
+        stage: &Stage,
+        now: DateTime<Utc>,
+    ) -> Option<(&'t str, &'t Stage)> {
+        let best = primary_drop(stage).and_then(|item_id| {
+            stage_table
+                .stages
+                .iter()
+                .filter(|(_, x)| {
+                    x.stage_type != StageType::Activity
+                        && stage_search::is_searchable(x)
+                        && self.availability(x, now) == StageAvailability::Permanent
+                })
+                .filter_map(|(map_id, x)| {
+                    let position = x
+                        .stage_drop_info
+                        .display_rewards
+                        .iter()
+                        .filter(|r| r.drop_type == StageDropType::Normal)
+                        .position(|r| r.id == item_id)?;
+                    Some((position, map_id, x))
+                })
+                .min_by(|(lhs_pos, lhs_map_id, lhs), (rhs_pos, rhs_map_id, rhs)| {
+                    lhs_pos
+                        .cmp(rhs_pos)
+                        .then_with(|| lhs.ap_cost.cmp(&rhs.ap_cost))
+                        .then_with(|| lhs.cmp(rhs))
+                        .then_with(|| lhs_map_id.cmp(rhs_map_id))
+                })
+                .map(|(_, map_id, x)| (map_id.as_str(), x))
+        });
+        best.or_else(|| {
+            stage_table
+                .stages
+                .get_key_value(consts::DEFAULT_FALLBACK_MAP_ID)
+                .map(|(map_id, x)| (map_id.as_str(), x))
+        })
+    }
+}
+
+/// 首个常规掉落物品，没有时为首个特殊掉落物品
+fn primary_drop(stage: &Stage) -> Option<&str> {
+    let rewards = &stage.stage_drop_info.display_rewards;
+    rewards
+        .iter()
+        .find(|x| x.drop_type == StageDropType::Normal)
+        .or_else(|| {
+            rewards
+                .iter()
+                .find(|x| x.drop_type == StageDropType::Special)
+        })
+        .map(|x| x.id.as_str())
+}
+
+/// 非正数或过远的时间表示不限
+fn timestamp(ts: i64) -> Option<DateTime<Utc>> {
+    if ts <= 0 || ts >= consts::UNLIMITED_TS {
+        None
+    } else {
+        DateTime::from_timestamp(ts, 0)
+    }
+}
+
+pub mod consts {
+    pub const DEFAULT_FALLBACK_MAP_ID: &str = "main_01-07";
+    /// 2096 年后的时间视为不限
+    pub const UNLIMITED_TS: i64 = 4_000_000_000;
+
+    /// 剩余开放时间少于该值时视为即将关闭
+    pub fn closing_soon() -> chrono::Duration {
+        chrono::Duration::days(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stage(code: &str, stage_type: &str, zone_id: &str, rewards: &[&str]) -> serde_json::Value {
+        let rewards: Vec<serde_json::Value> = rewards
+            .iter()
+            .map(|id| json!({ "id": id, "dropType": 2 }))
+            .collect();
+        json!({
+            "name": code,
+            "stageType": stage_type,
+            "stageDropInfo": { "displayRewards": rewards },
+            "diffGroup": "NONE",
+            "zoneId": zone_id,
+            "code": code,
+            "apCost": 6,
+            "isPredefined": false,
+            "isHardPredefined": false,
+            "isSkillSelectablePredefined": false,
+            "isStoryOnly": false,
+            "canBattleReplay": true,
+        })
+    }
+
+    #[test]
+    fn test_stage_availability() {
+        let stage_table: StageTable = serde_json::from_value(json!({
+            "stages": {
+                "main_01-07": stage("1-7", "MAIN", "main_1", &["30012"]),
+                "main_04-04": stage("4-4", "MAIN", "main_4", &["30062", "30012"]),
+                "main_02-05": stage("2-5", "MAIN", "main_2", &["30022", "30012"]),
+                "act_01": stage("AC-1", "ACTIVITY", "act_zone1", &["30012"]),
+                "act_02": stage("AC-2", "ACTIVITY", "act_zone2", &["30062"]),
+                "act_03": stage("AC-3", "ACTIVITY", "act_zone3", &["30021"]),
+                "act_04": stage("AC-4", "ACTIVITY", "act_zone4", &["30012"]),
+            }
+        }))
+        .unwrap();
+        let zone_table: ZoneTable = serde_json::from_value(json!({
+            "zoneValidInfo": {
+                "main_1": { "startTs": 1556676000, "endTs": -1 },
+                "main_4": { "startTs": 1556676000, "endTs": 4102415999i64 },
+                "act_zone3": { "startTs": 1700100000, "endTs": 1700200000 },
+            }
+        }))
+        .unwrap();
+        let activity_table: ActivityTable = serde_json::from_value(json!({
+            "basicInfo": {
+                "act1": { "startTime": 1690000000, "endTime": 1700001000 },
+                "act2": { "startTime": 1690000000, "endTime": 1701000000 },
+                "act4": { "startTime": 1600000000, "endTime": 1600001000 },
+            },
+            "zoneToActivity": { "act_zone1": "act1", "act_zone2": "act2", "act_zone4": "act4" },
+        }))
+        .unwrap();
+        let retro_table: RetroTable = serde_json::from_value(json!({
+            "zoneToRetro": { "act_zone4": "act4_retro" },
+        }))
+        .unwrap();
+        let times = OpeningTimes {
+            zone_table: Some(&zone_table),
+            activity_table: Some(&activity_table),
+            retro_table: Some(&retro_table),
+        };
+        let now = DateTime::from_timestamp(1700000000, 0).unwrap();
+        let availability = |map_id: &str| times.availability(&stage_table.stages[map_id], now);
+
+        assert_eq!(availability("main_01-07"), StageAvailability::Permanent);
+        assert_eq!(availability("main_04-04"), StageAvailability::Permanent);
+        assert_eq!(availability("main_02-05"), StageAvailability::Permanent);
+        assert_eq!(
+            availability("act_01"),
+            StageAvailability::Open {
+                end: DateTime::from_timestamp(1700001000, 0).unwrap()
+            }
+        );
+        assert!(availability("act_01").needs_attention(now));
+        assert!(!availability("act_02").needs_attention(now));
+        assert!(availability("act_03").needs_attention(now));
+        // 活动已结束但已复刻为常驻
+        assert_eq!(availability("act_04"), StageAvailability::Permanent);
+        assert_eq!(
+            times.availability(
+                &stage_table.stages["act_01"],
+                now + chrono::Duration::hours(1)
+            ),
+            StageAvailability::Closed
+        );
+        assert_eq!(
+            StageAvailability::Closed.description(now),
+            "已关闭".to_owned()
+        );
+
+        // 主要掉落为首个常规掉落的关卡优先
+        let fallback = |map_id: &str| {
+            times
+                .fallback(&stage_table, &stage_table.stages[map_id], now)
+                .map(|(map_id, _)| map_id)
+        };
+        assert_eq!(fallback("act_01"), Some("main_01-07"));
+        assert_eq!(fallback("act_02"), Some("main_04-04"));
+        assert_eq!(fallback("act_03"), Some("main_01-07"));
+    }
+}
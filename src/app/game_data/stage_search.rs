@@ -0,0 +1,344 @@
+use super::{ItemTable, Stage, StageDropType, StageTable, StageType};
+use crate::app::utils::{
+    levenshtein_distance::{ResultEntry, Trie},
+    pinyin,
+};
+use log::debug;
+
+/// 匹配方式，按优先级从高到低排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MatchKind {
+    /// 关卡代号完全一致，如 `1-7`
+    CodeExact,
+    /// 关卡名称完全一致
+    NameExact,
+    /// 掉落物品名称完全一致，如 `固源岩`
+    ItemExact,
+    CodePrefix,
+    NamePrefix,
+    ItemPrefix,
+    /// 关卡名称的拼音首字母
+    NameInitialsPrefix,
+    /// 掉落物品名称的拼音首字母，如 `gyy`
+    ItemInitialsPrefix,
+    NameContains,
+    ItemContains,
+    CodeContains,
+    /// 关卡代号或名称的编辑距离在允许范围内
+    Fuzzy,
+}
+
+///
+/// 搜索结果的排序依据，按字段顺序依次比较：
+///
+/// 1. 匹配方式（[`MatchKind`]）
+/// 2. 编辑距离，仅模糊匹配时非零
+/// 3. 匹配到的掉落物品的掉落类型（常规、特殊、额外）及其在掉落列表中的位置
+/// 4. 关卡代号顺序
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct MatchRank {
+    kind: MatchKind,
+    distance: i32,
+    drop: (u8, usize),
+    order: usize,
+}
+
+#[derive(Debug)]
+struct DropEntry {
+    name: String,
+    initials: String,
+    priority: u8,
+    position: usize,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    map_id: String,
+    code: String,
+    name: String,
+    name_initials: String,
+    drops: Vec<DropEntry>,
+}
+
+impl IndexEntry {
+    fn rank(&self, term: &str, order: usize) -> Option<MatchRank> {
+        let mut best: Option<(MatchKind, (u8, usize))> = None;
+        let mut consider = |kind: MatchKind, drop: (u8, usize)| {
+            if best.is_none_or(|x| (kind, drop) < x) {
+                best = Some((kind, drop));
+            }
+        };
+        let match_text = |text: &str, exact, prefix, contains| {
+            if text.is_empty() {
+                None
+            } else if text == term {
+                Some(exact)
+            } else if text.starts_with(term) {
+                Some(prefix)
+            } else if text.contains(term) {
+                Some(contains)
+            } else {
+                None
+            }
+        };
+        let initials_term = term.chars().all(|x| x.is_ascii_alphanumeric());
+
+        if let Some(kind) = match_text(
+            &self.code,
+            MatchKind::CodeExact,
+            MatchKind::CodePrefix,
+            MatchKind::CodeContains,
+        ) {
+            consider(kind, (0, 0));
+        }
+        if let Some(kind) = match_text(
+            &self.name,
+            MatchKind::NameExact,
+            MatchKind::NamePrefix,
+            MatchKind::NameContains,
+        ) {
+            consider(kind, (0, 0));
+        }
+        if initials_term && self.name_initials.starts_with(term) {
+            consider(MatchKind::NameInitialsPrefix, (0, 0));
+        }
+        for drop in &self.drops {
+            let drop_rank = (drop.priority, drop.position);
+            if let Some(kind) = match_text(
+                &drop.name,
+                MatchKind::ItemExact,
+                MatchKind::ItemPrefix,
+                MatchKind::ItemContains,
+            ) {
+                consider(kind, drop_rank);
+            }
+            if initials_term && drop.initials.starts_with(term) {
+                consider(MatchKind::ItemInitialsPrefix, drop_rank);
+            }
+        }
+
+        best.map(|(kind, drop)| MatchRank {
+            kind,
+            distance: 0,
+            drop,
+            order,
+        })
+    }
+}
+
+///
+/// 可代理作战的关卡的搜索索引
+///
+/// 可按关卡代号、名称、名称的拼音首字母及掉落物品名称（及其拼音首字母）搜索，
+/// 均不区分大小写；另以关卡代号及名称的编辑距离进行模糊匹配
+///
+#[derive(Debug, Default)]
+pub struct StageSearchIndex {
+    /// 按关卡代号排序
+    entries: Vec<IndexEntry>,
+    fuzzy_tree: Trie<usize>,
+}
+
+impl StageSearchIndex {
+    /// `item_table` 未加载时无法按掉落物品搜索
+    pub fn new(stage_table: &StageTable, item_table: Option<&ItemTable>) -> Self {
+        let mut stages: Vec<(&String, &Stage)> = stage_table
+            .stages
+            .iter()
+            .filter(|(_, stage)| is_searchable(stage))
+            .collect();
+        stages.sort_by(|(lhs_map_id, lhs_stage), (rhs_map_id, rhs_stage)| {
+            lhs_stage
+                .cmp(rhs_stage)
+                .then_with(|| lhs_map_id.cmp(rhs_map_id))
+        });
+
+        let mut fuzzy_tree = Trie::default();
+        let entries: Vec<IndexEntry> = stages
+            .into_iter()
+            .enumerate()
+            .map(|(order, (map_id, stage))| {
+                let code = stage.code.to_lowercase();
+                let name = stage.name.as_deref().unwrap_or_default().to_lowercase();
+                for text in [&code, &name] {
+                    if !text.is_empty() {
+                        let chars: Vec<char> = text.chars().collect();
+                        fuzzy_tree.insert(&chars, order);
+                    }
+                }
+                let drops = stage
+                    .stage_drop_info
+                    .display_rewards
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(position, reward)| {
+                        let priority = drop_priority(&reward.drop_type)?;
+                        let item = item_table?.items.get(&reward.id)?;
+                        Some(DropEntry {
+                            name: item.name.to_lowercase(),
+                            initials: pinyin::initials(&item.name),
+                            priority,
+                            position,
+                        })
+                    })
+                    .collect();
+                IndexEntry {
+                    map_id: map_id.clone(),
+                    name_initials: pinyin::initials(&name),
+                    code,
+                    name,
+                    drops,
+                }
+            })
+            .collect();
+
+        debug!("indexed {} stages for map search", entries.len());
+        Self {
+            entries,
+            fuzzy_tree,
+        }
+    }
+
+    /// 按 [`MatchRank`] 排序返回至多 `limit` 个关卡 ID
+    pub fn search(&self, term: &str, max_distance: i32, limit: usize) -> Vec<&str> {
+        let term = term.trim().to_lowercase();
+        if term.is_empty() {
+            return vec![];
+        }
+
+        let mut ranks: Vec<Option<MatchRank>> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(order, entry)| entry.rank(&term, order))
+            .collect();
+        let chars: Vec<char> = term.chars().collect();
+        for ResultEntry(distance, order) in self.fuzzy_tree.search(max_distance, &chars) {
+            let rank = MatchRank {
+                kind: MatchKind::Fuzzy,
+                distance,
+                drop: (0, 0),
+                order: *order,
+            };
+            let existing = &mut ranks[*order];
+            if existing.is_none_or(|x| rank < x) {
+                *existing = Some(rank);
+            }
+        }
+
+        let mut ranks: Vec<MatchRank> = ranks.into_iter().flatten().collect();
+        ranks.sort_unstable();
+        ranks
+            .into_iter()
+            .take(limit)
+            .map(|x| self.entries[x.order].map_id.as_str())
+            .collect()
+    }
+}
+
+/// 可代理作战且有常规或特殊掉落的关卡
+pub fn is_searchable(stage: &Stage) -> bool {
+    stage.can_battle_replay
+        && !matches!(
+            stage.stage_type,
+            StageType::Training
+                | StageType::Guide
+                | StageType::SpecialStory
+                | StageType::Campaign
+                | StageType::HandbookBattle
+                | StageType::ClimbTower
+        )
+        && stage
+            .stage_drop_info
+            .display_rewards
+            .iter()
+            .any(|x| matches!(x.drop_type, StageDropType::Normal | StageDropType::Special))
+        && stage.ap_cost > 0
+        && !stage.is_predefined
+        && !stage.is_hard_predefined
+        && !stage.is_skill_selectable_predefined
+        && !stage.is_story_only
+}
+
+/// 可用于搜索的掉落类型，数值越小越优先
+fn drop_priority(drop_type: &StageDropType) -> Option<u8> {
+    match drop_type {
+        StageDropType::Normal => Some(0),
+        StageDropType::Special => Some(1),
+        StageDropType::Additional => Some(2),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn stage(code: &str, name: &str, rewards: &[(&str, i32)]) -> serde_json::Value {
+        let rewards: Vec<serde_json::Value> = rewards
+            .iter()
+            .map(|(id, drop_type)| json!({ "id": id, "dropType": drop_type }))
+            .collect();
+        json!({
+            "name": name,
+            "stageType": "MAIN",
+            "stageDropInfo": { "displayRewards": rewards },
+            "diffGroup": "NONE",
+            "code": code,
+            "apCost": 18,
+            "isPredefined": false,
+            "isHardPredefined": false,
+            "isSkillSelectablePredefined": false,
+            "isStoryOnly": false,
+            "canBattleReplay": true,
+        })
+    }
+
+    fn index() -> StageSearchIndex {
+        let stage_table: StageTable = serde_json::from_value(json!({
+            "stages": {
+                "main_01-07": stage("1-7", "暴君", &[("30012", 2)]),
+                "main_02-05": stage("2-5", "代号：暴君", &[("30012", 3), ("30022", 2)]),
+                "sub_02-03": stage("S2-3", "开拓", &[("30022", 2), ("30012", 4)]),
+                "main_01-01": stage("1-1", "坍塌", &[("30061", 2)]),
+                "main_00-01": stage("0-1", "坠落", &[("30061", 1)]),
+            }
+        }))
+        .unwrap();
+        let item_table: ItemTable = serde_json::from_value(json!({
+            "items": {
+                "30012": { "name": "固源岩", "iconId": "MTL_SL_G2" },
+                "30022": { "name": "固源岩组", "iconId": "MTL_SL_G3" },
+                "30061": { "name": "破损装置", "iconId": "MTL_SL_BOSS1" },
+            }
+        }))
+        .unwrap();
+        StageSearchIndex::new(&stage_table, Some(&item_table))
+    }
+
+    #[test]
+    fn test_stage_search() {
+        let index = index();
+        // 仅有首次掉落的关卡不在索引中
+        assert_eq!(index.entries.len(), 4);
+        assert_eq!(index.search("1-7", 1, 50)[0], "main_01-07");
+        assert_eq!(index.search("s2", 1, 50), ["sub_02-03"]);
+        assert_eq!(index.search("暴君", 1, 50), ["main_01-07", "main_02-05"]);
+        // 完全一致的物品优先，其次按掉落类型
+        assert_eq!(
+            index.search("固源岩", 0, 50),
+            ["main_01-07", "main_02-05", "sub_02-03"]
+        );
+        assert_eq!(
+            index.search("gyy", 0, 50),
+            ["main_01-07", "sub_02-03", "main_02-05"]
+        );
+        assert_eq!(index.search("tt", 0, 50), ["main_01-01"]);
+        assert_eq!(index.search("坍塔", 1, 50), ["main_01-01"]);
+        assert!(index.search("坍塔", 0, 50).is_empty());
+        assert!(index.search("  ", 1, 50).is_empty());
+        assert_eq!(index.search("固源岩", 0, 1).len(), 1);
+    }
+}
@@ -0,0 +1,416 @@
+use super::utils::db;
+use arkhost_api::models::api_arkhost::{LogEntry, LogLevel};
+use chrono::{DateTime, Utc};
+use heed::types::{Str, Unit};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, ops::Bound};
+
+///
+/// 本地日志存档，按账号保存已获取的游戏日志
+///
+/// - 主库：`{account}\0{id}` -> 日志
+/// - 时间索引：`{account}\0{ts}\0{id}`
+/// - 等级索引：`{account}\0{bit}\0{id}`，日志等级中的每一位各一条
+/// - 全文索引：`{account}\0{token}\0{id}`，分词见 [`tokenize`]
+///
+/// 数字均以定长十进制编码，保证字典序与数值顺序一致
+///
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct ArchivedLog {
+    id: u64,
+    ts: i64,
+    log_level: u32,
+    content: String,
+}
+
+impl From<&LogEntry> for ArchivedLog {
+    fn from(value: &LogEntry) -> Self {
+        Self {
+            id: value.id,
+            ts: value.ts.timestamp(),
+            log_level: value.log_level.bits(),
+            content: value.content.clone(),
+        }
+    }
+}
+
+impl From<ArchivedLog> for LogEntry {
+    fn from(value: ArchivedLog) -> Self {
+        Self {
+            id: value.id,
+            ts: DateTime::from_timestamp(value.ts, 0).unwrap_or_default(),
+            log_level: LogLevel::from_bits_retain(value.log_level),
+            content: value.content,
+        }
+    }
+}
+
+/// 日志查询条件，各条件同时满足
+#[derive(Debug, Clone, Default)]
+pub struct LogQuery {
+    /// 日志内容包含的文本（不区分大小写）
+    pub text: Option<String>,
+    /// 日志等级与之有交集
+    pub levels: Option<LogLevel>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// 最多返回的数量，按 id 从新到旧
+    pub limit: Option<usize>,
+}
+
+impl LogQuery {
+    fn matches(&self, log: &ArchivedLog, text_lowercase: Option<&str>) -> bool {
+        if matches!(text_lowercase, Some(text) if !log.content.to_lowercase().contains(text)) {
+            return false;
+        }
+        if matches!(self.levels, Some(levels) if levels.bits() & log.log_level == 0) {
+            return false;
+        }
+        if matches!(self.since, Some(since) if log.ts < since.timestamp()) {
+            return false;
+        }
+        !matches!(self.until, Some(until) if log.ts > until.timestamp())
+    }
+}
+
+/// 存档保留策略
+#[derive(Debug, Clone)]
+pub struct Retention {
+    pub max_entries: usize,
+    pub max_age: chrono::Duration,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            max_entries: consts::MAX_ENTRIES_PER_ACCOUNT,
+            max_age: chrono::Duration::days(consts::MAX_AGE_DAYS),
+        }
+    }
+}
+
+struct Databases {
+    logs: heed::Database<Str, heed::types::SerdeBincode<ArchivedLog>>,
+    ts_index: heed::Database<Str, Unit>,
+    level_index: heed::Database<Str, Unit>,
+    text_index: heed::Database<Str, Unit>,
+}
+
+impl Databases {
+    fn open() -> heed::Result<Self> {
+        Ok(Self {
+            logs: db::database(Some(db::consts::db::GAME_LOGS))?,
+            ts_index: db::database(Some(db::consts::db::GAME_LOGS_TS_INDEX))?,
+            level_index: db::database(Some(db::consts::db::GAME_LOGS_LEVEL_INDEX))?,
+            text_index: db::database(Some(db::consts::db::GAME_LOGS_TEXT_INDEX))?,
+        })
+    }
+
+    fn put(&self, wtxn: &mut heed::RwTxn, account: &str, log: &ArchivedLog) -> heed::Result<()> {
+        self.logs.put(wtxn, &log_key(account, log.id), log)?;
+        for key in index_keys(account, log) {
+            match key {
+                IndexKey::Ts(key) => self.ts_index.put(wtxn, &key, &())?,
+                IndexKey::Level(key) => self.level_index.put(wtxn, &key, &())?,
+                IndexKey::Text(key) => self.text_index.put(wtxn, &key, &())?,
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&self, wtxn: &mut heed::RwTxn, account: &str, log: &ArchivedLog) -> heed::Result<()> {
+        self.logs.delete(wtxn, &log_key(account, log.id))?;
+        for key in index_keys(account, log) {
+            match key {
+                IndexKey::Ts(key) => self.ts_index.delete(wtxn, &key)?,
+                IndexKey::Level(key) => self.level_index.delete(wtxn, &key)?,
+                IndexKey::Text(key) => self.text_index.delete(wtxn, &key)?,
+            };
+        }
+        Ok(())
+    }
+
+    /// 索引键中 `prefix` 之后的 id
+    fn index_ids(
+        &self,
+        rtxn: &heed::RoTxn,
+        index: &heed::Database<Str, Unit>,
+        prefix: &str,
+    ) -> heed::Result<BTreeSet<u64>> {
+        let mut ids = BTreeSet::new();
+        for entry in index.prefix_iter(rtxn, prefix)? {
+            let (key, _) = entry?;
+            if let Some(id) = parse_id_suffix(key) {
+                ids.insert(id);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// 根据查询条件选出候选 id，之后仍需逐条检查
+    fn candidate_ids(
+        &self,
+        rtxn: &heed::RoTxn,
+        account: &str,
+        query: &LogQuery,
+        tokens: &[String],
+    ) -> heed::Result<BTreeSet<u64>> {
+        if !tokens.is_empty() {
+            let mut result: Option<BTreeSet<u64>> = None;
+            for token in tokens {
+                // ASCII 词按前缀匹配
+                let ids = self.index_ids(rtxn, &self.text_index, &format!("{account}\0{token}"))?;
+                result = Some(match result {
+                    Some(result) => result.intersection(&ids).copied().collect(),
+                    None => ids,
+                });
+            }
+            return Ok(result.unwrap_or_default());
+        }
+
+        if let Some(levels) = query.levels.filter(|x| x.bits() & 0xff != 0xff) {
+            let mut ids = BTreeSet::new();
+            for bit in (0..8).filter(|bit| levels.bits() & (1 << bit) != 0) {
+                ids.extend(self.index_ids(
+                    rtxn,
+                    &self.level_index,
+                    &format!("{account}\0{bit}\0"),
+                )?);
+            }
+            return Ok(ids);
+        }
+
+        if query.since.is_some() || query.until.is_some() {
+            let start = format!(
+                "{account}\0{:020}\0",
+                query.since.map_or(0, |x| x.timestamp().max(0))
+            );
+            let end = format!(
+                "{account}\0{:020}\0",
+                query
+                    .until
+                    .map_or(i64::MAX, |x| x.timestamp().max(0).saturating_add(1))
+            );
+            let mut ids = BTreeSet::new();
+            for entry in self.ts_index.range(
+                rtxn,
+                &(
+                    Bound::Included(start.as_str()),
+                    Bound::Excluded(end.as_str()),
+                ),
+            )? {
+                let (key, _) = entry?;
+                if let Some(id) = parse_id_suffix(key) {
+                    ids.insert(id);
+                }
+            }
+            return Ok(ids);
+        }
+
+        let mut ids = BTreeSet::new();
+        for entry in self
+            .logs
+            .remap_data_type::<heed::types::DecodeIgnore>()
+            .prefix_iter(rtxn, &format!("{account}\0"))?
+        {
+            let (key, _) = entry?;
+            if let Some(id) = parse_id_suffix(key) {
+                ids.insert(id);
+            }
+        }
+        Ok(ids)
+    }
+}
+
+enum IndexKey {
+    Ts(String),
+    Level(String),
+    Text(String),
+}
+
+fn log_key(account: &str, id: u64) -> String {
+    format!("{account}\0{id:020}")
+}
+
+fn index_keys(account: &str, log: &ArchivedLog) -> Vec<IndexKey> {
+    let id = log.id;
+    let mut keys = vec![IndexKey::Ts(format!(
+        "{account}\0{:020}\0{id:020}",
+        log.ts.max(0)
+    ))];
+    for bit in (0..8).filter(|bit| log.log_level & (1 << bit) != 0) {
+        keys.push(IndexKey::Level(format!("{account}\0{bit}\0{id:020}")));
+    }
+    let tokens: BTreeSet<String> = tokenize(&log.content).into_iter().collect();
+    for token in tokens {
+        keys.push(IndexKey::Text(format!("{account}\0{token}\0{id:020}")));
+    }
+    keys
+}
+
+fn parse_id_suffix(key: &str) -> Option<u64> {
+    key.rsplit_once('\0').and_then(|(_, id)| id.parse().ok())
+}
+
+///
+/// 全文索引分词：按非字母数字字符及 ASCII/非 ASCII 的边界切分，
+/// ASCII 部分取小写单词，非 ASCII 部分（中文等）取相邻两字；单个非 ASCII 字符不建立索引
+///
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut run = String::new();
+    let mut run_ascii = true;
+    let flush = |run: &mut String, ascii: bool, tokens: &mut Vec<String>| {
+        if ascii {
+            if !run.is_empty() {
+                tokens.push(run.to_lowercase());
+            }
+        } else {
+            let chars: Vec<char> = run.chars().collect();
+            tokens.extend(chars.windows(2).map(|x| x.iter().collect::<String>()));
+        }
+        run.clear();
+    };
+    for c in text.chars() {
+        if !c.is_alphanumeric() {
+            flush(&mut run, run_ascii, &mut tokens);
+            continue;
+        }
+        if !run.is_empty() && c.is_ascii() != run_ascii {
+            flush(&mut run, run_ascii, &mut tokens);
+        }
+        run_ascii = c.is_ascii();
+        run.push(c);
+    }
+    flush(&mut run, run_ascii, &mut tokens);
+    tokens
+}
+
+/// 保存日志，已存在的日志会被覆盖；返回保存的数量
+pub fn archive(account: &str, logs: &[LogEntry]) -> heed::Result<usize> {
+    if logs.is_empty() {
+        return Ok(0);
+    }
+    let dbs = Databases::open()?;
+    let env = db::env();
+    let mut wtxn = env.write_txn()?;
+    for log in logs {
+        dbs.put(&mut wtxn, account, &ArchivedLog::from(log))?;
+    }
+    wtxn.commit()?;
+    Ok(logs.len())
+}
+
+pub fn query(account: &str, query: &LogQuery) -> heed::Result<Vec<LogEntry>> {
+    let dbs = Databases::open()?;
+    let env = db::env();
+    let rtxn = env.read_txn()?;
+    let text_lowercase = query
+        .text
+        .as_ref()
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_lowercase());
+    let tokens = text_lowercase.as_deref().map(tokenize).unwrap_or_default();
+
+    let mut result = Vec::new();
+    for id in dbs
+        .candidate_ids(&rtxn, account, query, &tokens)?
+        .into_iter()
+        .rev()
+    {
+        if matches!(query.limit, Some(limit) if result.len() >= limit) {
+            break;
+        }
+        if let Some(log) = dbs.logs.get(&rtxn, &log_key(account, id))? {
+            if query.matches(&log, text_lowercase.as_deref()) {
+                result.push(log.into());
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// 按保留策略删除账号的旧日志，返回删除的数量
+pub fn prune(account: &str, retention: &Retention) -> heed::Result<usize> {
+    let dbs = Databases::open()?;
+    let env = db::env();
+    let mut wtxn = env.write_txn()?;
+    let min_ts = (Utc::now() - retention.max_age).timestamp();
+    let mut ids = Vec::new();
+    for entry in dbs.ts_index.prefix_iter(&wtxn, &format!("{account}\0"))? {
+        let (key, _) = entry?;
+        let mut parts = key.rsplitn(3, '\0');
+        if let (Some(id), Some(ts)) = (parts.next(), parts.next()) {
+            if let (Ok(id), Ok(ts)) = (id.parse::<u64>(), ts.parse::<i64>()) {
+                ids.push((ts, id));
+            }
+        }
+    }
+
+    // 时间索引按时间升序，超出数量上限的部分及过期的日志均被删除
+    let excess = ids.len().saturating_sub(retention.max_entries);
+    let expired: Vec<u64> = ids
+        .iter()
+        .enumerate()
+        .filter(|(i, (ts, _))| *i < excess || *ts < min_ts)
+        .map(|(_, (_, id))| *id)
+        .collect();
+    for id in &expired {
+        if let Some(log) = dbs.logs.get(&wtxn, &log_key(account, *id))? {
+            dbs.delete(&mut wtxn, account, &log)?;
+        }
+    }
+    wtxn.commit()?;
+    if !expired.is_empty() {
+        debug!(
+            "prune: removed {} archived logs of {account}",
+            expired.len()
+        );
+    }
+    Ok(expired.len())
+}
+
+pub mod consts {
+    pub const MAX_ENTRIES_PER_ACCOUNT: usize = 100_000;
+    pub const MAX_AGE_DAYS: i64 = 180;
+    pub const SEARCH_LIMIT: usize = 500;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("开始作战 1-7 Stage_A"),
+            vec!["开始", "始作", "作战", "1", "7", "stage", "a"]
+        );
+        assert_eq!(tokenize("理智Full!"), vec!["理智", "full"]);
+        assert!(tokenize("，赛 ").is_empty());
+    }
+
+    #[test]
+    fn test_log_query_matches() {
+        let log = ArchivedLog {
+            id: 1,
+            ts: 100,
+            log_level: LogLevel::WARNING.bits(),
+            content: "理智不足 Sanity".into(),
+        };
+        let query = LogQuery {
+            levels: Some(LogLevel::WARNING | LogLevel::ERROR),
+            since: DateTime::from_timestamp(50, 0),
+            ..Default::default()
+        };
+        assert!(query.matches(&log, Some("sanity")));
+        assert!(!query.matches(&log, Some("理智充足")));
+
+        let query = LogQuery {
+            levels: Some(LogLevel::NOTICE),
+            ..Default::default()
+        };
+        assert!(!query.matches(&log, None));
+    }
+}
@@ -0,0 +1,166 @@
+use super::{
+    data_bundle,
+    log_archive::{self, LogQuery},
+    utils::data_dir::data_dir_create_all,
+};
+use arkhost_api::models::api_arkhost::LogEntry;
+use chrono::Utc;
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogExportFormat {
+    /// 表头为 `id,time,level,content`
+    Csv,
+    /// 每行一个 JSON 对象
+    Ndjson,
+    /// 与日志界面相同的格式
+    Text,
+}
+
+impl LogExportFormat {
+    /// 按日志界面中的选项顺序
+    pub fn from_index(index: usize) -> Option<Self> {
+        [Self::Csv, Self::Ndjson, Self::Text].get(index).copied()
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Ndjson => "ndjson",
+            Self::Text => "txt",
+        }
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// 按给定顺序写出日志，时间均为本地时间
+pub fn write_logs<W: Write>(
+    format: LogExportFormat,
+    logs: &[LogEntry],
+    writer: &mut W,
+) -> io::Result<()> {
+    if format == LogExportFormat::Csv {
+        writeln!(writer, "id,time,level,content")?;
+    }
+    for log in logs {
+        let time = log.local_ts();
+        let level = log.log_level.attributes_tag();
+        match format {
+            LogExportFormat::Csv => writeln!(
+                writer,
+                "{},{},{level},{}",
+                log.id,
+                time.to_rfc3339(),
+                csv_field(&log.content)
+            )?,
+            LogExportFormat::Ndjson => {
+                let value = serde_json::json!({
+                    "id": log.id,
+                    "ts": log.ts.timestamp(),
+                    "time": time.to_rfc3339(),
+                    "level": level,
+                    "content": log.content,
+                });
+                writeln!(writer, "{value}")?;
+            }
+            LogExportFormat::Text => writeln!(
+                writer,
+                "{} [{level}] {}",
+                time.format("%Y-%m-%d %H:%M:%S"),
+                log.content
+            )?,
+        }
+    }
+    Ok(())
+}
+
+///
+/// 将账号在本地存档中符合 `query` 的日志按时间顺序导出到 `path`，
+/// 未指定时导出到数据目录下；返回实际导出的路径及日志数量
+///
+/// 仅导出已获取过的日志，需要更早的日志时应先通过 API 获取
+///
+pub fn export_to_file(
+    account: &str,
+    query: &LogQuery,
+    format: LogExportFormat,
+    path: Option<&Path>,
+) -> anyhow::Result<(PathBuf, usize)> {
+    let mut logs = log_archive::query(account, query)?;
+    logs.reverse();
+
+    let path = match path {
+        Some(path) => path.to_owned(),
+        None => {
+            let dir = data_dir_create_all().join(data_bundle::consts::EXPORT_DIR);
+            std::fs::create_dir_all(&dir)?;
+            let file_name = format!(
+                "logs-{account}-{}.{}",
+                Utc::now().format("%Y%m%d-%H%M%S"),
+                format.extension()
+            );
+            // 账号可能包含不适合作为文件名的字符
+            dir.join(file_name.replace(|c: char| !c.is_alphanumeric() && c != '.' && c != '-', "_"))
+        }
+    };
+    let mut writer = io::BufWriter::new(std::fs::File::create(&path)?);
+    write_logs(format, &logs, &mut writer)?;
+    writer.flush()?;
+    Ok((path, logs.len()))
+}
+
+pub mod consts {
+    /// 导出时间范围选项（天），`None` 为全部
+    pub const RANGE_OPTIONS_DAYS: [Option<i64>; 4] = [None, Some(1), Some(7), Some(30)];
+    /// 导出前最多向前获取的日志页数
+    pub const MAX_FORMER_PAGES: usize = 100;
+    /// 导出全部时向前获取的天数，更早的日志仅导出本地存档中已有的部分
+    pub const FETCH_DAYS_FOR_ALL: i64 = 30;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arkhost_api::models::api_arkhost::LogLevel;
+    use chrono::DateTime;
+
+    #[test]
+    fn test_write_logs() {
+        let logs = vec![LogEntry {
+            id: 42,
+            ts: DateTime::from_timestamp(0, 0).unwrap(),
+            log_level: LogLevel::NOTICE | LogLevel::WARNING,
+            content: "理智不足, \"1-7\"".into(),
+        }];
+
+        let mut csv = Vec::new();
+        write_logs(LogExportFormat::Csv, &logs, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("id,time,level,content"));
+        assert!(lines
+            .next()
+            .unwrap()
+            .ends_with(",----W-N-,\"理智不足, \"\"1-7\"\"\""));
+
+        let mut ndjson = Vec::new();
+        write_logs(LogExportFormat::Ndjson, &logs, &mut ndjson).unwrap();
+        let value: serde_json::Value = serde_json::from_slice(&ndjson).unwrap();
+        assert_eq!(value["id"], 42);
+        assert_eq!(value["content"], logs[0].content);
+        assert_eq!(
+            LogLevel::from_attributes_tag(value["level"].as_str().unwrap()).bits(),
+            logs[0].log_level.bits()
+        );
+    }
+}
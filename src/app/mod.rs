@@ -1,7 +1,13 @@
+/// 资源及托管状态变化的桌面提醒
+pub mod alerts;
+/// 理智恢复及作战次数规划
+pub mod ap_planner;
 /// API用户模型
 pub mod api_user_model;
 /// API请求处理器，用于接收API命令
 pub mod api_worker;
+/// 配置文件，及合并启动参数、环境变量后生效的配置
+pub mod app_config;
 /// AppState，管理UI中状态及其数据映射（Mapping）
 pub mod app_state;
 /// 资源处理器，用于接收资源命令并请求资源文件及缓存等
@@ -12,6 +18,10 @@ pub mod auth;
 pub mod auth_worker;
 /// UI控制器类，用于在Rust运行时和UI组件之间传输数据和执行操作
 pub mod controller;
+/// 设置及数据的导出与导入
+pub mod data_bundle;
+/// 诊断信息包，用于反馈问题
+pub mod diagnostics;
 /// 环境（变量）相关
 pub mod env;
 /// 游戏资源数据类，用于关卡信息显示、立绘定位等
@@ -19,6 +29,10 @@ pub mod game_data;
 /// 用于在桌面端中处理UI进程和验证网页弹窗进程的通讯
 #[cfg(feature = "desktop-app")]
 pub mod ipc_auth_comm;
+/// 游戏日志的本地存档及搜索
+pub mod log_archive;
+/// 游戏日志导出
+pub mod log_export;
 /// APP OTA 功能相关类型
 pub mod ota;
 /// 启动参数
@@ -67,7 +81,7 @@ pub async fn run() -> Result<(), slint::PlatformError> {
 
     let user_state = get_user_state();
     let user_state_data_or_null = user_state.read().unwrap().user_state_data();
-    let auth_client = create_auth_client(user_state);
+    let auth_client = create_auth_client(user_state.clone());
 
     let stop = CancellationToken::new();
     let _guard = stop.clone().drop_guard();
@@ -114,6 +128,7 @@ pub async fn run() -> Result<(), slint::PlatformError> {
         tx_api_command.clone(),
         tx_auth_command.clone(),
         tx_asset_command.clone(),
+        user_state,
     ));
     let login_window_ref = Rc::new(std::sync::OnceLock::new());
     let ui_main_thread_context = ui_context.clone().attach(&ui, login_window_ref.clone());
@@ -278,6 +293,41 @@ pub async fn run() -> Result<(), slint::PlatformError> {
     }
 
     ui.show()?;
+
+    // 主窗口持续运行一段时间后，视为更新后的新版本可正常运行
+    tokio::spawn({
+        let stop = stop.clone();
+        async move {
+            tokio::select! {
+                _ = tokio::time::sleep(ota::consts::PROBATION_HEALTHY_UPTIME) => {
+                    _ = ota::end_probation()
+                        .map_err(|e| error!("run: error removing probation record from DB: {e}"));
+                },
+                _ = stop.cancelled() => {}
+            }
+        }
+    });
+
+    // 定期按缓存上限淘汰资源缓存
+    tokio::spawn({
+        let stop = stop.clone();
+        let config_controller = ui_context.config_controller.clone();
+        async move {
+            let mut delay = utils::cache_manager::consts::SWEEP_STARTUP_DELAY;
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {},
+                    _ = stop.cancelled() => break,
+                }
+                let config_controller = config_controller.clone();
+                _ = tokio::task::spawn_blocking(move || config_controller.sweep_http_cache())
+                    .await
+                    .map_err(|e| error!("run: error joining HTTP cache sweeper: {e}"));
+                delay = utils::cache_manager::consts::SWEEP_INTERVAL;
+            }
+        }
+    });
+
     slint::run_event_loop()?;
 
     // join workers with timeout
@@ -316,13 +366,15 @@ fn get_user_state() -> Arc<RwLock<dyn UserState>> {
 fn create_auth_client(user_state: Arc<RwLock<dyn UserState>>) -> AuthClient {
     use reqwest_retry::policies::ExponentialBackoff;
     use reqwest_retry::RetryTransientMiddleware;
-    let client = AuthClient::default_client_builder()
+    let mut client_builder = AuthClient::default_client_builder()
         .use_rustls_tls()
         .gzip(true)
         .brotli(true)
-        .connect_timeout(consts::AUTH_CLIENT_CONNECT_TIMEOUT)
-        .build()
-        .unwrap();
+        .connect_timeout(consts::AUTH_CLIENT_CONNECT_TIMEOUT);
+    if let Some(proxy) = app_config::get().reqwest_proxy() {
+        client_builder = client_builder.proxy(proxy);
+    }
+    let client = client_builder.build().unwrap();
 
     let retry_policy: ExponentialBackoff =
         ExponentialBackoff::builder().build_with_max_retries(consts::AUTH_CLIENT_MAX_RETRIES);
@@ -331,7 +383,7 @@ fn create_auth_client(user_state: Arc<RwLock<dyn UserState>>) -> AuthClient {
         .build();
 
     AuthClient::new(
-        arkhost_api::consts::passport::API_BASE_URL,
+        &app_config::get().passport_server.value,
         client_with_middlewares,
         user_state,
     )
@@ -345,13 +397,15 @@ fn create_asset_client() -> AssetClient {
     use utils::cache_control::default_cache_mode_fn;
     use utils::cache_manager::DBCacheManager;
 
-    let client = AssetClient::default_client_builder()
+    let mut client_builder = AssetClient::default_client_builder()
         .use_rustls_tls()
         .gzip(true)
         .brotli(true)
-        .connect_timeout(consts::ASSET_CLIENT_CONNECT_TIMEOUT)
-        .build()
-        .unwrap();
+        .connect_timeout(consts::ASSET_CLIENT_CONNECT_TIMEOUT);
+    if let Some(proxy) = app_config::get().reqwest_proxy() {
+        client_builder = client_builder.proxy(proxy);
+    }
+    let client = client_builder.build().unwrap();
 
     let retry_policy =
         ExponentialBackoff::builder().build_with_max_retries(consts::ASSET_CLIENT_MAX_RETRIES);
@@ -368,7 +422,7 @@ fn create_asset_client() -> AssetClient {
         .build();
 
     AssetClient::new(
-        env::override_asset_server().unwrap_or(arkhost_api::consts::asset::API_BASE_URL),
+        &app_config::get().asset_server.value,
         client_with_middlewares,
     )
 }
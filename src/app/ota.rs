@@ -1,11 +1,24 @@
 #![allow(dead_code)]
 use super::utils::db;
+use arkhost_ota::keys::KeyRotation;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum RecordType {
     PendingUpdate,
+    /// 更新前的客户端程序备份
+    Backup,
+    /// 更新后尚未确认可正常运行的版本
+    Probation,
+    /// 因多次非正常退出而被回滚的版本
+    RolledBack,
+    /// 已见过的最大索引序号
+    LastSeenIndexSerial,
+    /// 已接受的公钥轮换声明
+    KeyRotations,
+    /// 随机生成的安装 ID，用于灰度发布分桶
+    InstallId,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,35 +39,166 @@ pub struct ReleaseRecord {
     pub binary: Resource,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbationRecord {
+    pub version: semver::Version,
+    pub sha256: [u8; 32],
+    /// 试用期内主窗口非正常退出的次数
+    pub abnormal_exits: u32,
+}
+
 pub fn upsert_pending_update(release_record: &ReleaseRecord) -> heed::Result<()> {
+    upsert_record(RecordType::PendingUpdate, release_record)
+}
+
+pub fn pending_update() -> heed::Result<Option<ReleaseRecord>> {
+    record(RecordType::PendingUpdate)
+}
+
+pub fn remove_pending_update() -> heed::Result<()> {
+    remove_record(RecordType::PendingUpdate)
+}
+
+pub fn upsert_backup(release_record: &ReleaseRecord) -> heed::Result<()> {
+    upsert_record(RecordType::Backup, release_record)
+}
+
+pub fn backup() -> heed::Result<Option<ReleaseRecord>> {
+    record(RecordType::Backup)
+}
+
+pub fn remove_backup() -> heed::Result<()> {
+    remove_record(RecordType::Backup)
+}
+
+pub fn start_probation(version: semver::Version, sha256: [u8; 32]) -> heed::Result<()> {
+    upsert_record(
+        RecordType::Probation,
+        &ProbationRecord {
+            version,
+            sha256,
+            abnormal_exits: 0,
+        },
+    )
+}
+
+pub fn probation() -> heed::Result<Option<ProbationRecord>> {
+    record(RecordType::Probation)
+}
+
+/// 结束试用期，当前版本视为可正常运行
+pub fn end_probation() -> heed::Result<()> {
+    remove_record(RecordType::Probation)
+}
+
+/// 记录一次试用期内的非正常退出，返回更新后的试用期记录；不在试用期时返回 `None`
+pub fn record_abnormal_exit() -> heed::Result<Option<ProbationRecord>> {
     let env = db::env();
-    let db = db()?;
+    let db = db::<ProbationRecord>()?;
+    let key = record_type_key(RecordType::Probation)?;
     let mut wtxn = env.write_txn()?;
-    db.put(
-        &mut wtxn,
-        &record_type_key(RecordType::PendingUpdate)?,
-        release_record,
-    )?;
+    let probation = match db.get(&wtxn, &key)? {
+        Some(mut probation) => {
+            probation.abnormal_exits += 1;
+            db.put(&mut wtxn, &key, &probation)?;
+            Some(probation)
+        }
+        None => None,
+    };
+    wtxn.commit()?;
+    Ok(probation)
+}
+
+/// 将试用期中的版本标记为已回滚，之后的更新检查将跳过该版本
+pub fn mark_probation_rolled_back() -> heed::Result<Option<ProbationRecord>> {
+    let env = db::env();
+    let db = db::<ProbationRecord>()?;
+    let probation_key = record_type_key(RecordType::Probation)?;
+    let mut wtxn = env.write_txn()?;
+    let probation = db.get(&wtxn, &probation_key)?;
+    if let Some(probation) = &probation {
+        db.put(
+            &mut wtxn,
+            &record_type_key(RecordType::RolledBack)?,
+            probation,
+        )?;
+        db.delete(&mut wtxn, &probation_key)?;
+    }
+    wtxn.commit()?;
+    Ok(probation)
+}
+
+pub fn rolled_back() -> heed::Result<Option<ProbationRecord>> {
+    record(RecordType::RolledBack)
+}
+
+pub fn last_seen_index_serial() -> heed::Result<Option<u64>> {
+    record(RecordType::LastSeenIndexSerial)
+}
+
+/// 更新已见过的最大索引序号，序号只增不减
+pub fn update_last_seen_index_serial(serial: u64) -> heed::Result<()> {
+    let env = db::env();
+    let db = db::<u64>()?;
+    let key = record_type_key(RecordType::LastSeenIndexSerial)?;
+    let mut wtxn = env.write_txn()?;
+    if !matches!(db.get(&wtxn, &key)?, Some(last) if last >= serial) {
+        db.put(&mut wtxn, &key, &serial)?;
+    }
     wtxn.commit()
 }
 
-pub fn pending_update() -> heed::Result<Option<ReleaseRecord>> {
+pub fn key_rotations() -> heed::Result<Vec<KeyRotation>> {
+    record(RecordType::KeyRotations).map(Option::unwrap_or_default)
+}
+
+pub fn upsert_key_rotations(rotations: &[KeyRotation]) -> heed::Result<()> {
+    upsert_record(RecordType::KeyRotations, &rotations.to_vec())
+}
+
+/// 获取安装 ID，不存在时随机生成
+pub fn install_id() -> heed::Result<[u8; 16]> {
     let env = db::env();
-    let db = db()?;
+    let db = db::<[u8; 16]>()?;
+    let key = record_type_key(RecordType::InstallId)?;
+    let mut wtxn = env.write_txn()?;
+    let install_id = match db.get(&wtxn, &key)? {
+        Some(install_id) => install_id,
+        None => {
+            let install_id: [u8; 16] = rand::random();
+            db.put(&mut wtxn, &key, &install_id)?;
+            install_id
+        }
+    };
+    wtxn.commit()?;
+    Ok(install_id)
+}
+
+fn upsert_record<T: Serialize + 'static>(rel_type: RecordType, record: &T) -> heed::Result<()> {
+    let env = db::env();
+    let db = db::<T>()?;
+    let mut wtxn = env.write_txn()?;
+    db.put(&mut wtxn, &record_type_key(rel_type)?, record)?;
+    wtxn.commit()
+}
+
+fn record<T: for<'de> Deserialize<'de> + 'static>(rel_type: RecordType) -> heed::Result<Option<T>> {
+    let env = db::env();
+    let db = db::<T>()?;
     let rtxn = env.read_txn()?;
 
-    db.get(&rtxn, &record_type_key(RecordType::PendingUpdate)?)
+    db.get(&rtxn, &record_type_key(rel_type)?)
 }
 
-pub fn remove_pending_update() -> heed::Result<()> {
+fn remove_record(rel_type: RecordType) -> heed::Result<()> {
     let env = db::env();
-    let db = db()?;
+    let db = db::<ReleaseRecord>()?;
     let mut wtxn = env.write_txn()?;
-    db.delete(&mut wtxn, &record_type_key(RecordType::PendingUpdate)?)?;
+    db.delete(&mut wtxn, &record_type_key(rel_type)?)?;
     wtxn.commit()
 }
 
-fn db() -> heed::Result<heed::Database<heed::types::Str, heed::types::SerdeBincode<ReleaseRecord>>>
+fn db<T: 'static>() -> heed::Result<heed::Database<heed::types::Str, heed::types::SerdeBincode<T>>>
 {
     db::database(Some(db::consts::db::OTA_RELEASE))
 }
@@ -62,3 +206,12 @@ fn db() -> heed::Result<heed::Database<heed::types::Str, heed::types::SerdeBinco
 fn record_type_key(rel_type: RecordType) -> heed::Result<String> {
     serde_json::ser::to_string(&rel_type).map_err(|e| heed::Error::Encoding(e.into()))
 }
+
+pub mod consts {
+    use std::time::Duration;
+
+    /// 试用期内主窗口非正常退出达到该次数后自动回滚
+    pub const DEFAULT_ROLLBACK_THRESHOLD: u32 = 3;
+    /// 主窗口持续运行该时长后结束试用期
+    pub const PROBATION_HEALTHY_UPTIME: Duration = Duration::from_secs(60);
+}
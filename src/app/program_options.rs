@@ -13,6 +13,22 @@ pub struct LaunchArgs {
     /// ARKHOST_APP_FORCE_UPDATE='1'
     pub force_update: Option<bool>,
 
+    #[argh(option)]
+    /// 指定数据目录，等同于设置环境变量
+    /// ARKHOST_APP_DATA_DIR=<data_dir>；
+    /// 未指定时若程序所在目录存在 portable.txt 则使用该目录下的 data 目录（便携模式）
+    pub data_dir: Option<String>,
+
+    #[argh(option)]
+    /// 指定配置文件，等同于设置环境变量
+    /// ARKHOST_APP_CONFIG=<config>；未指定时使用数据目录下的 config.toml
+    pub config: Option<String>,
+
+    #[argh(option)]
+    /// 指定 HTTP 代理，等同于设置环境变量
+    /// ARKHOST_APP_PROXY=<proxy>
+    pub proxy: Option<String>,
+
     #[argh(option)]
     /// 指定资源服务器，等同于设置环境变量
     /// ARKHOST_APP_OVERRIDE_ASSET_SERVER=<asset_server>
@@ -23,6 +39,11 @@ pub struct LaunchArgs {
     /// ARKHOST_APP_USER_TOKEN=<user_token>
     pub user_token: Option<String>,
 
+    #[argh(option)]
+    /// 更新后新版本主窗口非正常退出达到该次数时自动回滚，等同于设置环境变量
+    /// ARKHOST_APP_OTA_ROLLBACK_THRESHOLD=<ota_rollback_threshold>
+    pub ota_rollback_threshold: Option<u32>,
+
     #[argh(subcommand)]
     pub launch_spec: Option<LaunchSpec>,
 }
@@ -32,6 +53,10 @@ pub struct LaunchArgs {
 pub enum LaunchSpec {
     AppWindow(LaunchAppWindowArgs),
     WebView(LaunchWebViewArgs),
+    Export(ExportArgs),
+    Import(ImportArgs),
+    Config(ConfigArgs),
+    Db(DbArgs),
 }
 
 #[derive(FromArgs)]
@@ -52,3 +77,87 @@ pub struct LaunchWebViewArgs {
     /// 父进程 IPC Server 名称（WebView）
     pub ipc: String,
 }
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+/// 导出设置及数据
+pub struct ExportArgs {
+    #[argh(option)]
+    /// 导出文件路径，不指定则导出到数据目录下
+    pub out: Option<String>,
+
+    #[argh(switch)]
+    /// 同时导出登录凭据（JWT），默认不导出
+    pub include_token: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "import")]
+/// 导入设置及数据，导入前请关闭 App
+pub struct ImportArgs {
+    #[argh(positional)]
+    /// 导入文件路径
+    pub path: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "config")]
+/// 配置文件相关
+pub struct ConfigArgs {
+    #[argh(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum ConfigCommand {
+    Dump(ConfigDumpArgs),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dump")]
+/// 输出生效的配置及其来源
+pub struct ConfigDumpArgs {
+    #[argh(switch)]
+    /// 仅输出配置文件路径
+    pub path: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "db")]
+/// 数据库维护，除校验外需先关闭 App
+pub struct DbArgs {
+    #[argh(subcommand)]
+    pub command: DbCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum DbCommand {
+    Verify(DbVerifyArgs),
+    Compact(DbCompactArgs),
+    Clear(DbClearArgs),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "verify")]
+/// 校验各数据库中的条目能否正确解码
+pub struct DbVerifyArgs {
+    #[argh(switch)]
+    /// 删除无法解码的条目
+    pub repair: bool,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "compact")]
+/// 压缩数据库文件
+pub struct DbCompactArgs {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "clear")]
+/// 清除部分数据
+pub struct DbClearArgs {
+    #[argh(positional)]
+    /// 要清除的数据：cache、config、credentials、logs 或 ota
+    pub target: String,
+}
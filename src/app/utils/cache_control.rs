@@ -24,7 +24,7 @@ pub fn default_cache_mode_fn() -> CacheModeFn {
                     (split.next(), split.next()), 
                         (Some(hash_versioned_file), Some(hash_version_dir)) if
                             (hash_version_dir.ends_with(".exe")
-                            || hash_versioned_file.ends_with(".bspatch")))
+                            || arkhost_ota::bin_diff::PatchFormat::from_filename(hash_versioned_file).is_some()))
                     // TODO: 其他方式识别OTA更新文件
                 };
                 if matches_ota_file {
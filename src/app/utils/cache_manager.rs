@@ -1,26 +1,97 @@
 use super::db;
+use heed::BytesEncode;
 use http_cache::{CacheManager, HttpResponse, Result};
 use http_cache_semantics::CachePolicy;
-use log::trace;
+use log::{debug, trace, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub struct DBCacheManager {
     db: heed::Database<heed::types::Str, heed::types::SerdeBincode<Store>>,
+    access_db: heed::Database<heed::types::Str, heed::types::SerdeBincode<AccessRecord>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-struct Store {
+pub(crate) struct Store {
     cache_key: String,
     response: HttpResponse,
     policy: CachePolicy,
 }
 
+/// 缓存分类，各分类有独立的配额
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CacheCategory {
+    Avatar,
+    CharIllust,
+    Screenshot,
+    Gamedata,
+    Other,
+}
+
+impl CacheCategory {
+    /// 根据缓存键（`{METHOD}:{URL}`）中的URL判断分类
+    pub fn from_cache_key(cache_key: &str) -> Self {
+        let url = cache_key
+            .split_once(':')
+            .and_then(|(_, url)| url::Url::parse(url).ok());
+        let Some(url) = url else {
+            return CacheCategory::Other;
+        };
+        if url.host_str()
+            == url::Url::parse(arkhost_api::consts::screenshot::API_BASE_URL)
+                .ok()
+                .as_ref()
+                .and_then(|x| x.host_str())
+        {
+            return CacheCategory::Screenshot;
+        }
+        let path = url.path();
+        match path.trim_start_matches('/').split_once('/') {
+            Some(("avatar", _)) => CacheCategory::Avatar,
+            Some(("gamedata", _)) => CacheCategory::Gamedata,
+            Some(("charpack", _)) if path.ends_with(".json") => CacheCategory::Gamedata,
+            Some(("charpack", _)) => CacheCategory::CharIllust,
+            _ => CacheCategory::Other,
+        }
+    }
+
+    /// 占总缓存上限的百分比
+    pub fn quota_percentage(self) -> u64 {
+        match self {
+            CacheCategory::Avatar => consts::AVATAR_QUOTA_PERCENTAGE,
+            CacheCategory::CharIllust => consts::CHAR_ILLUST_QUOTA_PERCENTAGE,
+            CacheCategory::Screenshot => consts::SCREENSHOT_QUOTA_PERCENTAGE,
+            CacheCategory::Gamedata => consts::GAMEDATA_QUOTA_PERCENTAGE,
+            CacheCategory::Other => consts::OTHER_QUOTA_PERCENTAGE,
+        }
+    }
+}
+
+/// 缓存项的访问记录，用于 LRU 淘汰
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AccessRecord {
+    pub category: CacheCategory,
+    /// 缓存项在数据库中的大小
+    pub size: u64,
+    /// 最后访问时间（Unix 时间戳，秒）
+    pub last_access: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct SweepResult {
+    pub evicted: usize,
+    pub evicted_size: u64,
+    pub remaining_size: u64,
+}
+
 #[allow(dead_code)]
 impl DBCacheManager {
     pub fn new() -> Self {
         let db = db::database(Some(db::consts::db::HTTP_CACHE))
             .expect("Unable to create index on cache collection");
-        Self { db }
+        let access_db = db::database(Some(db::consts::db::HTTP_CACHE_ACCESS))
+            .expect("Unable to create index on cache access collection");
+        Self { db, access_db }
     }
 
     /// Clears out the entire cache.
@@ -28,26 +99,101 @@ impl DBCacheManager {
         let env = db::env();
         let mut wtxn = env.write_txn().map_err(into_box_error)?;
         self.db.clear(&mut wtxn).map_err(into_box_error)?;
+        self.access_db.clear(&mut wtxn).map_err(into_box_error)?;
         wtxn.commit().map_err(into_box_error)
     }
+
+    ///
+    /// 按最后访问时间淘汰缓存项：先将超出配额的分类削减至配额内，再将总大小削减至 `budget` 内
+    ///
+    /// 访问记录的读取与删除在同一写事务中进行
+    ///
+    pub fn sweep(&self, budget: u64) -> heed::Result<SweepResult> {
+        let env = db::env();
+        let mut wtxn = env.write_txn()?;
+        let mut records = Vec::new();
+        for entry in self.access_db.iter(&wtxn)? {
+            let (key, record) = entry?;
+            records.push((key.to_owned(), record));
+        }
+
+        let evicted = select_evictions(records.clone(), budget);
+        let mut result = SweepResult::default();
+        for key in &evicted {
+            self.db.delete(&mut wtxn, key)?;
+            self.access_db.delete(&mut wtxn, key)?;
+        }
+        wtxn.commit()?;
+
+        let total_size: u64 = records.iter().map(|(_, x)| x.size).sum();
+        result.evicted = evicted.len();
+        result.evicted_size = records
+            .iter()
+            .filter(|(key, _)| evicted.contains(key))
+            .map(|(_, x)| x.size)
+            .sum();
+        result.remaining_size = total_size - result.evicted_size;
+        Ok(result)
+    }
+
+    fn touch(
+        access_db: heed::Database<heed::types::Str, heed::types::SerdeBincode<AccessRecord>>,
+        cache_key: &str,
+    ) -> heed::Result<()> {
+        let now = chrono::Utc::now().timestamp();
+        let record = {
+            let env = db::env();
+            let rtxn = env.read_txn()?;
+            access_db.get(&rtxn, cache_key)?
+        };
+        // 避免每次读取缓存都进行写事务
+        match record {
+            Some(record)
+                if now - record.last_access < consts::ACCESS_UPDATE_INTERVAL.as_secs() as i64 =>
+            {
+                Ok(())
+            }
+            Some(mut record) => {
+                record.last_access = now;
+                let env = db::env();
+                let mut wtxn = env.write_txn()?;
+                access_db.put(&mut wtxn, cache_key, &record)?;
+                wtxn.commit()
+            }
+            None => Ok(()),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl CacheManager for DBCacheManager {
     async fn get(&self, cache_key: &str) -> Result<Option<(HttpResponse, CachePolicy)>> {
-        let env = db::env();
-        let rtxn = env.read_txn().map_err(into_box_error)?;
-        self.db
-            .get(&rtxn, cache_key)
-            .map(|x| {
-                trace!(
-                    "retrieving '{cache_key}' found: {}, body size: {}B",
-                    x.is_some(),
-                    x.as_ref().map_or(0usize, |x| x.response.body.len())
-                );
-                x.map(|x| (x.response, x.policy))
-            })
-            .map_err(into_box_error)
+        let cached = {
+            let env = db::env();
+            let rtxn = env.read_txn().map_err(into_box_error)?;
+            self.db
+                .get(&rtxn, cache_key)
+                .map(|x| {
+                    trace!(
+                        "retrieving '{cache_key}' found: {}, body size: {}B",
+                        x.is_some(),
+                        x.as_ref().map_or(0usize, |x| x.response.body.len())
+                    );
+                    x.map(|x| (x.response, x.policy))
+                })
+                .map_err(into_box_error)?
+        };
+        // 访问记录仅用于淘汰，更新失败不影响读取结果；写事务可能阻塞，不在异步任务中进行
+        if cached.is_some() {
+            let access_db = self.access_db;
+            let cache_key = cache_key.to_owned();
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) = Self::touch(access_db, &cache_key) {
+                    warn!("error updating access record of '{cache_key}': {e}");
+                }
+            });
+        }
+        Ok(cached)
     }
 
     async fn put(
@@ -62,12 +208,24 @@ impl CacheManager for DBCacheManager {
             response,
             policy,
         };
+        let size = heed::types::SerdeBincode::<Store>::bytes_encode(&store)
+            .map_err(|e| into_box_error(heed::Error::Encoding(e)))?
+            .len()
+            + cache_key.len();
+        let record = AccessRecord {
+            category: CacheCategory::from_cache_key(&cache_key),
+            size: size as u64,
+            last_access: chrono::Utc::now().timestamp(),
+        };
 
         let env = db::env();
         let mut wtxn = env.write_txn().map_err(into_box_error)?;
         self.db
             .put(&mut wtxn, &cache_key, &store)
             .map_err(into_box_error)?;
+        self.access_db
+            .put(&mut wtxn, &cache_key, &record)
+            .map_err(into_box_error)?;
         wtxn.commit().map_err(into_box_error)?;
         Ok(store.response)
     }
@@ -79,10 +237,165 @@ impl CacheManager for DBCacheManager {
         self.db
             .delete(&mut wtxn, cache_key)
             .map_err(into_box_error)?;
+        self.access_db
+            .delete(&mut wtxn, cache_key)
+            .map_err(into_box_error)?;
         wtxn.commit().map_err(into_box_error)
     }
 }
 
+///
+/// 选出需要淘汰的缓存项
+///
+/// 1. 各分类中最久未访问的项被淘汰，直至该分类大小不超过其配额
+/// 2. 剩余项中最久未访问的项被淘汰，直至总大小不超过 `budget`
+///
+pub fn select_evictions(mut records: Vec<(String, AccessRecord)>, budget: u64) -> Vec<String> {
+    records.sort_by_key(|(_, x)| x.last_access);
+
+    let mut category_sizes = HashMap::<CacheCategory, u64>::new();
+    for (_, record) in &records {
+        *category_sizes.entry(record.category).or_default() += record.size;
+    }
+    let mut evicted = Vec::new();
+    let mut remaining = Vec::new();
+    for (key, record) in records {
+        let category_size = category_sizes.entry(record.category).or_default();
+        if *category_size > budget.saturating_mul(record.category.quota_percentage()) / 100 {
+            *category_size -= record.size;
+            evicted.push(key);
+        } else {
+            remaining.push((key, record));
+        }
+    }
+
+    let mut total_size: u64 = remaining.iter().map(|(_, x)| x.size).sum();
+    for (key, record) in remaining {
+        if total_size <= budget {
+            break;
+        }
+        total_size -= record.size;
+        evicted.push(key);
+    }
+    evicted
+}
+
+///
+/// 为升级前已存在的缓存项建立访问记录，最后访问时间视为最早
+///
+pub fn migrate_build_access_index(env: &heed::Env, wtxn: &mut heed::RwTxn) -> heed::Result<()> {
+    let cache_db: heed::Database<heed::types::Str, heed::types::Bytes> =
+        env.create_database(wtxn, Some(db::consts::db::HTTP_CACHE))?;
+    let access_db: heed::Database<heed::types::Str, heed::types::SerdeBincode<AccessRecord>> =
+        env.create_database(wtxn, Some(db::consts::db::HTTP_CACHE_ACCESS))?;
+    let mut records = Vec::new();
+    for entry in cache_db.iter(wtxn)? {
+        let (key, value) = entry?;
+        records.push((
+            key.to_owned(),
+            AccessRecord {
+                category: CacheCategory::from_cache_key(key),
+                size: (key.len() + value.len()) as u64,
+                last_access: 0,
+            },
+        ));
+    }
+    debug!(
+        "migrate_build_access_index: indexing {} cache entries",
+        records.len()
+    );
+    for (key, record) in records {
+        access_db.put(wtxn, &key, &record)?;
+    }
+    Ok(())
+}
+
 fn into_box_error(e: heed::Error) -> http_cache::BoxError {
     anyhow::anyhow!(e.to_string()).into()
 }
+
+pub mod consts {
+    use std::time::Duration;
+
+    pub const DEFAULT_BUDGET_MB: u64 = 512;
+    /// 设置页面中可选的缓存上限（MB），与 UI 中的选项一一对应
+    pub const BUDGET_OPTIONS_MB: [u64; 4] = [256, 512, 1024, 2048];
+    // 各分类配额之和可超过 100%，总大小仍受缓存上限限制
+    pub const AVATAR_QUOTA_PERCENTAGE: u64 = 20;
+    pub const CHAR_ILLUST_QUOTA_PERCENTAGE: u64 = 60;
+    pub const SCREENSHOT_QUOTA_PERCENTAGE: u64 = 40;
+    pub const GAMEDATA_QUOTA_PERCENTAGE: u64 = 30;
+    pub const OTHER_QUOTA_PERCENTAGE: u64 = 10;
+    /// 距上次记录超过该时间的访问才会更新访问记录
+    pub const ACCESS_UPDATE_INTERVAL: Duration = Duration::from_secs(60);
+    pub const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+    /// 启动后延迟进行首次清理，避免与启动时的资源加载争用数据库
+    pub const SWEEP_STARTUP_DELAY: Duration = Duration::from_secs(30);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(category: CacheCategory, size: u64, last_access: i64) -> AccessRecord {
+        AccessRecord {
+            category,
+            size,
+            last_access,
+        }
+    }
+
+    #[test]
+    fn test_cache_category() {
+        let asset = arkhost_api::consts::asset::API_BASE_URL;
+        let screenshot = arkhost_api::consts::screenshot::API_BASE_URL;
+        for (key, category) in [
+            (
+                format!("GET:{asset}avatar/ASSISTANT/char_002_amiya.webp"),
+                CacheCategory::Avatar,
+            ),
+            (
+                format!("GET:{asset}charpack/char_002_amiya_1.webp"),
+                CacheCategory::CharIllust,
+            ),
+            (
+                format!("GET:{asset}charpack/summary.json"),
+                CacheCategory::Gamedata,
+            ),
+            (
+                format!("GET:{asset}gamedata/excel/stage_table.json"),
+                CacheCategory::Gamedata,
+            ),
+            (
+                format!("GET:{screenshot}/foo.png"),
+                CacheCategory::Screenshot,
+            ),
+            (
+                format!("GET:{asset}ui/ota/v1/index.json"),
+                CacheCategory::Other,
+            ),
+            ("invalid".to_owned(), CacheCategory::Other),
+        ] {
+            assert_eq!(CacheCategory::from_cache_key(&key), category, "{key}");
+        }
+    }
+
+    #[test]
+    fn test_select_evictions() {
+        let records = vec![
+            ("s1".to_owned(), record(CacheCategory::Screenshot, 30, 0)),
+            ("a1".to_owned(), record(CacheCategory::Avatar, 10, 1)),
+            ("a2".to_owned(), record(CacheCategory::Avatar, 10, 5)),
+            ("c1".to_owned(), record(CacheCategory::CharIllust, 30, 2)),
+            ("c2".to_owned(), record(CacheCategory::CharIllust, 30, 4)),
+            ("g1".to_owned(), record(CacheCategory::Gamedata, 20, 3)),
+        ];
+        // 未超出上限及配额
+        assert!(select_evictions(records.clone(), 1000).is_empty());
+        // 各分类均未超出配额，总大小 130 超出上限，淘汰最久未访问的 s1
+        assert_eq!(select_evictions(records.clone(), 100), vec!["s1"]);
+        // 头像配额为 16，淘汰 a1；立绘配额为 48，淘汰 c1；剩余 90 超出上限，再淘汰 s1
+        // 上限不足 100 时配额不应被舍入为 0
+        assert_eq!(select_evictions(records, 80), vec!["a1", "c1", "s1"]);
+    }
+}
@@ -0,0 +1,315 @@
+use super::data_dir::data_dir_create_all;
+use log::{LevelFilter, Log, Metadata, Record};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+///
+/// 按大小轮转的日志文件
+///
+/// 超出 `max_size` 时依次将 `x.log` 重命名为 `x.log.1`、`x.log.1` 重命名为 `x.log.2`……，
+/// 最多保留 `max_files` 个旧文件
+///
+pub struct RotatingFile {
+    path: PathBuf,
+    file: Option<File>,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: PathBuf, max_size: u64, max_files: usize) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file: Some(file),
+            size,
+            max_size,
+            max_files,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    ///
+    /// 轮转失败时继续追加写入原文件，并推迟至再写入 `max_size` 后重试
+    ///
+    fn rotate(&mut self) -> io::Result<()> {
+        // Windows 下无法重命名已打开的文件
+        self.file = None;
+        self.size = 0;
+        match self.rename_and_create() {
+            Ok(file) => {
+                self.file = Some(file);
+                Ok(())
+            }
+            Err(e) => {
+                self.file = Some(File::options().create(true).append(true).open(&self.path)?);
+                Err(e)
+            }
+        }
+    }
+
+    fn rename_and_create(&self) -> io::Result<File> {
+        for i in (1..self.max_files).rev() {
+            let from = self.rotated_path(i);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated_path(i + 1))?;
+            }
+        }
+        if self.max_files > 0 {
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        File::options()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            if let Err(e) = self.rotate() {
+                _ = writeln!(io::stderr(), "error rotating log file {:?}: {e}", self.path);
+            }
+        }
+        let file = self
+            .file
+            .as_mut()
+            .ok_or_else(|| io::Error::other("log file is not open"))?;
+        let written = file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+///
+/// 同时输出到 stderr 及日志文件的 Logger
+///
+/// 过滤规则同 env_logger；运行时设置的日志等级仅作用于本程序的模块，
+/// 避免依赖库输出过多日志
+///
+struct ClientLogger {
+    filter: env_logger::Logger,
+    /// 0 表示使用过滤规则，否则为 `LevelFilter as usize`
+    override_level: AtomicUsize,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl ClientLogger {
+    fn override_level(&self) -> Option<LevelFilter> {
+        match self.override_level.load(Ordering::Relaxed) {
+            0 => None,
+            level => LevelFilter::iter().nth(level),
+        }
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.filter
+            .filter()
+            .max(self.override_level().unwrap_or(LevelFilter::Off))
+    }
+}
+
+impl Log for ClientLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match self.override_level() {
+            Some(level)
+                if consts::APP_TARGETS
+                    .iter()
+                    .any(|x| metadata.target().starts_with(x)) =>
+            {
+                metadata.level() <= level
+            }
+            _ => self.filter.enabled(metadata),
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        let enabled = match self.override_level() {
+            Some(_) => self.enabled(record.metadata()),
+            None => self.filter.matches(record),
+        };
+        if !enabled {
+            return;
+        }
+        let line = format!(
+            "[{} {:<5} {}] {}\n",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        _ = io::stderr().write_all(line.as_bytes());
+        if let Some(file) = &self.file {
+            _ = file.lock().unwrap().write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.file {
+            _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+static LOGGER: OnceLock<ClientLogger> = OnceLock::new();
+static LOG_FILE_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+///
+/// 初始化日志，`filters` 为 env_logger 过滤规则，未指定时读取 `RUST_LOG`
+///
+/// 指定 `file_name` 时同时写入数据目录下的日志文件，文件无法打开时仅输出到 stderr
+///
+pub fn init(filters: Option<&str>, file_name: Option<&str>) {
+    let mut builder = env_logger::Builder::from_default_env();
+    if let Some(filters) = filters {
+        builder.parse_filters(filters);
+    }
+    let mut file_error = None;
+    let file = file_name.and_then(|file_name| {
+        let path = log_dir().join(file_name);
+        match RotatingFile::open(
+            path.clone(),
+            consts::MAX_FILE_SIZE,
+            consts::MAX_ROTATED_FILES,
+        ) {
+            Ok(file) => {
+                _ = LOG_FILE_PATH.set(path);
+                Some(Mutex::new(file))
+            }
+            Err(e) => {
+                file_error = Some(format!("{}: {e}", path.display()));
+                None
+            }
+        }
+    });
+    let logger = LOGGER.get_or_init(|| ClientLogger {
+        filter: builder.build(),
+        override_level: AtomicUsize::new(0),
+        file,
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.max_level());
+    }
+    if let Some(e) = file_error {
+        log::warn!("init: unable to open log file {e}");
+    }
+}
+
+/// 运行时调整本程序模块的日志等级，`None` 为恢复启动时的过滤规则
+pub fn set_level(level: Option<LevelFilter>) {
+    let Some(logger) = LOGGER.get() else {
+        return;
+    };
+    logger
+        .override_level
+        .store(level.map_or(0, |x| x as usize), Ordering::Relaxed);
+    log::set_max_level(logger.max_level());
+    log::info!("set_level: log level set to {level:?}");
+}
+
+pub fn log_dir() -> PathBuf {
+    let dir = data_dir_create_all().join(consts::LOG_DIR);
+    _ = std::fs::create_dir_all(&dir);
+    dir
+}
+
+/// 当前进程写入的日志文件
+pub fn log_file_path() -> Option<&'static Path> {
+    LOG_FILE_PATH.get().map(|x| x.as_path())
+}
+
+/// 读取文件末尾至多 `max_bytes` 字节，舍弃不完整的首行
+pub fn tail(path: &Path, max_bytes: u64) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = len.saturating_sub(max_bytes);
+    file.seek(SeekFrom::Start(start))?;
+    let mut buf = Vec::with_capacity((len - start) as usize);
+    file.read_to_end(&mut buf)?;
+    let text = String::from_utf8_lossy(&buf);
+    Ok(match (start, text.find('\n')) {
+        (0, _) | (_, None) => text.into_owned(),
+        (_, Some(i)) => text[i + 1..].to_owned(),
+    })
+}
+
+pub mod consts {
+    pub const LOG_DIR: &str = "logs";
+    pub const BOOTSTRAP_LOG_FILE: &str = "bootstrap.log";
+    pub const APP_LOG_FILE: &str = "app.log";
+    pub const WEBVIEW_LOG_FILE: &str = "webview.log";
+    pub const MAX_FILE_SIZE: u64 = 5 << 20;
+    pub const MAX_ROTATED_FILES: usize = 3;
+    /// 运行时日志等级作用的模块
+    pub const APP_TARGETS: [&str; 3] = ["closure_studio", "arkhost_api", "arkhost_ota"];
+    /// 日志界面中的日志等级选项，`None` 为启动时的过滤规则
+    pub const LEVEL_OPTIONS: [Option<log::LevelFilter>; 4] = [
+        None,
+        Some(log::LevelFilter::Info),
+        Some(log::LevelFilter::Debug),
+        Some(log::LevelFilter::Trace),
+    ];
+    /// 日志界面显示的日志大小
+    pub const VIEWER_TAIL_BYTES: u64 = 64 << 10;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("client_log_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.log");
+        let mut file = RotatingFile::open(path.clone(), 16, 2).unwrap();
+        for i in 0..4 {
+            file.write_all(format!("line {i:<9}\n").as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(tail(&path, 1024).unwrap(), format!("line {:<9}\n", 3));
+        assert!(file.rotated_path(2).exists());
+        assert!(!file.rotated_path(3).exists());
+        // 从行中间开始读取时舍弃不完整的首行
+        let mut file = RotatingFile::open(path.clone(), 1024, 2).unwrap();
+        file.write_all(b"line 4\n").unwrap();
+        assert_eq!(tail(&path, 10).unwrap(), "line 4\n");
+
+        // 轮转失败时继续写入原文件
+        let mut file = RotatingFile::open(path.clone(), 16, 1).unwrap();
+        std::fs::remove_file(file.rotated_path(1)).unwrap();
+        std::fs::create_dir_all(file.rotated_path(1).join("blocker")).unwrap();
+        file.write_all(b"line 5 is long enough\n").unwrap();
+        file.write_all(b"line 6\n").unwrap();
+        file.flush().unwrap();
+        assert_eq!(
+            tail(&path, 1024).unwrap(),
+            format!("line {:<9}\nline 4\nline 5 is long enough\nline 6\n", 3)
+        );
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
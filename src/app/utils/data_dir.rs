@@ -2,11 +2,21 @@ use std::{env, fs, path::PathBuf};
 
 static DATA_DIR: &str = ".arkhost-ui-slint";
 
+///
+/// 数据目录，按以下顺序确定：
+/// 1. 环境变量 `ARKHOST_APP_DATA_DIR`（启动参数 `--data-dir`）
+/// 2. 便携模式：可执行文件所在目录存在 [`consts::PORTABLE_MARKER`] 时，使用该目录下的 [`consts::PORTABLE_DATA_DIR`]
+/// 3. 用户目录下的 `.arkhost-ui-slint`
+///
 pub fn data_dir() -> PathBuf {
     if let Some(data_dir) = crate::app::env::data_dir() {
         return PathBuf::from(data_dir);
     }
 
+    if let Some(data_dir) = portable_data_dir() {
+        return data_dir;
+    }
+
     home::home_dir()
         .or(env::current_dir().ok())
         .unwrap_or(PathBuf::from("."))
@@ -18,3 +28,19 @@ pub fn data_dir_create_all() -> PathBuf {
     _ = fs::create_dir_all(&dir);
     dir
 }
+
+/// 便携模式下的数据目录，未启用便携模式时为空
+pub fn portable_data_dir() -> Option<PathBuf> {
+    let current_exe = env::current_exe().ok()?;
+    let exe_dir = current_exe.parent()?;
+    exe_dir
+        .join(consts::PORTABLE_MARKER)
+        .is_file()
+        .then(|| exe_dir.join(consts::PORTABLE_DATA_DIR))
+}
+
+pub mod consts {
+    /// 可执行文件旁存在该文件时启用便携模式
+    pub const PORTABLE_MARKER: &str = "portable.txt";
+    pub const PORTABLE_DATA_DIR: &str = "data";
+}
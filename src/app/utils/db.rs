@@ -1,8 +1,9 @@
 use super::data_dir::data_dir_create_all;
+use super::db_migration;
 use heed::types::*;
 use heed::Result;
 use log::debug;
-use log::{error, info};
+use log::{error, info, warn};
 use serde::{Deserialize, Serialize};
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
@@ -28,17 +29,59 @@ pub fn env_or_none() -> &'static RwLock<Option<heed::Env>> {
     ENV_INSTANCE
         .get_or_init(|| {
             let path = env_path();
-            let env = try_open_env(&path)
-                .unwrap_or_else(
-                    || panic!("加载数据库失败！请尝试：\n1. 首次发生请关闭所有应用实例后重试\n2. 检查数据库路径是否可读写是否正确后重试\n3. 删除数据库目录\n数据库路径：{}", path.display()));
-            let env = verify_schema_version_v1(env)
-                .unwrap_or_else(
-                    |e| panic!("校验数据库格式失败！\n{}\n 请尝试：\n1. 首次发生请关闭所有应用实例后重试\n2. 删除数据库目录\n数据库路径：{}", e, path.display()));
+            let env = open_verified_env(&path).unwrap_or_else(|e| match e {
+                OpenEnvError::Open(_) => panic!("加载数据库失败！\n{}\n请尝试：\n1. 首次发生请关闭所有应用实例后重试\n2. 检查数据库路径是否可读写是否正确后重试\n3. 删除数据库目录\n数据库路径：{}", e, path.display()),
+                OpenEnvError::Schema(_) => panic!("校验数据库格式失败！\n{}\n 请尝试：\n1. 首次发生请关闭所有应用实例后重试\n2. 删除数据库目录\n数据库路径：{}", e, path.display()),
+            });
 
             RwLock::new(Some(env))
         })
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum OpenEnvError {
+    #[error("打开数据库失败：{0}")]
+    Open(heed::Error),
+    #[error("校验数据库格式失败：{0}")]
+    Schema(heed::Error),
+}
+
+///
+/// 尝试打开并校验数据库，成功后供 [`env`] 使用
+///
+/// 与 [`env_or_none`] 不同，失败时返回错误而不是 panic，以便在启动时进行修复
+///
+pub fn try_init() -> std::result::Result<(), OpenEnvError> {
+    if ENV_INSTANCE.get().is_some() {
+        return Ok(());
+    }
+    let env = open_verified_env(&env_path())?;
+    _ = ENV_INSTANCE.set(RwLock::new(Some(env)));
+    Ok(())
+}
+
+/// 打开指定路径的数据库并校验格式，校验失败时关闭数据库
+pub fn open_verified_env(path: &std::path::Path) -> std::result::Result<heed::Env, OpenEnvError> {
+    let env = open_env(path).map_err(OpenEnvError::Open)?;
+    match verify_schema_version_v1(&env) {
+        Ok(()) => Ok(env),
+        Err(e) => {
+            close_env(env);
+            Err(OpenEnvError::Schema(e))
+        }
+    }
+}
+
+/// 关闭由该进程打开的数据库，需确保没有其他引用
+pub fn close_env(env: heed::Env) {
+    if !env
+        .prepare_for_closing()
+        .wait_timeout(consts::DB_SHUTDOWN_TIMEOUT)
+    {
+        warn!("timed out waiting for Env to close");
+    }
+}
+
 pub fn database<K: 'static, D: 'static>(name: Option<&str>) -> Result<heed::Database<K, D>> {
     let env = env();
     let mut wtxn = env.write_txn()?;
@@ -57,6 +100,16 @@ pub fn shutdown() {
                 false
             }
         };
+    // 压缩后的数据库中不应保留该请求，故在关闭前清除
+    let compaction_requested = HANDLE_SELF_DELETE.load(Ordering::Relaxed)
+        && !self_delete_requested
+        && match compaction_requested().and_then(|x| request_compaction(false).map(|_| x)) {
+            Ok(val) => val,
+            Err(e) => {
+                error!("error reading compaction flag: {e}");
+                false
+            }
+        };
     let closing_event = ENV_INSTANCE
         .get()
         .and_then(|x| x.write().unwrap().take())
@@ -72,24 +125,50 @@ pub fn shutdown() {
         let path = env_path();
         info!("deleting DB: {path:?}");
         std::fs::remove_dir_all(path).expect("清除 App 数据失败！");
+        let image_cache_dir = crate::app::asset_worker::image_cache::cache_dir();
+        if image_cache_dir.exists() {
+            std::fs::remove_dir_all(image_cache_dir).expect("清除图片缓存失败！");
+        }
         super::notification::toast("已清除 App 数据", None, "", None);
+    } else if compaction_requested {
+        match super::db_maintenance::compact(&env_path()) {
+            Ok((before, after)) => info!("compacted DB: {before} -> {after} bytes"),
+            Err(e) => error!("error compacting DB: {e}"),
+        }
     }
 }
 
-/// 该进程是否处理清除数据的请求
+/// 该进程是否处理清除数据及压缩数据库的请求
 pub fn handle_self_delete(handle: bool) {
     HANDLE_SELF_DELETE.store(handle, Ordering::Relaxed);
 }
 
 pub fn request_self_delete(request: bool) -> Result<()> {
+    set_schema_flag(consts::schema_info_v1::SELF_DELETE_REQUESTED, request)
+}
+
+pub fn self_delete_requested() -> Result<bool> {
+    schema_flag(consts::schema_info_v1::SELF_DELETE_REQUESTED)
+}
+
+/// 请求在 App 退出后压缩数据库
+pub fn request_compaction(request: bool) -> Result<()> {
+    set_schema_flag(consts::schema_info_v1::COMPACTION_REQUESTED, request)
+}
+
+pub fn compaction_requested() -> Result<bool> {
+    schema_flag(consts::schema_info_v1::COMPACTION_REQUESTED)
+}
+
+fn set_schema_flag(key: &str, val: bool) -> Result<()> {
     let env = env_or_none();
     if let Some(env) = env.read().unwrap().as_ref() {
         let mut wtxn = env.write_txn()?;
         let db = env.create_database::<Str, Str>(&mut wtxn, Some(consts::db::SCHEMA_INFO))?;
-        if request {
-            db.put(&mut wtxn, consts::schema_info_v1::SELF_DELETE_REQUESTED, "")?;
+        if val {
+            db.put(&mut wtxn, key, "")?;
         } else {
-            db.delete(&mut wtxn, consts::schema_info_v1::SELF_DELETE_REQUESTED)?;
+            db.delete(&mut wtxn, key)?;
         }
         wtxn.commit()
     } else {
@@ -97,15 +176,13 @@ pub fn request_self_delete(request: bool) -> Result<()> {
     }
 }
 
-pub fn self_delete_requested() -> Result<bool> {
+fn schema_flag(key: &str) -> Result<bool> {
     let env = env_or_none();
     if let Some(env) = env.read().unwrap().as_ref() {
         let rtxn = env.read_txn()?;
         let db = env.open_database::<Str, Str>(&rtxn, Some(consts::db::SCHEMA_INFO))?;
         if let Some(db) = db {
-            Ok(db
-                .get(&rtxn, consts::schema_info_v1::SELF_DELETE_REQUESTED)?
-                .is_some())
+            Ok(db.get(&rtxn, key)?.is_some())
         } else {
             Ok(false)
         }
@@ -148,11 +225,12 @@ impl Drop for InnerCleanupGuard {
     }
 }
 
-fn env_path() -> std::path::PathBuf {
+pub fn env_path() -> std::path::PathBuf {
     data_dir_create_all().join(std::path::Path::new(consts::DB_DATA_PATH))
 }
 
-fn try_open_env(path: &std::path::Path) -> Option<heed::Env> {
+/// 打开指定路径的数据库，不校验格式
+pub fn open_env(path: &std::path::Path) -> Result<heed::Env> {
     _ = std::fs::create_dir_all(path);
 
     // Safety: 见heed::EnvOpenOptions::open()
@@ -166,32 +244,58 @@ fn try_open_env(path: &std::path::Path) -> Option<heed::Env> {
         info!("opened Env at {path:?}");
         db
     })
-    .map_err(|e| error!("error opening LMDB Env at {path:?}: {e}"))
-    .ok()
+    .inspect_err(|e| error!("error opening LMDB Env at {path:?}: {e}"))
 }
 
-fn verify_schema_version_v1(env: heed::Env) -> Result<heed::Env> {
+fn verify_schema_version_v1(env: &heed::Env) -> Result<()> {
     let current_schema_version = &consts::schema_info_v1::CURRENT_SCHEMA_VERSION;
+    let stored_version = stored_schema_version(env)?;
+    if let Some(stored_version) = &stored_version {
+        debug!("schema version found: {stored_version}");
+    }
+
+    // 数据库由更新版本的客户端写入（如回滚 OTA 更新后），保持原样以免清空登录信息、配置及更新记录
+    if let Some(stored_version) = &stored_version {
+        if stored_version > current_schema_version {
+            warn!(
+                "schema version {stored_version} is newer than {current_schema_version}, leaving DBs untouched"
+            );
+            return Ok(());
+        }
+    }
+
+    if stored_version.as_ref() != Some(current_schema_version) {
+        // 备份需在写事务开始前进行，备份失败时中止升级
+        if let Some(stored_version) = &stored_version {
+            let path = db_migration::backup_env(env, stored_version).inspect_err(|e| {
+                error!("unable to back up Env before schema upgrade, aborting: {e}")
+            })?;
+            info!("backed up Env before schema upgrade to {path:?}");
+        }
+    }
+
     let mut wtxn = env.write_txn()?;
     let schema_info_db: heed::Database<Str, Str> =
         env.create_database(&mut wtxn, Some(consts::db::SCHEMA_INFO))?;
-    let current_version = schema_info_db
-        .get(&wtxn, consts::schema_info_v1::SCHEMA_VERSION_KEY)
-        .ok()
-        .flatten()
-        .and_then(|ver| serde_json::de::from_str::<semver::Version>(ver).ok());
-
-    if let Some(current_version) = &current_version {
-        debug!("schema version found: {current_version}");
+    match &stored_version {
+        Some(ver) if ver == current_schema_version => {}
+        Some(ver)
+            if ver < current_schema_version
+                && ver >= &db_migration::consts::MIN_MIGRATABLE_SCHEMA_VERSION =>
+        {
+            info!("migrating DBs from schema version {ver} to {current_schema_version}");
+            db_migration::run_migrations(env, &mut wtxn, ver, current_schema_version)?;
+        }
+        _ => {
+            info!("dropping DBs on schema version mismatch");
+            drop_dbs(env, schema_info_db, &mut wtxn)?;
+        }
     }
-    if !current_version.is_some_and(|ver| ver == consts::schema_info_v1::CURRENT_SCHEMA_VERSION) {
-        info!("dropping DBs on schema version mismatch");
-        drop_dbs(&env, schema_info_db, &mut wtxn)?;
-
+    if stored_version.as_ref() != Some(current_schema_version) {
         schema_info_db.put(
             &mut wtxn,
             consts::schema_info_v1::SCHEMA_VERSION_KEY,
-            &serde_json::ser::to_string(&consts::schema_info_v1::CURRENT_SCHEMA_VERSION)
+            &serde_json::ser::to_string(current_schema_version)
                 .map_err(|e| heed::Error::Encoding(e.into()))?,
         )?;
     }
@@ -200,11 +304,28 @@ fn verify_schema_version_v1(env: heed::Env) -> Result<heed::Env> {
     track_db_in_schema(schema_info_db, consts::db::USER_STATE, &mut wtxn)?;
     track_db_in_schema(schema_info_db, consts::db::USER_CONFIG, &mut wtxn)?;
     track_db_in_schema(schema_info_db, consts::db::HTTP_CACHE, &mut wtxn)?;
+    track_db_in_schema(schema_info_db, consts::db::HTTP_CACHE_ACCESS, &mut wtxn)?;
     track_db_in_schema(schema_info_db, consts::db::OTA_RELEASE, &mut wtxn)?;
+    track_db_in_schema(schema_info_db, consts::db::GAME_LOGS, &mut wtxn)?;
+    track_db_in_schema(schema_info_db, consts::db::GAME_LOGS_TS_INDEX, &mut wtxn)?;
+    track_db_in_schema(schema_info_db, consts::db::GAME_LOGS_LEVEL_INDEX, &mut wtxn)?;
+    track_db_in_schema(schema_info_db, consts::db::GAME_LOGS_TEXT_INDEX, &mut wtxn)?;
     wtxn.commit()?;
 
     debug!("verified schema version: {}", current_schema_version);
-    Ok(env)
+    Ok(())
+}
+
+fn stored_schema_version(env: &heed::Env) -> Result<Option<semver::Version>> {
+    let rtxn = env.read_txn()?;
+    let schema_info_db = env.open_database::<Str, Str>(&rtxn, Some(consts::db::SCHEMA_INFO))?;
+    Ok(schema_info_db
+        .and_then(|db| {
+            db.get(&rtxn, consts::schema_info_v1::SCHEMA_VERSION_KEY)
+                .ok()
+                .flatten()
+        })
+        .and_then(|ver| serde_json::de::from_str::<semver::Version>(ver).ok()))
 }
 
 fn drop_dbs(
@@ -256,14 +377,20 @@ pub mod consts {
         pub const USER_STATE: &str = "arkhost_app:user_state";
         pub const USER_CONFIG: &str = "arkhost_app:user_config";
         pub const HTTP_CACHE: &str = "arkhost_app:http_cache";
+        pub const HTTP_CACHE_ACCESS: &str = "arkhost_app:http_cache_access";
         pub const OTA_RELEASE: &str = "arkhost_app:ota_release";
+        pub const GAME_LOGS: &str = "arkhost_app:game_logs";
+        pub const GAME_LOGS_TS_INDEX: &str = "arkhost_app:game_logs_ts_index";
+        pub const GAME_LOGS_LEVEL_INDEX: &str = "arkhost_app:game_logs_level_index";
+        pub const GAME_LOGS_TEXT_INDEX: &str = "arkhost_app:game_logs_text_index";
     }
 
     pub mod schema_info_v1 {
         pub const SCHEMA_VERSION_KEY: &str = "__schema:version";
         pub const DATABASE_INDEX_PREFIX: &str = "__db_index:";
         pub const SELF_DELETE_REQUESTED: &str = "__self_delete_requested";
-        pub static CURRENT_SCHEMA_VERSION: semver::Version = semver::Version::new(1, 1, 0);
+        pub const COMPACTION_REQUESTED: &str = "__compaction_requested";
+        pub static CURRENT_SCHEMA_VERSION: semver::Version = semver::Version::new(1, 3, 0);
     }
 
     pub mod user_state {
@@ -0,0 +1,322 @@
+use super::{cache_manager, db, user_state};
+use crate::app::{app_state::model::UserConfig, asset_worker::image_cache, log_archive};
+use heed::types::{Bytes, SerdeBincode, SerdeJson, Str, Unit};
+use heed::{BytesDecode, CompactionOption};
+use log::{info, warn};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// 可单独清除的数据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceTarget {
+    /// 资源缓存（含图片缓存目录）
+    Cache,
+    /// 设置
+    Config,
+    /// 登录凭据
+    Credentials,
+    /// 日志存档
+    Logs,
+    /// 更新记录
+    Ota,
+}
+
+impl MaintenanceTarget {
+    pub const ALL: [Self; 5] = [
+        Self::Cache,
+        Self::Config,
+        Self::Credentials,
+        Self::Logs,
+        Self::Ota,
+    ];
+
+    /// App 运行中可安全清除的数据，顺序与设置界面中的选项一致
+    pub const IN_APP: [Self; 3] = [Self::Cache, Self::Config, Self::Logs];
+
+    pub fn db_names(&self) -> &'static [&'static str] {
+        match self {
+            Self::Cache => &[
+                db::consts::db::HTTP_CACHE,
+                db::consts::db::HTTP_CACHE_ACCESS,
+            ],
+            Self::Config => &[db::consts::db::USER_CONFIG],
+            Self::Credentials => &[db::consts::db::USER_STATE],
+            Self::Logs => &[
+                db::consts::db::GAME_LOGS,
+                db::consts::db::GAME_LOGS_TS_INDEX,
+                db::consts::db::GAME_LOGS_LEVEL_INDEX,
+                db::consts::db::GAME_LOGS_TEXT_INDEX,
+            ],
+            Self::Ota => &[db::consts::db::OTA_RELEASE],
+        }
+    }
+
+    /// 命令行参数中使用的名称
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cache => "cache",
+            Self::Config => "config",
+            Self::Credentials => "credentials",
+            Self::Logs => "logs",
+            Self::Ota => "ota",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|x| x.name() == name)
+    }
+}
+
+impl fmt::Display for MaintenanceTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Cache => "资源缓存",
+            Self::Config => "设置",
+            Self::Credentials => "登录凭据",
+            Self::Logs => "日志存档",
+            Self::Ota => "更新记录",
+        })
+    }
+}
+
+/// 单个数据库的校验结果
+#[derive(Debug, Default)]
+pub struct DbCheck {
+    pub entries: usize,
+    /// 无法解码的条目的键
+    pub corrupted_keys: Vec<Vec<u8>>,
+}
+
+#[derive(Debug)]
+pub struct DbReport {
+    pub name: &'static str,
+    pub result: heed::Result<DbCheck>,
+}
+
+impl DbReport {
+    pub fn is_ok(&self) -> bool {
+        matches!(&self.result, Ok(check) if check.corrupted_keys.is_empty())
+    }
+}
+
+impl fmt::Display for DbReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.result {
+            Ok(check) if check.corrupted_keys.is_empty() => {
+                write!(f, "{}: {} 条，正常", self.name, check.entries)
+            }
+            Ok(check) => write!(
+                f,
+                "{}: {} 条，其中 {} 条无法解码",
+                self.name,
+                check.entries,
+                check.corrupted_keys.len()
+            ),
+            Err(e) => write!(f, "{}: 读取失败：{e}", self.name),
+        }
+    }
+}
+
+type CheckFn = fn(&heed::Env, &str) -> heed::Result<DbCheck>;
+
+///
+/// 各数据库的校验方式，按存储的类型逐条解码
+///
+/// 新增数据库时需在此注册；更新记录中各键的值类型不同，仅校验键
+///
+fn checks() -> Vec<(&'static str, CheckFn)> {
+    use db::consts::db::*;
+    vec![
+        (SCHEMA_INFO, check_typed::<Str, Str>),
+        (
+            USER_STATE,
+            check_typed::<Str, SerdeBincode<user_state::Store>>,
+        ),
+        (USER_CONFIG, check_typed::<Str, SerdeJson<UserConfig>>),
+        (
+            HTTP_CACHE,
+            check_typed::<Str, SerdeBincode<cache_manager::Store>>,
+        ),
+        (
+            HTTP_CACHE_ACCESS,
+            check_typed::<Str, SerdeBincode<cache_manager::AccessRecord>>,
+        ),
+        (OTA_RELEASE, check_typed::<Str, Bytes>),
+        (
+            GAME_LOGS,
+            check_typed::<Str, SerdeBincode<log_archive::ArchivedLog>>,
+        ),
+        (GAME_LOGS_TS_INDEX, check_typed::<Str, Unit>),
+        (GAME_LOGS_LEVEL_INDEX, check_typed::<Str, Unit>),
+        (GAME_LOGS_TEXT_INDEX, check_typed::<Str, Unit>),
+    ]
+}
+
+fn check_typed<K, D>(env: &heed::Env, name: &str) -> heed::Result<DbCheck>
+where
+    K: for<'a> BytesDecode<'a>,
+    D: for<'a> BytesDecode<'a>,
+{
+    let rtxn = env.read_txn()?;
+    let mut check = DbCheck::default();
+    let Some(db) = env.open_database::<Bytes, Bytes>(&rtxn, Some(name))? else {
+        return Ok(check);
+    };
+    for entry in db.iter(&rtxn)? {
+        let (key, data) = entry?;
+        check.entries += 1;
+        if K::bytes_decode(key).is_err() || D::bytes_decode(data).is_err() {
+            check.corrupted_keys.push(key.to_vec());
+        }
+    }
+    Ok(check)
+}
+
+/// 逐个校验数据库中的条目能否正确解码
+pub fn verify(env: &heed::Env) -> Vec<DbReport> {
+    checks()
+        .into_iter()
+        .map(|(name, check)| DbReport {
+            name,
+            result: check(env, name),
+        })
+        .collect()
+}
+
+/// 删除校验时发现的无法解码的条目，返回删除的数量
+pub fn remove_corrupted(env: &heed::Env, reports: &[DbReport]) -> heed::Result<usize> {
+    let mut removed = 0;
+    let mut wtxn = env.write_txn()?;
+    for report in reports {
+        let Ok(check) = &report.result else {
+            continue;
+        };
+        if check.corrupted_keys.is_empty() {
+            continue;
+        }
+        let Some(db) = env.open_database::<Bytes, Bytes>(&wtxn, Some(report.name))? else {
+            continue;
+        };
+        for key in &check.corrupted_keys {
+            if db.delete(&mut wtxn, key)? {
+                removed += 1;
+            }
+        }
+        warn!(
+            "removed {} corrupted entries from '{}'",
+            check.corrupted_keys.len(),
+            report.name
+        );
+    }
+    wtxn.commit()?;
+    Ok(removed)
+}
+
+/// 清空指定的数据
+pub fn clear(env: &heed::Env, target: MaintenanceTarget) -> anyhow::Result<()> {
+    let mut wtxn = env.write_txn()?;
+    for name in target.db_names() {
+        if let Some(db) = env.open_database::<Bytes, Bytes>(&wtxn, Some(name))? {
+            db.clear(&mut wtxn)?;
+        }
+    }
+    wtxn.commit()?;
+    if target == MaintenanceTarget::Cache {
+        let image_cache_dir = image_cache::cache_dir();
+        if image_cache_dir.exists() {
+            std::fs::remove_dir_all(image_cache_dir)?;
+        }
+    }
+    info!("cleared {}", target.name());
+    Ok(())
+}
+
+///
+/// 将数据库复制压缩后替换原文件，返回压缩前后的大小
+///
+/// 调用前所有进程均需关闭该数据库
+///
+pub fn compact(path: &Path) -> anyhow::Result<(u64, u64)> {
+    let data_file = path.join(consts::DATA_FILE);
+    let compacted_file = path.join(consts::COMPACTED_FILE);
+    let before = std::fs::metadata(&data_file)?.len();
+    if compacted_file.exists() {
+        std::fs::remove_file(&compacted_file)?;
+    }
+    let env = db::open_env(path)?;
+    let copied = env.copy_to_file(&compacted_file, CompactionOption::Enabled);
+    db::close_env(env);
+    drop(copied?);
+
+    std::fs::rename(&compacted_file, &data_file)?;
+    let after = std::fs::metadata(&data_file)?.len();
+    Ok((before, after))
+}
+
+/// 将数据库目录移至一旁（保留以便手动恢复），下次打开时将创建新的数据库；返回移动后的路径
+pub fn reset(path: &Path) -> std::io::Result<PathBuf> {
+    let target = broken_path(path);
+    std::fs::rename(path, &target)?;
+    Ok(target)
+}
+
+/// 使用升级前的最新备份替换数据库，原数据库目录被移至一旁；返回使用的备份路径
+pub fn restore_latest_backup(path: &Path) -> anyhow::Result<PathBuf> {
+    let backup_dir = path
+        .parent()
+        .unwrap_or(path)
+        .join(super::db_migration::consts::BACKUP_DIR);
+    let mut backups = vec![];
+    for entry in std::fs::read_dir(&backup_dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            backups.push((metadata.modified()?, entry.path()));
+        }
+    }
+    let Some((_, backup)) = backups.into_iter().max_by_key(|(modified, _)| *modified) else {
+        anyhow::bail!("no backup found in {}", backup_dir.display());
+    };
+
+    if path.exists() {
+        reset(path)?;
+    }
+    std::fs::create_dir_all(path)?;
+    std::fs::copy(&backup, path.join(consts::DATA_FILE))?;
+    Ok(backup)
+}
+
+fn broken_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        "-broken-{}",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    path.with_file_name(name)
+}
+
+pub mod consts {
+    pub const DATA_FILE: &str = "data.mdb";
+    pub const COMPACTED_FILE: &str = "data.mdb.compact";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_maintenance_targets() {
+        for target in MaintenanceTarget::ALL {
+            assert_eq!(MaintenanceTarget::from_name(target.name()), Some(target));
+        }
+        // 所有被清除的数据库均需可被校验
+        let checked: Vec<&str> = checks().into_iter().map(|(name, _)| name).collect();
+        for target in MaintenanceTarget::ALL {
+            for name in target.db_names() {
+                assert!(checked.contains(name), "{name} is not checked");
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use super::{cache_manager, data_dir::data_dir_create_all, db};
+use heed::{CompactionOption, Result};
+use log::info;
+use std::path::PathBuf;
+
+///
+/// 单个数据库的升级步骤，`version` 为执行该步骤后的 schema 版本。
+///
+/// 所有待执行的步骤在同一个写事务中按版本顺序执行，任一步骤失败时整个升级回滚。
+///
+pub struct Migration {
+    pub version: semver::Version,
+    pub db_name: &'static str,
+    pub description: &'static str,
+    pub migrate: fn(&heed::Env, &mut heed::RwTxn) -> Result<()>,
+}
+
+///
+/// 已注册的升级步骤。
+///
+/// 修改某个数据库的存储格式时，增加 `CURRENT_SCHEMA_VERSION` 并在此注册对应的步骤；
+/// 仅新增数据库或新增带默认值的字段时只需增加版本号，数据会被保留。
+///
+pub fn migrations() -> Vec<Migration> {
+    vec![Migration {
+        version: semver::Version::new(1, 2, 0),
+        db_name: db::consts::db::HTTP_CACHE_ACCESS,
+        description: "build access index for LRU eviction of HTTP cache",
+        migrate: cache_manager::migrate_build_access_index,
+    }]
+}
+
+/// 选出 `(from, to]` 范围内的升级步骤，按版本排序（同版本保持注册顺序）
+pub fn pending_migrations(
+    migrations: Vec<Migration>,
+    from: &semver::Version,
+    to: &semver::Version,
+) -> Vec<Migration> {
+    let mut pending: Vec<Migration> = migrations
+        .into_iter()
+        .filter(|x| &x.version > from && &x.version <= to)
+        .collect();
+    pending.sort_by(|a, b| a.version.cmp(&b.version));
+    pending
+}
+
+pub fn run_migrations(
+    env: &heed::Env,
+    wtxn: &mut heed::RwTxn,
+    from: &semver::Version,
+    to: &semver::Version,
+) -> Result<()> {
+    for migration in pending_migrations(migrations(), from, to) {
+        info!(
+            "run_migrations: migrating '{}' to {}: {}",
+            migration.db_name, migration.version, migration.description
+        );
+        (migration.migrate)(env, wtxn)?;
+    }
+    Ok(())
+}
+
+/// 升级前备份整个 Env，同一版本的旧备份会被覆盖
+pub fn backup_env(env: &heed::Env, from: &semver::Version) -> Result<PathBuf> {
+    let backup_dir = data_dir_create_all().join(consts::BACKUP_DIR);
+    std::fs::create_dir_all(&backup_dir)?;
+    let backup_path = backup_dir.join(format!("data-v{from}.mdb"));
+    if backup_path.exists() {
+        std::fs::remove_file(&backup_path)?;
+    }
+    env.copy_to_file(&backup_path, CompactionOption::Enabled)?;
+    Ok(backup_path)
+}
+
+pub mod consts {
+    pub const BACKUP_DIR: &str = "heed_backup";
+    /// 早于该版本的数据库没有可用的升级步骤，将被清空
+    pub static MIN_MIGRATABLE_SCHEMA_VERSION: semver::Version = semver::Version::new(1, 1, 0);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noop(_: &heed::Env, _: &mut heed::RwTxn) -> Result<()> {
+        Ok(())
+    }
+
+    fn migration(version: semver::Version, description: &'static str) -> Migration {
+        Migration {
+            version,
+            db_name: "test",
+            description,
+            migrate: noop,
+        }
+    }
+
+    #[test]
+    fn test_pending_migrations() {
+        let migrations = vec![
+            migration(semver::Version::new(1, 3, 0), "c"),
+            migration(semver::Version::new(1, 2, 0), "a"),
+            migration(semver::Version::new(1, 1, 0), "old"),
+            migration(semver::Version::new(1, 2, 0), "b"),
+            migration(semver::Version::new(1, 4, 0), "future"),
+        ];
+        let pending = pending_migrations(
+            migrations,
+            &semver::Version::new(1, 1, 0),
+            &semver::Version::new(1, 3, 0),
+        );
+        let descriptions: Vec<_> = pending.iter().map(|x| x.description).collect();
+        assert_eq!(descriptions, vec!["a", "b", "c"]);
+    }
+}
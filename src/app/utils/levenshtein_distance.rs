@@ -32,10 +32,6 @@ impl<T: Hash + Eq> Trie<T> {
         n.data.push(data);
     }
 
-    pub fn clear(&mut self) {
-        self.next.clear();
-    }
-
     pub fn search<'a>(&'a self, max_distance: i32, word: &[char]) -> HashSet<ResultEntry<'a, T>> {
         let mut word = word.to_owned();
         word.insert(0, '$');
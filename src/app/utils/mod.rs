@@ -3,12 +3,16 @@ pub mod app_metadata;
 pub mod app_user_model;
 pub mod cache_control;
 pub mod cache_manager;
+pub mod client_log;
 pub mod data_dir;
 pub mod db;
+pub mod db_maintenance;
+pub mod db_migration;
 pub mod db_store;
 pub mod ext_link;
 pub mod levenshtein_distance;
 pub mod notification;
+pub mod pinyin;
 #[cfg(feature = "desktop-app")]
 pub mod subprocess;
 pub mod time;
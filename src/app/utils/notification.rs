@@ -1,23 +1,201 @@
-use log::warn;
+use log::{debug, warn};
 use notify_rust::Notification;
 use std::time::Duration;
 
 pub fn toast(summary: &str, subtitle: Option<&str>, body: &str, duration: Option<Duration>) {
+    if let Some(notification) = build(summary, subtitle, body, duration) {
+        let res = notification.show();
+        log_on_show_failed(res);
+    }
+}
+
+///
+/// 点击通知时调用 `on_click`
+///
+/// Linux 等平台通过 D-Bus、macOS 通过 NSUserNotification 等待点击，需占用一个线程直至通知被点击或关闭，
+/// 等待中的通知超过 [`consts::MAX_CLICK_WAITERS`] 时不再响应点击；
+/// Windows 通过 Toast 的激活事件响应点击，不占用线程
+///
+pub fn toast_with_click(
+    summary: &str,
+    subtitle: Option<&str>,
+    body: &str,
+    duration: Option<Duration>,
+    on_click: impl FnOnce() + Send + 'static,
+) {
+    let Some(notification) = build(summary, subtitle, body, duration) else {
+        return;
+    };
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let Some(waiter) = ClickWaiter::acquire() else {
+            log_on_show_failed(notification.show());
+            return;
+        };
+        let mut notification = notification;
+        notification.action(consts::DEFAULT_ACTION, "查看");
+        match notification.show() {
+            // 等待至通知被点击或关闭
+            Ok(handle) => {
+                _ = std::thread::spawn(move || {
+                    let _waiter = waiter;
+                    handle.wait_for_action(|action| {
+                        if action == consts::DEFAULT_ACTION {
+                            on_click();
+                        }
+                    });
+                })
+            }
+            Err(e) => log_on_show_failed::<()>(Err(e)),
+        }
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let Some(waiter) = ClickWaiter::acquire() else {
+            log_on_show_failed(notification.show());
+            return;
+        };
+        // 等待至通知被点击或关闭
+        _ = std::thread::spawn(move || {
+            let _waiter = waiter;
+            match mac_notification_sys::Notification::default()
+                .title(&notification.summary)
+                .message(&notification.body)
+                .maybe_subtitle(notification.subtitle.as_deref())
+                .wait_for_click(true)
+                .send()
+            {
+                Ok(mac_notification_sys::NotificationResponse::Click) => on_click(),
+                Ok(_) => {}
+                Err(e) => warn!("error showing notification: {e}"),
+            }
+        });
+    }
+    #[cfg(windows)]
+    {
+        if let Err(e) = windows_toast::show_with_click(&notification, on_click) {
+            warn!("error showing notification with click handler: {e}");
+            log_on_show_failed(notification.show());
+        }
+    }
+}
+
+///
+/// 限制同时等待点击的通知数量，释放时归还
+///
+#[cfg(unix)]
+struct ClickWaiter;
+
+#[cfg(unix)]
+static CLICK_WAITERS: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+#[cfg(unix)]
+impl ClickWaiter {
+    fn acquire() -> Option<Self> {
+        use std::sync::atomic::Ordering;
+        CLICK_WAITERS
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
+                (x < consts::MAX_CLICK_WAITERS).then_some(x + 1)
+            })
+            .ok()
+            .map(|_| Self)
+            .or_else(|| {
+                debug!("too many notifications waiting for click, showing without click handler");
+                None
+            })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for ClickWaiter {
+    fn drop(&mut self) {
+        CLICK_WAITERS.fetch_sub(1, std::sync::atomic::Ordering::AcqRel);
+    }
+}
+
+#[cfg(windows)]
+mod windows_toast {
+    use notify_rust::{Notification, Timeout};
+    use std::sync::Mutex;
+    use windows::{
+        core::HSTRING,
+        Data::Xml::Dom::XmlDocument,
+        Foundation::TypedEventHandler,
+        UI::Notifications::{ToastNotification, ToastNotificationManager},
+    };
+
+    /// 与 notify-rust 相同，未注册 AppUserModelID 时借用 PowerShell 的 ID 显示通知
+    const POWERSHELL_APP_ID: &str =
+        "{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\\WindowsPowerShell\\v1.0\\powershell.exe";
+    /// notify-rust 中超过该时长的通知显示为长通知
+    const LONG_DURATION_MS: u32 = 25000;
+
+    pub fn show_with_click(
+        notification: &Notification,
+        on_click: impl FnOnce() + Send + 'static,
+    ) -> windows::core::Result<()> {
+        let duration = match notification.timeout {
+            Timeout::Milliseconds(t) if t >= LONG_DURATION_MS => "long",
+            Timeout::Never => "long",
+            _ => "short",
+        };
+        let xml = XmlDocument::new()?;
+        xml.LoadXml(&HSTRING::from(format!(
+            r#"<toast duration="{duration}"><visual><binding template="ToastGeneric"><text>{}</text><text>{}</text><text>{}</text></binding></visual></toast>"#,
+            escape(&notification.summary),
+            escape(notification.subtitle.as_deref().unwrap_or_default()),
+            escape(&notification.body),
+        )))?;
+
+        let toast = ToastNotification::CreateToastNotification(&xml)?;
+        let on_click = Mutex::new(Some(on_click));
+        toast.Activated(&TypedEventHandler::new(move |_, _| {
+            if let Some(on_click) = on_click.lock().unwrap().take() {
+                on_click();
+            }
+            Ok(())
+        }))?;
+        ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(POWERSHELL_APP_ID))?
+            .Show(&toast)
+    }
+
+    fn escape(text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+            .replace('\'', "&apos;")
+    }
+}
+
+fn build(
+    summary: &str,
+    subtitle: Option<&str>,
+    body: &str,
+    duration: Option<Duration>,
+) -> Option<Notification> {
+    let app_config = crate::app::app_config::get();
+    if !app_config.notification_enabled.value {
+        debug!("notification disabled: {summary}: {body}");
+        return None;
+    }
+
     let mut notification = Notification::new();
     notification
         .appname(consts::APP_NAME)
         .summary(summary)
         .body(body)
-        .timeout(duration.unwrap_or(Duration::from_millis(consts::DEFAULT_TIMEOUT_MS)));
+        .timeout(duration.unwrap_or(Duration::from_millis(
+            app_config.notification_timeout_ms.value,
+        )));
     // TODO: 安装快捷方式到开始屏幕并自定义AppUserModelID
     // #[cfg(target_os = "windows")]
     // notification.app_id(app_user_model::consts::DEFAULT_ID);
     if let Some(subtitle) = subtitle {
         notification.subtitle(subtitle);
     }
-
-    let res = notification.show();
-    log_on_show_failed(res);
+    Some(notification)
 }
 
 fn log_on_show_failed<T>(result: Result<T, notify_rust::error::Error>) {
@@ -29,4 +207,8 @@ fn log_on_show_failed<T>(result: Result<T, notify_rust::error::Error>) {
 pub mod consts {
     pub const APP_NAME: &str = "Closure Studio";
     pub const DEFAULT_TIMEOUT_MS: u64 = 4000;
+    /// 点击通知本身时的操作
+    pub const DEFAULT_ACTION: &str = "default";
+    /// 同时等待点击的通知数量上限，每个等待中的通知占用一个线程
+    pub const MAX_CLICK_WAITERS: usize = 4;
 }
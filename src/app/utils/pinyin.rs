@@ -0,0 +1,47 @@
+///
+/// CJK 统一表意文字（U+4E00..=U+9FFF）的拼音首字母，每个字符对应一个字母，`_` 表示未收录
+///
+/// 由 Perl `Unicode::Collate::CJK::Pinyin` 的排序表生成，多音字取排序最靠前的读音
+///
+const INITIALS: &str = include_str!("pinyin_initials.txt");
+
+/// 单个汉字的拼音首字母（小写）
+pub fn initial(ch: char) -> Option<char> {
+    let index = (ch as u32).checked_sub(consts::CJK_START)? as usize;
+    match INITIALS.as_bytes().get(index) {
+        Some(b'_') | None => None,
+        Some(x) => Some(*x as char),
+    }
+}
+
+/// 文本的拼音首字母，英文字母及数字转为小写保留，其余字符忽略
+pub fn initials(text: &str) -> String {
+    text.chars()
+        .filter_map(|ch| {
+            if ch.is_ascii_alphanumeric() {
+                Some(ch.to_ascii_lowercase())
+            } else {
+                initial(ch)
+            }
+        })
+        .collect()
+}
+
+pub mod consts {
+    pub const CJK_START: u32 = 0x4E00;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initials() {
+        assert_eq!(INITIALS.len(), 0x9FFF - 0x4E00 + 1);
+        assert_eq!(initials("固源岩"), "gyy");
+        assert_eq!(initials("酮凝集组"), "tnjz");
+        assert_eq!(initials("LS-5 战术演习"), "ls5zsyx");
+        assert_eq!(initial('a'), None);
+        assert_eq!(initial('\u{A000}'), None);
+    }
+}
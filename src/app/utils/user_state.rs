@@ -9,7 +9,7 @@ pub struct UserStateDBStore {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Store {
+pub(crate) struct Store {
     jwt: String,
 }
 
@@ -47,6 +47,11 @@ impl UserStateDBStore {
         wtxn.commit()
     }
 
+    /// 写入登录凭据，写入失败时返回错误（[`UserState::set_login_state`] 仅记录日志）
+    pub fn save_login_state(jwt: String) -> heed::Result<()> {
+        Self { jwt: Some(jwt) }.save_to_db()
+    }
+
     fn db() -> heed::Result<heed::Database<heed::types::Str, heed::types::SerdeBincode<Store>>> {
         db::database(Some(db::consts::db::USER_STATE))
     }
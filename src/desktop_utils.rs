@@ -63,6 +63,20 @@ pub fn show_crash_window(exit_status: &str, error_info: &str) {
         _ = SetConsoleTextAttribute(hconsole, FOREGROUND_RED);
     }
 
+    let bundle_info = match app::diagnostics::create_bundle(
+        Some(&app::diagnostics::CrashInfo {
+            exit_status,
+            error_info,
+        }),
+        None,
+    ) {
+        Ok(path) => format!("诊断信息包：{}", path.display()),
+        Err(e) => {
+            error!("show_crash_window: error creating diagnostics bundle: {e:?}");
+            format!("生成诊断信息包失败：{e}")
+        }
+    };
+
     println!(
         "\n********************************************************************************\n"
     );
@@ -74,13 +88,16 @@ pub fn show_crash_window(exit_status: &str, error_info: &str) {
             "- SHA256\t: {}\n",
             "- ExitStatus\t: {}\n",
             "\n",
-            "如果发生反复崩溃无法使用、功能异常等问题，\n请截图控制台输出，反馈至可露希尔QQ群或QQ频道“PRTS接入 - APP讨论”板块。\n",
+            "{}\n",
+            "\n",
+            "如果发生反复崩溃无法使用、功能异常等问题，\n请将诊断信息包（或控制台截图）反馈至可露希尔QQ群或QQ频道“PRTS接入 - APP讨论”板块。\n",
             "重新启动客户端前，请关闭该控制台窗口……\n"
         ),
         error_info,
         app::utils::app_metadata::CARGO_PKG_VERSION.unwrap_or("not found"),
         app::utils::app_metadata::executable_sha256().map_or("unable to hash".into(), |x| hex::encode(*x)),
-        exit_status
+        exit_status,
+        bundle_info
     );
     println!(
         "\n********************************************************************************\n"
@@ -117,9 +134,6 @@ pub fn on_duplicated_instance() {
 }
 
 pub async fn update_client_if_exist() -> anyhow::Result<()> {
-    use sha2::Digest;
-    use tokio::io::AsyncBufReadExt;
-
     let pending_update = app::ota::pending_update()
         .map_err(|e| {
             error!("update_client_if_exist: error reading pending update record from DB: {e}");
@@ -150,23 +164,14 @@ pub async fn update_client_if_exist() -> anyhow::Result<()> {
         return Ok(());
     }
 
-    {
-        let release_file = tokio::fs::File::open(file_path).await?;
-        let mut reader = tokio::io::BufReader::new(release_file);
-        let mut hasher = sha2::Sha256::new();
-        let mut buf;
-        while {
-            buf = reader.fill_buf().await?;
-            !buf.is_empty()
-        } {
-            hasher.update(buf);
-            let len = buf.len();
-            reader.consume(len);
-        }
+    if file_sha256(file_path).await?[..] != pending_update.binary.sha256[..] {
+        anyhow::bail!("更新未完整下载或校验错误，请重试");
+    }
 
-        if hasher.finalize()[..] != pending_update.binary.sha256[..] {
-            anyhow::bail!("更新未完整下载或校验错误，请重试");
-        }
+    // 没有备份时无法回滚，因此备份失败时不进行更新
+    if let Err(e) = backup_current_executable().await {
+        error!("update_client_if_exist: unable to backup current executable: {e}");
+        anyhow::bail!("无法备份当前客户端程序文件，已取消更新，请稍后重试\n错误：{e}");
     }
 
     if let Err(e) = self_replace::self_replace(file_path) {
@@ -178,11 +183,208 @@ pub async fn update_client_if_exist() -> anyhow::Result<()> {
 
     _ = tokio::fs::remove_file(file_path).await;
     _ = app::ota::remove_pending_update();
+    _ = app::ota::start_probation(pending_update.version.clone(), pending_update.binary.sha256)
+        .map_err(|e| error!("update_client_if_exist: error creating probation record in DB: {e}"));
     app::utils::notification::toast("可露希尔客户端更新成功！", None, "", None);
     Ok(())
 }
 
+///
+/// 启动时打开数据库，失败时在控制台中提供修复选项，直至成功打开或用户选择退出
+///
+/// 返回 `false` 表示用户选择退出
+///
+pub fn open_db_or_recover() -> bool {
+    use app::utils::{db, db_maintenance};
+
+    loop {
+        let err = match db::try_init() {
+            Ok(()) => return true,
+            Err(e) => e,
+        };
+        error!("open_db_or_recover: {err}");
+        show_console(true);
+        let path = db::env_path();
+        println!(
+            concat!(
+                "\n{}\n数据库路径：{}\n\n",
+                "请选择修复方式（输入序号后回车）：\n",
+                "1. 重试（请先关闭所有应用实例）\n",
+                "2. 压缩数据库\n",
+                "3. 校验并删除无法解码的数据\n",
+                "4. 清除部分数据\n",
+                "5. 使用升级前的备份恢复\n",
+                "6. 重置数据库（原数据库目录将被保留）\n",
+                "0. 退出\n"
+            ),
+            err,
+            path.display()
+        );
+
+        // 无法读取输入（如没有控制台）时退出
+        let Some(choice) = read_console_line() else {
+            return false;
+        };
+        let result = match choice.as_str() {
+            "1" => Ok(()),
+            "2" => db_maintenance::compact(&path).map(|(before, after)| {
+                println!(
+                    "已压缩数据库：{} -> {}",
+                    humansize::format_size(before, humansize::DECIMAL),
+                    humansize::format_size(after, humansize::DECIMAL)
+                )
+            }),
+            "3" => with_raw_env(&path, |env| {
+                let reports = db_maintenance::verify(env);
+                for report in &reports {
+                    println!("{report}");
+                }
+                let removed = db_maintenance::remove_corrupted(env, &reports)?;
+                println!("已删除 {removed} 条无法解码的数据");
+                Ok(())
+            }),
+            "4" => {
+                for (i, target) in db_maintenance::MaintenanceTarget::ALL.iter().enumerate() {
+                    println!("{}. {target}", i + 1);
+                }
+                println!("请选择要清除的数据：");
+                let target = read_console_line()
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .and_then(|i| i.checked_sub(1))
+                    .and_then(|i| db_maintenance::MaintenanceTarget::ALL.get(i).copied());
+                match target {
+                    Some(target) => with_raw_env(&path, |env| {
+                        db_maintenance::clear(env, target)?;
+                        println!("已清除{target}");
+                        Ok(())
+                    }),
+                    None => Ok(()),
+                }
+            }
+            "5" => db_maintenance::restore_latest_backup(&path)
+                .map(|backup| println!("已使用备份恢复：{}", backup.display())),
+            "6" => db_maintenance::reset(&path)
+                .map(|moved| println!("已重置数据库，原数据库目录：{}", moved.display()))
+                .map_err(anyhow::Error::from),
+            "0" => return false,
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            error!("open_db_or_recover: recovery failed: {e:?}");
+            println!("修复失败：{e}");
+        }
+    }
+}
+
+/// 不校验格式打开数据库并执行操作，结束后关闭数据库
+fn with_raw_env(
+    path: &std::path::Path,
+    f: impl FnOnce(&heed::Env) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let env = app::utils::db::open_env(path)?;
+    let result = f(&env);
+    app::utils::db::close_env(env);
+    result
+}
+
+fn read_console_line() -> Option<String> {
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_owned()),
+    }
+}
+
+/// 主窗口非正常退出时调用。
+///
+/// 若当前版本仍在更新后的试用期内，且非正常退出次数达到 `threshold`，则使用更新前的备份替换当前客户端程序，
+/// 返回回滚后的版本号。
+pub async fn rollback_client_if_unstable(
+    threshold: u32,
+) -> anyhow::Result<Option<semver::Version>> {
+    let probation = match app::ota::record_abnormal_exit()? {
+        Some(probation) => probation,
+        None => return Ok(None),
+    };
+    warn!(
+        "rollback_client_if_unstable: version {} exited abnormally during probation ({}/{threshold})",
+        &probation.version, probation.abnormal_exits
+    );
+    if probation.abnormal_exits < threshold {
+        return Ok(None);
+    }
+
+    let backup = match app::ota::backup()? {
+        Some(backup) => backup,
+        None => anyhow::bail!("未找到更新前的客户端备份"),
+    };
+    let file_path = match &backup.binary.blob {
+        app::ota::Blob::File(file_path) => file_path,
+        #[allow(unused)]
+        _ => anyhow::bail!("不支持备份数据类型，请提交Bug"),
+    };
+    if file_sha256(file_path).await?[..] != backup.binary.sha256[..] {
+        anyhow::bail!("客户端备份校验错误\n备份路径：{}", file_path.display());
+    }
+
+    if let Err(e) = self_replace::self_replace(file_path) {
+        anyhow::bail!(format!(
+            "无法使用备份替换当前客户端程序文件，请手动使用备份覆盖当前客户端\n备份路径：{}\n错误：{e}",
+            file_path.display()
+        ));
+    }
+
+    info!(
+        "rollback_client_if_unstable: rolled back from {} to {}",
+        &probation.version, &backup.version
+    );
+    app::ota::mark_probation_rolled_back()?;
+    // 保留备份文件，直到下一次更新覆盖
+    Ok(Some(backup.version))
+}
+
+async fn backup_current_executable() -> anyhow::Result<()> {
+    let version = semver::Version::parse(
+        app::utils::app_metadata::CARGO_PKG_VERSION
+            .ok_or(anyhow::anyhow!("no version found in APP metadata"))?,
+    )?;
+    let sha256 = app::utils::app_metadata::executable_sha256()?;
+    let backup_path = app::utils::data_dir::data_dir_create_all()
+        .join(arkhost_ota::consts::BACKUP_EXECUTABLE_NAME);
+    tokio::fs::copy(std::env::current_exe()?, &backup_path).await?;
+    app::ota::upsert_backup(&app::ota::ReleaseRecord {
+        rel_type: app::ota::RecordType::Backup,
+        version,
+        binary: app::ota::Resource {
+            blob: app::ota::Blob::File(backup_path),
+            sha256: (*sha256).into(),
+        },
+    })?;
+    Ok(())
+}
+
+async fn file_sha256(path: &std::path::Path) -> std::io::Result<digest::Output<sha2::Sha256>> {
+    use sha2::Digest;
+    use tokio::io::AsyncBufReadExt;
+
+    let file = tokio::fs::File::open(path).await?;
+    let mut reader = tokio::io::BufReader::new(file);
+    let mut hasher = sha2::Sha256::new();
+    let mut buf;
+    while {
+        buf = reader.fill_buf().await?;
+        !buf.is_empty()
+    } {
+        hasher.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+
+    Ok(hasher.finalize())
+}
+
 #[allow(unused)]
 pub mod consts {
     pub const WINDOWS_TITLE: &str = "Closure Studio";
+    pub const SINGLE_INSTANCE_KEY: &str = "arkhost-ui-slint-single-instance";
 }
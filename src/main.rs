@@ -7,7 +7,7 @@ mod app;
 #[cfg(feature = "desktop-app")]
 mod desktop_utils;
 
-use app::program_options::{LaunchAppWindowArgs, LaunchArgs, LaunchSpec};
+use app::program_options::{ConfigCommand, DbCommand, LaunchAppWindowArgs, LaunchArgs, LaunchSpec};
 #[cfg(feature = "desktop-app")]
 use app::utils::subprocess::spawn_executable;
 #[cfg(feature = "desktop-app")]
@@ -22,7 +22,29 @@ async fn launch_app_window(_launch_args: &LaunchAppWindowArgs) -> Result<(), sli
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let launch_args: LaunchArgs = argh::from_env();
-    env_logger::init();
+    if let Some(ref data_dir) = launch_args.data_dir {
+        let data_dir = std::path::absolute(data_dir).unwrap_or(data_dir.into());
+        std::env::set_var(app::env::consts::DATA_DIR, data_dir);
+    }
+    if let Some(ref config) = launch_args.config {
+        let config = std::path::absolute(config).unwrap_or(config.into());
+        std::env::set_var(app::env::consts::CONFIG, config);
+    }
+    let app_config = app::app_config::init(&launch_args);
+    // 命令行工具仅输出到 stderr
+    let log_file = match &launch_args.launch_spec {
+        None => Some(app::utils::client_log::consts::BOOTSTRAP_LOG_FILE),
+        Some(LaunchSpec::AppWindow(_)) => Some(app::utils::client_log::consts::APP_LOG_FILE),
+        Some(LaunchSpec::WebView(_)) => Some(app::utils::client_log::consts::WEBVIEW_LOG_FILE),
+        Some(_) => None,
+    };
+    app::utils::client_log::init(app_config.log_level.value.as_deref(), log_file);
+    if let Some(ref e) = app_config.file_error {
+        log::warn!(
+            "error loading config file {}: {e}",
+            app_config.path.display()
+        );
+    }
     let _cleanup_guard = app::utils::db::CleanupGuard::new();
 
     match &launch_args.launch_spec {
@@ -31,8 +53,7 @@ async fn main() -> anyhow::Result<()> {
             #[cfg(feature = "desktop-app")]
             let _instance = {
                 let instance: single_instance::SingleInstance =
-                    single_instance::SingleInstance::new("arkhost-ui-slint-single-instance")
-                        .unwrap();
+                    single_instance::SingleInstance::new(consts::SINGLE_INSTANCE_KEY).unwrap();
                 if !instance.is_single() {
                     on_duplicated_instance();
                     anyhow::bail!("duplicated instance");
@@ -54,13 +75,22 @@ async fn main() -> anyhow::Result<()> {
                 app::utils::app_metadata::CARGO_PKG_VERSION.unwrap_or("not found")
             );
 
+            info!("data dir: {}", app::utils::data_dir::data_dir().display());
             app::utils::db::handle_self_delete(true);
 
             #[cfg(feature = "desktop-app")]
             {
+                if !open_db_or_recover() {
+                    anyhow::bail!("failed to open DB");
+                }
+
                 let current_exe = std::env::current_exe().unwrap_or_default();
 
-                let mut env = vec![];
+                // 数据目录（含便携模式）统一在此确定后传递给子进程
+                let mut env = vec![(
+                    app::env::consts::DATA_DIR.into(),
+                    app::utils::data_dir::data_dir().into_os_string(),
+                )];
                 if let Some(true) = launch_args.attach_console {
                     env.push((app::env::consts::ATTACH_CONSOLE.into(), "1".into()));
                 }
@@ -76,10 +106,19 @@ async fn main() -> anyhow::Result<()> {
                 if let Some(ref user_token) = launch_args.user_token {
                     env.push((app::env::consts::USER_TOKEN.into(), user_token.into()))
                 }
-                // 设置 App Window env_logger
-                if std::env::var("RUST_LOG").is_err() {
-                    env.push(("RUST_LOG".into(), "info,closure_studio=debug".into()));
+                if let Some(ref proxy) = launch_args.proxy {
+                    env.push((app::env::consts::PROXY.into(), proxy.into()))
                 }
+                // 设置 App Window env_logger
+                env.push((
+                    app::app_config::consts::RUST_LOG.into(),
+                    app_config
+                        .log_level
+                        .value
+                        .as_deref()
+                        .unwrap_or("info,closure_studio=debug")
+                        .into(),
+                ));
 
                 let mut app_window = spawn_executable(
                     current_exe.as_os_str(),
@@ -94,11 +133,24 @@ async fn main() -> anyhow::Result<()> {
                 info!("\n### AppWindow process exited with status '{exit_status}' ###\n");
 
                 if exit_status.success() {
+                    _ = app::ota::end_probation()
+                        .map_err(|e| log::error!("error removing probation record from DB: {e}"));
                     if let Err(e) = update_client_if_exist().await {
                         show_crash_window(&format!("{exit_status}"), &format!("更新失败\n{e}"));
                     }
                 } else {
-                    show_crash_window(&format!("{exit_status}"), "主窗口非正常退出");
+                    let rollback_threshold = launch_args
+                        .ota_rollback_threshold
+                        .or(app::env::ota_rollback_threshold())
+                        .unwrap_or(app::ota::consts::DEFAULT_ROLLBACK_THRESHOLD);
+                    let error_info = match rollback_client_if_unstable(rollback_threshold).await {
+                        Ok(None) => "主窗口非正常退出".to_owned(),
+                        Ok(Some(version)) => format!(
+                            "主窗口非正常退出\n新版本客户端多次非正常退出，已自动回滚至版本 {version}，请重新启动客户端"
+                        ),
+                        Err(e) => format!("主窗口非正常退出\n自动回滚失败\n{e}"),
+                    };
+                    show_crash_window(&format!("{exit_status}"), &error_info);
                 }
             }
         }
@@ -120,6 +172,94 @@ async fn main() -> anyhow::Result<()> {
             #[cfg(feature = "desktop-app")]
             app::webview::auth::subprocess_webview::launch(launch_args)?;
         }
+        Some(LaunchSpec::Export(export_args)) => {
+            let path = app::data_bundle::export_to_file(
+                export_args.out.as_ref().map(std::path::Path::new),
+                export_args.include_token,
+            )?;
+            println!("Exported to {}", path.display());
+        }
+        Some(LaunchSpec::Import(import_args)) => {
+            // 导入会写入数据库，需在 App 关闭时进行
+            #[cfg(feature = "desktop-app")]
+            let _instance = {
+                let instance = single_instance::SingleInstance::new(consts::SINGLE_INSTANCE_KEY)?;
+                if !instance.is_single() {
+                    anyhow::bail!("App is running, please close it first");
+                }
+                instance
+            };
+            let summary =
+                app::data_bundle::import_from_file(std::path::Path::new(&import_args.path))?;
+            println!("{summary}");
+        }
+        Some(LaunchSpec::Config(config_args)) => match &config_args.command {
+            ConfigCommand::Dump(dump_args) if dump_args.path => {
+                println!("{}", app_config.path.display())
+            }
+            ConfigCommand::Dump(_) => print!("{app_config}"),
+        },
+        Some(LaunchSpec::Db(db_args)) => {
+            use app::utils::{db, db_maintenance};
+            // 除只读校验外，均需在 App 关闭时进行
+            #[cfg(feature = "desktop-app")]
+            let _instance = match &db_args.command {
+                DbCommand::Verify(verify_args) if !verify_args.repair => None,
+                _ => {
+                    let instance =
+                        single_instance::SingleInstance::new(consts::SINGLE_INSTANCE_KEY)?;
+                    if !instance.is_single() {
+                        anyhow::bail!("App is running, please close it first");
+                    }
+                    Some(instance)
+                }
+            };
+
+            let path = db::env_path();
+            match &db_args.command {
+                DbCommand::Verify(verify_args) => {
+                    let env = db::open_env(&path)?;
+                    let reports = db_maintenance::verify(&env);
+                    for report in &reports {
+                        println!("{report}");
+                    }
+                    let result = if verify_args.repair {
+                        db_maintenance::remove_corrupted(&env, &reports)
+                            .map(|removed| println!("Removed {removed} corrupted entries"))
+                    } else {
+                        Ok(())
+                    };
+                    db::close_env(env);
+                    result?;
+                }
+                DbCommand::Compact(_) => {
+                    let (before, after) = db_maintenance::compact(&path)?;
+                    println!(
+                        "Compacted {}: {} -> {}",
+                        path.display(),
+                        humansize::format_size(before, humansize::DECIMAL),
+                        humansize::format_size(after, humansize::DECIMAL)
+                    );
+                }
+                DbCommand::Clear(clear_args) => {
+                    let target = db_maintenance::MaintenanceTarget::from_name(&clear_args.target)
+                        .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "unknown target '{}', expected one of: {}",
+                            clear_args.target,
+                            db_maintenance::MaintenanceTarget::ALL
+                                .map(|x| x.name())
+                                .join(", ")
+                        )
+                    })?;
+                    let env = db::open_env(&path)?;
+                    let result = db_maintenance::clear(&env, target);
+                    db::close_env(env);
+                    result?;
+                    println!("Cleared {}", target.name());
+                }
+            }
+        }
     }
 
     Ok(())